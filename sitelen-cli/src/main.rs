@@ -1,10 +1,15 @@
 /// CLI application for Sitelen Sitelen renderer
 
+#[allow(clippy::useless_attribute, clippy::empty_line_after_doc_comments)]
 use clap::{Parser, ValueEnum};
-use sitelen_core::{OutputFormat, Pipeline, RenderConfig, Sentence};
+use sitelen_core::search::DEFAULT_MAX_NODES;
+use sitelen_core::{
+    Ast, InputMode, LayoutSampling, LayoutWeights, Lexicon, NameDetection, NumberSystem,
+    OutputFormat, Pipeline, RenderConfig, SearchLimits, Sentence, Severity, SyllableMode,
+};
 use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::PathBuf;
-use std::io::{self, Write, Read, IsTerminal};
 
 #[derive(Parser)]
 #[command(name = "sitelen")]
@@ -22,10 +27,40 @@ struct Cli {
     #[arg(short, long, value_enum, default_value = "svg")]
     format: Format,
 
-    /// Optimal aspect ratio (height/width)
+    /// Optimal aspect ratio (width/height)
     #[arg(long, default_value_t = 0.8)]
     ratio: f64,
 
+    /// Smallest ratio (width/height) a compound may have; the nearest layouts are drawn
+    /// when none fits
+    #[arg(long, default_value_t = RenderConfig::default().min_ratio)]
+    min_ratio: f64,
+
+    /// Largest ratio (width/height) a compound may have
+    #[arg(long, default_value_t = RenderConfig::default().max_ratio)]
+    max_ratio: f64,
+
+    /// Draw every compound at its own size instead of stretching it to the widest one
+    #[arg(long)]
+    ignore_height: bool,
+
+    /// Pick the layout of every compound at random among those within the ratio bounds
+    #[arg(long)]
+    random: bool,
+
+    /// Seed of --random and --samples; the same text and seed draw the same layouts
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Render N different layouts of the whole input, drawn at random with the better
+    /// scored ones more often, to separate files (or one after another on stdout)
+    #[arg(long, value_name = "N", conflicts_with_all = ["from_ast", "emit_ast", "document", "lint", "rank"])]
+    samples: Option<usize>,
+
+    /// How often --samples draws worse scored layouts; 0 draws the best ones in order
+    #[arg(long, default_value_t = LayoutSampling::default().temperature)]
+    temperature: f64,
+
     /// Stroke width
     #[arg(long, default_value_t = 2.0)]
     stroke_width: f64,
@@ -34,10 +69,108 @@ struct Cli {
     #[arg(long)]
     shadow: bool,
 
+    /// Draw a shared frame around words joined by en or anu
+    #[arg(long)]
+    coordination_frame: bool,
+
     /// Don't embed glyph definitions in SVG (output will only contain references). By default, glyphs are embedded.
     #[arg(long = "no-embed-glyphs", action = clap::ArgAction::SetTrue)]
     no_embed_glyphs: bool,
 
+    /// Additional words (TOML or JSON lexicon file) on top of the built-in lexicon
+    #[arg(long)]
+    lexicon: Option<PathBuf>,
+
+    /// Render illegal words as placeholders and report them on stderr instead of failing
+    #[arg(long)]
+    lenient: bool,
+
+    /// How proper names are split into cartouche syllables
+    #[arg(long, value_enum, default_value = "strict")]
+    syllables: Syllables,
+
+    /// How proper names are recognised
+    #[arg(long, value_enum, default_value = "capitalized")]
+    names: Names,
+
+    /// Counting system used to draw number phrases as compact blocks
+    #[arg(long, value_enum, default_value = "off")]
+    numbers: Numbers,
+
+    /// Read the input as structure markup such as "(mi) li[moku] e(kili)."
+    #[arg(long, conflicts_with = "from_ast")]
+    markup: bool,
+
+    /// Render the input as one page: blank lines separate paragraphs and stanzas,
+    /// and lines starting with "# " are headings
+    #[arg(long, conflicts_with_all = ["from_ast", "emit_ast"])]
+    document: bool,
+
+    /// Width of a document page, in glyphs
+    #[arg(long, default_value_t = 10.0)]
+    page_width: f64,
+
+    /// Height of a document page, in glyphs; text flows into --columns columns and
+    /// continues on new pages below
+    #[arg(long)]
+    page_height: Option<f64>,
+
+    /// Columns of a document page with a --page-height
+    #[arg(long, default_value_t = 1, requires = "page_height")]
+    columns: usize,
+
+    /// Choose the layouts of a document's compounds with the line breaks, so that rows
+    /// are filled evenly, and justify paragraphs
+    #[arg(long)]
+    fill_lines: bool,
+
+    /// Lay out long sentences with a beam search keeping this many partial layouts,
+    /// instead of trying every arrangement
+    #[arg(long)]
+    beam_width: Option<usize>,
+
+    /// Placements tried per compound before the layout search is finished greedily
+    #[arg(long, default_value_t = DEFAULT_MAX_NODES)]
+    layout_budget: usize,
+
+    /// Weight of the distance from the optimal ratio when choosing a layout
+    #[arg(long, default_value_t = LayoutWeights::default().ratio)]
+    weight_ratio: f64,
+
+    /// Weight of the surface per glyph, which grows as glyphs are drawn smaller
+    #[arg(long, default_value_t = 0.0)]
+    weight_surface: f64,
+
+    /// Weight of tiny glyphs drawn next to huge ones
+    #[arg(long, default_value_t = 0.0)]
+    weight_contrast: f64,
+
+    /// Weight of containers whose units differ in size
+    #[arg(long, default_value_t = 0.0)]
+    weight_balance: f64,
+
+    /// Weight of a predicate drawn beside its subject instead of below it
+    #[arg(long, default_value_t = 0.0)]
+    weight_subject_order: f64,
+
+    /// List the best N layouts of every compound with the score of every criterion
+    /// instead of rendering
+    #[arg(long, value_name = "N", conflicts_with_all = ["from_ast", "emit_ast", "document", "lint"])]
+    rank: Option<usize>,
+
+    /// Check the input for common mistakes instead of rendering it; exits with status 1
+    /// when an error is found
+    #[arg(long, conflicts_with_all = ["from_ast", "emit_ast", "document"])]
+    lint: bool,
+
+    /// Write the parsed sentences as a JSON AST instead of rendering them
+    #[arg(long)]
+    emit_ast: bool,
+
+    /// Read the input as a JSON AST instead of Toki Pona text
+    #[arg(long)]
+    from_ast: bool,
+
     /// Input text directly (alternative to --input)
     text: Option<String>,
 }
@@ -49,6 +182,70 @@ enum Format {
     Html,
 }
 
+#[derive(Clone, ValueEnum)]
+enum Syllables {
+    /// Split names as written and report letters that are not Toki Pona
+    Strict,
+    /// Map foreign letters to the closest Toki Pona syllables
+    Lenient,
+}
+
+impl From<Syllables> for SyllableMode {
+    fn from(s: Syllables) -> Self {
+        match s {
+            Syllables::Strict => SyllableMode::Strict,
+            Syllables::Lenient => SyllableMode::Lenient,
+        }
+    }
+}
+
+#[derive(Clone, ValueEnum)]
+enum Names {
+    /// Capitalised words and names marked up as {Pona}
+    Capitalized,
+    /// Only names marked up as {Pona}
+    Explicit,
+}
+
+impl From<Names> for NameDetection {
+    fn from(n: Names) -> Self {
+        match n {
+            Names::Capitalized => NameDetection::Capitalized,
+            Names::Explicit => NameDetection::Explicit,
+        }
+    }
+}
+
+#[derive(Clone, ValueEnum)]
+enum Numbers {
+    /// Number words are ordinary words
+    Off,
+    /// wan and tu are added up (tu wan is three)
+    Pu,
+    /// nasin nanpa pona: wan 1, tu 2, luka 5, mute 20 and ale 100
+    Pona,
+}
+
+impl From<Numbers> for NumberSystem {
+    fn from(n: Numbers) -> Self {
+        match n {
+            Numbers::Off => NumberSystem::Off,
+            Numbers::Pu => NumberSystem::Pu,
+            Numbers::Pona => NumberSystem::Pona,
+        }
+    }
+}
+
+impl Format {
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Svg => "svg",
+            Format::Png => "png",
+            Format::Html => "html",
+        }
+    }
+}
+
 impl From<Format> for OutputFormat {
     fn from(f: Format) -> Self {
         match f {
@@ -59,6 +256,7 @@ impl From<Format> for OutputFormat {
     }
 }
 
+#[allow(clippy::field_reassign_with_default)]
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
@@ -80,31 +278,171 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     } else if let Some(text) = &cli.text {
         text.clone()
     } else {
-        eprintln!("Error: No input provided. Pipe text via stdin, or use --input <file> or --text.");
+        eprintln!(
+            "Error: No input provided. Pipe text via stdin, or use --input <file> or --text."
+        );
         std::process::exit(1);
     };
 
     // Create configuration
     let mut config = RenderConfig::default();
     config.optimal_ratio = cli.ratio;
+    config.min_ratio = cli.min_ratio;
+    config.max_ratio = cli.max_ratio;
+    config.ignore_height = cli.ignore_height;
+    config.random = cli.random;
+    config.seed = cli.seed;
     config.stroke_width = cli.stroke_width;
     config.shadow = cli.shadow;
     config.exportable = !cli.no_embed_glyphs;
+    config.coordination_frame = cli.coordination_frame;
+    config.page_width = cli.page_width;
+    config.page_height = cli.page_height;
+    config.columns = cli.columns;
+    config.fill_lines = cli.fill_lines;
+    config.layout_weights = LayoutWeights {
+        ratio: cli.weight_ratio,
+        surface: cli.weight_surface,
+        contrast: cli.weight_contrast,
+        balance: cli.weight_balance,
+        subject_order: cli.weight_subject_order,
+    };
+
+    // Load additional words, if any
+    let mut lexicon = Lexicon::default();
+    if let Some(path) = &cli.lexicon {
+        lexicon.extend(Lexicon::from_file(path)?);
+    }
 
     // Create pipeline
-    let pipeline = Pipeline::with_config(config)?;
+    let mut pipeline = Pipeline::with_lexicon(config, lexicon)?;
+    pipeline.set_syllabifier(SyllableMode::from(cli.syllables.clone()));
+    pipeline.set_name_detection(NameDetection::from(cli.names.clone()));
+    pipeline.set_number_system(NumberSystem::from(cli.numbers.clone()));
+    pipeline.set_search_limits(SearchLimits {
+        beam_width: cli.beam_width,
+        max_nodes: cli.layout_budget,
+    });
+    if cli.markup {
+        pipeline.set_input_mode(InputMode::Markup);
+    }
 
-    // Parse into sentences
-    let sentences: Vec<Sentence> = pipeline.parse(&text)?;
+    // Lint issues are listed instead of rendering
+    if cli.lint {
+        let (_, diagnostics) = pipeline.parse_with_diagnostics(&text);
+        for diagnostic in &diagnostics {
+            println!("error: {}", diagnostic);
+        }
+        let issues = pipeline.lint(&text);
+        for issue in &issues {
+            println!("{}", issue);
+        }
+        if !diagnostics.is_empty() || issues.iter().any(|issue| issue.severity == Severity::Error) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Layouts are ranked instead of rendering
+    if let Some(count) = cli.rank {
+        for (index, options) in pipeline.compound_options(&text)?.iter().enumerate() {
+            let ranking = pipeline.rank_layouts(options, count);
+            if let Some(best) = ranking.first() {
+                println!("compound {}: {}", index + 1, best.option.to_text());
+            }
+            for (rank, ranked) in ranking.iter().enumerate() {
+                let criteria = &ranked.criteria;
+                println!(
+                    "  {}. score {:.3}  {:.2}x{:.2}  ratio {:.3}  surface {:.3}  contrast {:.3}  balance {:.3}  subject order {:.0}",
+                    rank + 1,
+                    ranked.score,
+                    ranked.option.size.width,
+                    ranked.option.size.height,
+                    criteria.ratio,
+                    criteria.surface,
+                    criteria.contrast,
+                    criteria.balance,
+                    criteria.subject_order,
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    // Sampled layouts are rendered like separate sentences
+    if let Some(count) = cli.samples {
+        let sampling = LayoutSampling {
+            count,
+            seed: cli.seed,
+            temperature: cli.temperature,
+        };
+        let renders = pipeline
+            .sample_layouts(&text, &sampling)?
+            .iter()
+            .map(|layout| pipeline.render(layout, cli.format.clone().into()))
+            .collect::<Result<Vec<_>, _>>()?;
+        return write_outputs(&renders, &cli.output, cli.format.extension(), "sample");
+    }
+
+    // Whole documents are rendered to a single page
+    if cli.document {
+        let document = if cli.lenient {
+            let (document, diagnostics) = pipeline.parse_document_with_diagnostics(&text);
+            for diagnostic in &diagnostics {
+                eprintln!("Warning: {}", diagnostic);
+            }
+            document
+        } else {
+            pipeline.parse_document(&text)?
+        };
+        let bytes = pipeline.render_document(&document, cli.format.clone().into())?;
+        match &cli.output {
+            Some(path) => {
+                fs::write(path, bytes)?;
+                eprintln!("Rendered to: {}", path.display());
+            }
+            None => {
+                let mut stdout = io::stdout();
+                stdout.write_all(&bytes)?;
+                stdout.flush()?;
+            }
+        }
+        return Ok(());
+    }
+
+    // Parse into sentences, or read them from a JSON AST
+    let sentences: Vec<Sentence> = if cli.from_ast {
+        Ast::from_json(&text)?.sentences
+    } else if cli.lenient {
+        let (sentences, diagnostics) = pipeline.parse_with_diagnostics(&text);
+        for diagnostic in &diagnostics {
+            eprintln!("Warning: {}", diagnostic);
+        }
+        sentences
+    } else {
+        pipeline.parse(&text)?
+    };
+
+    if cli.emit_ast {
+        let json = Ast::new(sentences).to_json();
+        match &cli.output {
+            Some(path) => {
+                fs::write(path, json)?;
+                eprintln!("Wrote AST to: {}", path.display());
+            }
+            None => {
+                let mut stdout = io::stdout();
+                writeln!(stdout, "{}", json)?;
+                stdout.flush()?;
+            }
+        }
+        return Ok(());
+    }
 
     let format: OutputFormat = cli.format.clone().into();
 
     // Determine output naming
-    let ext = match cli.format {
-        Format::Svg => "svg",
-        Format::Png => "png",
-        Format::Html => "html",
-    };
+    let ext = cli.format.extension();
 
     // If only one sentence, keep previous behavior (single output file)
     if sentences.len() <= 1 {
@@ -120,7 +458,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             stdout.write_all(&bytes)?;
             stdout.flush()?;
         } else {
-            let output_path = cli.output.unwrap_or_else(|| PathBuf::from(format!("output.{}", ext)));
+            let output_path = cli
+                .output
+                .unwrap_or_else(|| PathBuf::from(format!("output.{}", ext)));
             fs::write(&output_path, bytes)?;
             eprintln!("Rendered to: {}", output_path.display());
         }
@@ -128,38 +468,57 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Multiple sentences -> write multiple files with index suffix
-    let (base_dir, base_stem) = match &cli.output {
+    #[allow(clippy::clone_on_copy)]
+    let renders = sentences
+        .iter()
+        .map(|sentence| pipeline.render_sentence(sentence, format.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+    write_outputs(&renders, &cli.output, ext, "sentence")
+}
+
+/// Write renders to files numbered from 1 next to `output`, or one after another to stdout
+fn write_outputs(
+    renders: &[Vec<u8>],
+    output: &Option<PathBuf>,
+    ext: &str,
+    what: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (base_dir, base_stem) = match output {
         Some(path) => {
             if path.is_dir() {
                 (path.clone(), String::from("output"))
             } else {
-                let dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| PathBuf::from("."));
-                let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("output").to_string();
+                let dir = path
+                    .parent()
+                    .map(|p| p.to_path_buf())
+                    .unwrap_or_else(|| PathBuf::from("."));
+                let stem = path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("output")
+                    .to_string();
                 (dir, stem)
             }
         }
         None => (PathBuf::from("."), String::from("output")),
     };
 
-    if cli.output.is_none() {
+    if output.is_none() {
         // Stream all rendered outputs to stdout (no extra messages on stdout)
         let mut stdout = io::stdout();
-        for sentence in &sentences {
-            let bytes = pipeline.render_sentence(sentence, format.clone())?;
-            stdout.write_all(&bytes)?;
+        for bytes in renders {
+            stdout.write_all(bytes)?;
         }
         stdout.flush()?;
     } else {
-        for (idx, sentence) in sentences.iter().enumerate() {
-            let bytes = pipeline.render_sentence(sentence, format.clone())?;
+        for (idx, bytes) in renders.iter().enumerate() {
             let filename = format!("{}_{}.{}", base_stem, idx + 1, ext);
             let mut out_path = base_dir.clone();
             out_path.push(filename);
-            fs::write(&out_path, &bytes)?;
-            eprintln!("Rendered sentence {} to: {}", idx + 1, out_path.display());
+            fs::write(&out_path, bytes)?;
+            eprintln!("Rendered {} {} to: {}", what, idx + 1, out_path.display());
         }
     }
 
     Ok(())
 }
-
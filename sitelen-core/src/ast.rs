@@ -0,0 +1,151 @@
+//! JSON import and export of parsed sentences
+//!
+//! Parse trees can be saved, corrected by hand and rendered again without going
+//! through the text parser. The JSON document wraps the sentences in a versioned
+//! envelope:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "sentences": [
+//!     {
+//!       "parts": [
+//!         { "type": "subject", "tokens": [{ "text": "mi" }] },
+//!         { "type": "object_marker", "separator": "li", "tokens": [{ "text": "moku" }] },
+//!         { "type": "punctuation", "tokens": [{ "text": "period", "kind": "punctuation" }] }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Schema version 1:
+//!
+//! - A sentence has `parts` and a `span`.
+//! - A part has a `type`, which is one of `subject`, `object_marker`,
+//!   `prep_phrase`, `address`, `preverb`, `coordination`, `negation`,
+//!   `ala_question`, `interjection`, `number`, `quotation` or `punctuation`. The other fields of
+//!   a part are those of the matching [`SentencePart`] variant:
+//!   - `tokens`
+//!   - `separator`, which is the container glyph, such as `li`, `e`, `pi`,
+//!     `cartouche` or `quote`; an `object_marker` with separator `o` is a command
+//!     and an `address` is the vocative `o`
+//!   - `parts`, which holds nested parts, or the conjuncts of a coordination
+//!     (its `tokens` are the `en` and `anu` between them)
+//!   - `value`, for numbers
+//!   - `sentences`, for quotations
+//!   - `span`
+//! - A token has its `text`, a `span` and a `kind`. The kind is one of `word`,
+//!   `name`, `syllable`, `quote` or `punctuation`.
+//! - A span is `{ "start": 0, "end": 2 }`, in byte offsets into the parsed text.
+//!
+//! Spans, token kinds, empty `tokens` and absent `separator`/`parts` may be left
+//! out when writing a document by hand. Token kinds default to `word`. The layout
+//! only reads token texts and part structure.
+//!
+//! [`SentencePart`]: crate::types::SentencePart
+
+use crate::types::Sentence;
+use serde::{Deserialize, Serialize};
+
+/// Schema version written by [`Ast::to_json`] and accepted by [`Ast::from_json`]
+pub const AST_VERSION: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AstError {
+    #[error("JSON error: {0}")]
+    Json(String),
+    #[error("Unsupported AST version {0} (expected {AST_VERSION})")]
+    UnsupportedVersion(u32),
+}
+
+/// Versioned document holding parsed sentences
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ast {
+    pub version: u32,
+    pub sentences: Vec<Sentence>,
+}
+
+impl Ast {
+    /// Wrap sentences in a document of the current schema version
+    pub fn new(sentences: Vec<Sentence>) -> Self {
+        Self {
+            version: AST_VERSION,
+            sentences,
+        }
+    }
+
+    /// Serialise the document as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("AST serialises to JSON")
+    }
+
+    /// Read a document, rejecting schema versions this library does not understand
+    pub fn from_json(json: &str) -> Result<Self, AstError> {
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+
+        // Check the version first, so an unknown schema is not reported as a shape error
+        let Version { version } =
+            serde_json::from_str(json).map_err(|e| AstError::Json(e.to_string()))?;
+        if version != AST_VERSION {
+            return Err(AstError::UnsupportedVersion(version));
+        }
+        serde_json::from_str(json).map_err(|e| AstError::Json(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::numbers::NumberSystem;
+    use crate::parser::Parser;
+    use crate::types::{SentencePart, TokenKind};
+
+    #[test]
+    fn test_round_trip() {
+        let mut parser = Parser::new();
+        parser.set_number_system(NumberSystem::Pona);
+        let text = "jan Ana li wile ala moku e kili luka tu lon tomo pi telo nasa. \
+                    ona li toki e ni: \"mi pona!\"";
+        let ast = Ast::new(parser.parse(text).unwrap());
+
+        let json = ast.to_json();
+        assert!(json.contains("\"version\": 1"));
+        assert!(json.contains("\"type\": \"preverb\""));
+        assert_eq!(Ast::from_json(&json).unwrap(), ast);
+    }
+
+    #[test]
+    fn test_hand_written_document() {
+        let json = r#"{
+            "version": 1,
+            "sentences": [{
+                "parts": [
+                    { "type": "subject", "tokens": [{ "text": "mi" }] },
+                    { "type": "object_marker", "separator": "li", "tokens": [{ "text": "moku" }] }
+                ]
+            }]
+        }"#;
+        let ast = Ast::from_json(json).unwrap();
+        let SentencePart::ObjectMarker {
+            separator, tokens, ..
+        } = &ast.sentences[0].parts[1]
+        else {
+            panic!("expected an object marker");
+        };
+        assert_eq!(separator, "li");
+        assert_eq!(tokens[0].kind, TokenKind::Word);
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        assert!(matches!(
+            Ast::from_json(r#"{ "version": 2, "sentences": [] }"#),
+            Err(AstError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(Ast::from_json("{}"), Err(AstError::Json(_))));
+    }
+}
@@ -1,4 +1,5 @@
 /// Configuration for rendering
+use crate::scoring::LayoutWeights;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,9 +10,10 @@ pub struct RenderConfig {
     pub scale_skew: f64,
     /// Optimal aspect ratio
     pub optimal_ratio: f64,
-    /// Minimum allowed ratio
+    /// Minimum ratio (width / height) of a compound; when no layout fits the bounds,
+    /// the nearest layouts are used
     pub min_ratio: f64,
-    /// Maximum allowed ratio
+    /// Maximum ratio (width / height) of a compound
     pub max_ratio: f64,
     /// Stroke width
     pub stroke_width: f64,
@@ -19,10 +21,44 @@ pub struct RenderConfig {
     pub shadow: bool,
     /// Whether output should be exportable (include glyph definitions)
     pub exportable: bool,
-    /// Whether to ignore height constraints
+    /// Whether to draw every compound at its own size, instead of stretching it to the
+    /// width of the widest compound, which lets a short compound grow tall
     pub ignore_height: bool,
-    /// Whether to use random layout selection
+    /// Whether to pick a layout of every compound at random, among those within the
+    /// ratio bounds, instead of the best scored one
     pub random: bool,
+    /// Seed of random layout selection; the same text and seed draw the same layouts
+    #[serde(default)]
+    pub seed: u64,
+    /// Whether to draw a shared frame around coordinated words (`mi en sina`)
+    #[serde(default)]
+    pub coordination_frame: bool,
+    /// Width of a document page, in glyphs
+    #[serde(default = "default_page_width")]
+    pub page_width: f64,
+    /// Height of a document page, in glyphs; when set, text flows into `columns`
+    /// columns and continues on new pages below
+    #[serde(default)]
+    pub page_height: Option<f64>,
+    /// Columns of a page with a height
+    #[serde(default = "default_columns")]
+    pub columns: usize,
+    /// Whether to choose the layouts of the compounds of a document together with the
+    /// line breaks, so that rows are filled evenly, instead of the best layout of every
+    /// compound
+    #[serde(default)]
+    pub fill_lines: bool,
+    /// Weights of the criteria that choose the layout of a compound
+    #[serde(default)]
+    pub layout_weights: LayoutWeights,
+}
+
+fn default_page_width() -> f64 {
+    10.0
+}
+
+fn default_columns() -> usize {
+    1
 }
 
 impl Default for RenderConfig {
@@ -38,6 +74,13 @@ impl Default for RenderConfig {
             exportable: true,
             ignore_height: false,
             random: false,
+            seed: 0,
+            coordination_frame: false,
+            page_width: default_page_width(),
+            page_height: None,
+            columns: default_columns(),
+            fill_lines: false,
+            layout_weights: LayoutWeights::default(),
         }
     }
 }
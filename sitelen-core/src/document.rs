@@ -0,0 +1,193 @@
+//! Documents of headings, paragraphs and stanzas
+//!
+//! Whole texts such as books and poems are parsed into a [`Document`] with
+//! `Parser::parse_document`, which reads these conventions from plain text:
+//!
+//! ```text
+//! # toki pona
+//!
+//! mi jan pona. sina jan pona.
+//!
+//! kon li tawa
+//! telo li tawa
+//! ```
+//!
+//! Blocks are separated by blank lines. A line starting with one to six `#` and a
+//! space is a heading of that level. A block on a single line is a paragraph, whose
+//! sentences flow across the page; a block of several lines is a stanza, where
+//! every line break is kept.
+
+use crate::types::{Sentence, Span};
+use serde::{Deserialize, Serialize};
+
+/// Parsed text of several blocks
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Document {
+    pub blocks: Vec<Block>,
+}
+
+impl Document {
+    /// Every sentence of the document, in reading order
+    pub fn sentences(&self) -> impl Iterator<Item = &Sentence> {
+        self.blocks
+            .iter()
+            .flat_map(|block| block.lines().into_iter().flatten())
+    }
+}
+
+/// Heading, paragraph or stanza
+///
+/// Serialised with a `type` field holding the snake case variant name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    /// `# toki pona`; the level is the number of `#`
+    Heading {
+        level: usize,
+        sentences: Vec<Sentence>,
+        #[serde(default)]
+        span: Span,
+    },
+    /// Sentences that flow across the page
+    Paragraph {
+        sentences: Vec<Sentence>,
+        #[serde(default)]
+        span: Span,
+    },
+    /// Lines of a poem, each starting on a new row
+    Stanza {
+        lines: Vec<Vec<Sentence>>,
+        #[serde(default)]
+        span: Span,
+    },
+}
+
+impl Block {
+    /// Source range of the block, from its first line to its last
+    pub fn span(&self) -> Span {
+        match self {
+            Block::Heading { span, .. }
+            | Block::Paragraph { span, .. }
+            | Block::Stanza { span, .. } => *span,
+        }
+    }
+
+    /// Sentences of the block by line; headings and paragraphs are a single line
+    pub fn lines(&self) -> Vec<&[Sentence]> {
+        match self {
+            Block::Heading { sentences, .. } | Block::Paragraph { sentences, .. } => {
+                vec![sentences]
+            }
+            Block::Stanza { lines, .. } => lines.iter().map(Vec::as_slice).collect(),
+        }
+    }
+}
+
+/// Kind of a block found in the source text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BlockKind {
+    Heading(usize),
+    Paragraph,
+    Stanza,
+}
+
+/// Block of source text, with the byte range of each of its lines
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SourceBlock {
+    pub kind: BlockKind,
+    pub lines: Vec<Span>,
+}
+
+/// Split text into blocks at blank lines and headings
+///
+/// Line ranges exclude the line break and, for headings, the leading `#` marks.
+pub(crate) fn split_blocks(text: &str) -> Vec<SourceBlock> {
+    let mut blocks = Vec::new();
+    let mut lines = Vec::new();
+
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let span = Span::new(start, start + content.len());
+        start += line.len();
+
+        if content.trim().is_empty() {
+            close_block(&mut blocks, &mut lines);
+        } else if let Some((level, offset)) = heading(content) {
+            close_block(&mut blocks, &mut lines);
+            blocks.push(SourceBlock {
+                kind: BlockKind::Heading(level),
+                lines: vec![Span::new(span.start + offset, span.end)],
+            });
+        } else {
+            lines.push(span);
+        }
+    }
+    close_block(&mut blocks, &mut lines);
+
+    blocks
+}
+
+/// Level of a heading line and the offset of its text
+fn heading(line: &str) -> Option<(usize, usize)> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    let rest = &trimmed[level..];
+    if !(1..=6).contains(&level) || !rest.starts_with([' ', '\t']) || rest.trim().is_empty() {
+        return None;
+    }
+    Some((level, line.len() - rest.trim_start().len()))
+}
+
+fn close_block(blocks: &mut Vec<SourceBlock>, lines: &mut Vec<Span>) {
+    let kind = match lines.len() {
+        0 => return,
+        1 => BlockKind::Paragraph,
+        _ => BlockKind::Stanza,
+    };
+    blocks.push(SourceBlock {
+        kind,
+        lines: std::mem::take(lines),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_blocks() {
+        let text =
+            "# toki pona\n\nmi pona. sina pona.\n\n\nkon li tawa\r\ntelo li tawa\n## lipu\nmi#";
+        let blocks = split_blocks(text);
+        let found: Vec<(BlockKind, Vec<&str>)> = blocks
+            .iter()
+            .map(|block| {
+                let lines = block
+                    .lines
+                    .iter()
+                    .map(|span| &text[span.start..span.end])
+                    .collect();
+                (block.kind, lines)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (BlockKind::Heading(1), vec!["toki pona"]),
+                (BlockKind::Paragraph, vec!["mi pona. sina pona."]),
+                (BlockKind::Stanza, vec!["kon li tawa", "telo li tawa"]),
+                (BlockKind::Heading(2), vec!["lipu"]),
+                (BlockKind::Paragraph, vec!["mi#"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_banners_are_not_headings() {
+        for line in ["#", "#toki", "####### toki", "# "] {
+            assert_eq!(heading(line), None, "{line:?}");
+        }
+        assert_eq!(heading("  ### toki"), Some((3, 6)));
+    }
+}
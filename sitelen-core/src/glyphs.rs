@@ -1,3 +1,4 @@
+use crate::lexicon::{Lexicon, WordRole};
 use roxmltree::Document;
 /// Glyph loading and management
 use std::collections::HashSet;
@@ -152,6 +153,25 @@ impl GlyphRegistry {
         format!("tp-wg-{}", word)
     }
 
+    /// Get word glyph ID, honouring glyph overrides from the lexicon
+    pub fn lexicon_glyph_id(lexicon: &Lexicon, word: &str) -> String {
+        lexicon
+            .glyph(word)
+            .map(str::to_string)
+            .unwrap_or_else(|| Self::word_glyph_id(word))
+    }
+
+    /// Get the words of a lexicon whose glyph is missing from the sprite
+    pub fn missing_glyphs(&self, lexicon: &Lexicon) -> Vec<String> {
+        let ids = self.get_all_symbol_ids();
+        lexicon
+            .words()
+            .filter(|word| lexicon.role(word) != Some(WordRole::Punctuation))
+            .filter(|word| !ids.contains(&Self::lexicon_glyph_id(lexicon, word)))
+            .map(str::to_string)
+            .collect()
+    }
+
     /// Get syllable glyph ID
     pub fn syllable_glyph_id(syllable: &str) -> String {
         format!("tp-syl-{}", syllable)
@@ -192,7 +212,7 @@ pub fn init_glyph_registry(sprite_content: &str) -> Result<(), GlyphError> {
         // Already initialized, return success
         return Ok(());
     }
-    
+
     let mut registry = GlyphRegistry::new();
     registry.load_sprite(sprite_content)?;
     GLYPH_REGISTRY
@@ -0,0 +1,436 @@
+//! Incremental parsing for live editing
+//!
+//! Live editors change a few characters at a time. `Parser::parse_incremental`
+//! keeps the text with its sentences in a [`ParsedText`], and `Parser::apply_edit`
+//! applies a [`TextEdit`] to it, reparsing only the sentences the edit touches. The
+//! returned [`SentenceSplice`] tells which sentences were replaced, so everything
+//! derived from the other sentences, such as their layouts, can be kept.
+//!
+//! Sentences after the edit keep their structure and only have their spans moved.
+//! An edit that adds or removes a quote mark can change where sentences end far
+//! away from it, so it reparses the whole text, as does every edit to markup input.
+
+use crate::parser::{self, Diagnostic};
+use crate::types::{LayoutOption, Sentence, SentencePart, Span};
+use serde::Serialize;
+use std::ops::Range;
+
+/// Replacement of a byte range of the text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    /// Bytes to replace; an empty range inserts
+    pub range: Span,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Span, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+
+    /// Insert text at a byte offset
+    pub fn insert(offset: usize, text: impl Into<String>) -> Self {
+        Self::new(Span::new(offset, offset), text)
+    }
+
+    /// Delete a byte range
+    pub fn delete(range: Span) -> Self {
+        Self::new(range, "")
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum EditError {
+    #[error("Edit range {start}..{end} is outside the text or splits a character")]
+    InvalidRange { start: usize, end: usize },
+}
+
+/// Sentences replaced by an edit
+///
+/// The `removed` sentences from `start` were replaced by `inserted` new ones; sentences
+/// before `start` are unchanged and the ones after only moved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct SentenceSplice {
+    pub start: usize,
+    pub removed: usize,
+    pub inserted: usize,
+}
+
+impl SentenceSplice {
+    /// Indices of the new sentences
+    pub fn changed(&self) -> Range<usize> {
+        self.start..self.start + self.inserted
+    }
+
+    /// Replace the values kept for the removed sentences by the values for the new ones
+    pub fn apply<T>(&self, values: &mut Vec<T>, inserted: impl IntoIterator<Item = T>) {
+        values.splice(self.start..self.start + self.removed, inserted);
+    }
+}
+
+/// Text kept together with its sentences and the diagnostics found in them
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParsedText {
+    text: String,
+    sentences: Vec<Sentence>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ParsedText {
+    pub(crate) fn new(text: &str, sentences: Vec<Sentence>, diagnostics: Vec<Diagnostic>) -> Self {
+        Self {
+            text: text.to_string(),
+            sentences,
+            diagnostics,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn sentences(&self) -> &[Sentence] {
+        &self.sentences
+    }
+
+    /// Illegal words and syllables, in sentence order, as from `Parser::parse_with_diagnostics`
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Apply an edit, reparsing the touched sentences with `parse`
+    ///
+    /// `parse` parses a byte range of the edited text leniently, numbering sentences from
+    /// zero. With `full` the whole text is reparsed.
+    pub(crate) fn apply(
+        &mut self,
+        edit: &TextEdit,
+        full: bool,
+        parse: impl Fn(&str, usize, usize) -> (Vec<Sentence>, Vec<Diagnostic>),
+    ) -> Result<SentenceSplice, EditError> {
+        let Span { start, end } = edit.range;
+        if start > end
+            || end > self.text.len()
+            || !self.text.is_char_boundary(start)
+            || !self.text.is_char_boundary(end)
+        {
+            return Err(EditError::InvalidRange { start, end });
+        }
+
+        let full = full
+            || self.text[start..end]
+                .chars()
+                .chain(edit.text.chars())
+                .any(parser::is_quote_mark);
+        let delta = edit.text.len() as isize - (end - start) as isize;
+        let mut text = self.text.clone();
+        text.replace_range(start..end, &edit.text);
+
+        // Sentences touching the edit, including sentences that merely end where it starts
+        let count = self.sentences.len();
+        let (mut first, mut last) = if full {
+            (0, count)
+        } else {
+            let first = self
+                .sentences
+                .iter()
+                .position(|sentence| sentence.span.end >= start)
+                .unwrap_or(count);
+            let last = self
+                .sentences
+                .iter()
+                .rposition(|sentence| sentence.span.start <= end)
+                .map_or(0, |index| index + 1);
+            (first, last.max(first))
+        };
+
+        // A sentence without a terminator runs on into the text after it
+        while first > 0 {
+            let span = self.sentences[first - 1].span;
+            if parser::ends_sentence(&self.text, span.start, span.end) {
+                break;
+            }
+            first -= 1;
+        }
+
+        loop {
+            let region_start = match first {
+                0 => 0,
+                _ => self.sentences[first - 1].span.end,
+            };
+            let old_end = match self.sentences.get(last) {
+                Some(sentence) => sentence.span.start,
+                None => self.text.len(),
+            };
+            let region_end = old_end.wrapping_add_signed(delta);
+
+            // The reparsed text must end a sentence, or the next one would continue it
+            if last < count && !parser::ends_sentence(&text, region_start, region_end) {
+                last += 1;
+                continue;
+            }
+
+            let (sentences, diagnostics) = parse(&text, region_start, region_end);
+            let splice = SentenceSplice {
+                start: first,
+                removed: last - first,
+                inserted: sentences.len(),
+            };
+            self.splice(splice, sentences, diagnostics, delta);
+            self.text = text;
+            return Ok(splice);
+        }
+    }
+
+    fn splice(
+        &mut self,
+        splice: SentenceSplice,
+        sentences: Vec<Sentence>,
+        diagnostics: Vec<Diagnostic>,
+        delta: isize,
+    ) {
+        let after = splice.start + splice.removed;
+        for sentence in &mut self.sentences[after..] {
+            shift_sentence(sentence, delta);
+        }
+        splice.apply(&mut self.sentences, sentences);
+
+        let kept = std::mem::take(&mut self.diagnostics);
+        let (before, rest): (Vec<_>, Vec<_>) = kept
+            .into_iter()
+            .partition(|diagnostic| diagnostic.sentence < splice.start);
+        let mut merged = before;
+        merged.extend(diagnostics.into_iter().map(|diagnostic| Diagnostic {
+            sentence: diagnostic.sentence + splice.start,
+            ..diagnostic
+        }));
+        merged.extend(
+            rest.into_iter()
+                .filter(|diagnostic| diagnostic.sentence >= after)
+                .map(|diagnostic| Diagnostic {
+                    sentence: diagnostic.sentence - splice.removed + splice.inserted,
+                    span: shift(diagnostic.span, delta),
+                    ..diagnostic
+                }),
+        );
+        self.diagnostics = merged;
+    }
+}
+
+/// Layouts kept for every sentence of a text being edited live
+///
+/// Created with `Pipeline::live_text`; `Pipeline::edit_live_text` lays out only the
+/// sentences an edit replaced.
+#[derive(Debug, Clone, Default)]
+pub struct LiveText {
+    pub(crate) parsed: ParsedText,
+    /// Compounds of every sentence
+    pub(crate) layouts: Vec<Vec<LayoutOption>>,
+}
+
+impl LiveText {
+    pub fn parsed(&self) -> &ParsedText {
+        &self.parsed
+    }
+
+    pub fn text(&self) -> &str {
+        self.parsed.text()
+    }
+
+    /// Compounds of every sentence, in reading order
+    pub fn compounds(&self) -> impl Iterator<Item = &LayoutOption> {
+        self.layouts.iter().flatten()
+    }
+}
+
+fn shift(span: Span, delta: isize) -> Span {
+    Span::new(
+        span.start.wrapping_add_signed(delta),
+        span.end.wrapping_add_signed(delta),
+    )
+}
+
+/// Move every span of a sentence by `delta` bytes
+fn shift_sentence(sentence: &mut Sentence, delta: isize) {
+    sentence.span = shift(sentence.span, delta);
+    for part in &mut sentence.parts {
+        shift_part(part, delta);
+    }
+}
+
+fn shift_part(part: &mut SentencePart, delta: isize) {
+    let (tokens, span, parts) = match part {
+        SentencePart::Subject {
+            tokens,
+            span,
+            parts,
+            ..
+        }
+        | SentencePart::ObjectMarker {
+            tokens,
+            span,
+            parts,
+            ..
+        }
+        | SentencePart::PrepPhrase {
+            tokens,
+            span,
+            parts,
+            ..
+        }
+        | SentencePart::Address {
+            tokens,
+            span,
+            parts,
+            ..
+        } => (tokens, span, parts.as_deref_mut()),
+        SentencePart::Preverb {
+            tokens,
+            parts,
+            span,
+        }
+        | SentencePart::Coordination {
+            tokens,
+            parts,
+            span,
+        } => (tokens, span, Some(parts.as_mut_slice())),
+        SentencePart::Negation { tokens, span }
+        | SentencePart::AlaQuestion { tokens, span }
+        | SentencePart::Interjection { tokens, span }
+        | SentencePart::Number { tokens, span, .. }
+        | SentencePart::Punctuation { tokens, span } => (tokens, span, None),
+        SentencePart::Quotation { sentences, span } => {
+            *span = shift(*span, delta);
+            for sentence in sentences {
+                shift_sentence(sentence, delta);
+            }
+            return;
+        }
+    };
+    *span = shift(*span, delta);
+    for token in tokens {
+        token.span = shift(token.span, delta);
+    }
+    for part in parts.into_iter().flatten() {
+        shift_part(part, delta);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{InputMode, Parser};
+    use crate::prepositions::PrepositionUse;
+    use std::collections::HashMap;
+
+    /// Apply an edit and check the result against parsing the edited text from scratch
+    fn edit(
+        parser: &Parser,
+        text: &str,
+        range: (usize, usize),
+        replacement: &str,
+    ) -> SentenceSplice {
+        let mut parsed = parser.parse_incremental(text);
+        let edit = TextEdit::new(Span::new(range.0, range.1), replacement);
+        let splice = parser.apply_edit(&mut parsed, &edit).unwrap();
+
+        let mut edited = text.to_string();
+        edited.replace_range(range.0..range.1, replacement);
+        assert_eq!(parsed, parser.parse_incremental(&edited), "{edited:?}");
+        splice
+    }
+
+    fn splice(start: usize, removed: usize, inserted: usize) -> SentenceSplice {
+        SentenceSplice {
+            start,
+            removed,
+            inserted,
+        }
+    }
+
+    #[test]
+    fn test_edits_match_full_parse() {
+        let parser = Parser::new();
+        let text = "mi moku. sina pona! jan li toki e ni: \"toki. pona.\" ona li lape";
+        let cases = [
+            // Word added to the second sentence
+            ((14, 14), " mute", splice(1, 1, 1)),
+            // Terminator removed, merging two sentences
+            ((7, 8), "", splice(0, 2, 1)),
+            // Terminator added, splitting a sentence
+            ((3, 3), ".", splice(0, 1, 2)),
+            // Edits at the start and the end of the text
+            ((0, 2), "sina", splice(0, 1, 1)),
+            ((text.len(), text.len()), " kin.", splice(2, 1, 1)),
+            // Text added right after a sentence, which is reparsed too
+            ((8, 8), " a!", splice(0, 1, 2)),
+            // Replacement across sentences
+            ((4, 17), "tawa. mi", splice(0, 2, 2)),
+        ];
+        for (range, replacement, expected) in cases {
+            assert_eq!(
+                edit(&parser, text, range, replacement),
+                expected,
+                "{replacement:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unterminated_sentence_continues_after_edit() {
+        let parser = Parser::new();
+        // The edit falls after the last sentence, which it extends
+        assert_eq!(
+            edit(&parser, "mi pona. sina ", (14, 14), "moku"),
+            splice(1, 1, 1)
+        );
+        // A terminator turns the rest of the text into a sentence of its own
+        assert_eq!(
+            edit(&parser, "mi pona sina moku.", (7, 7), "."),
+            splice(0, 1, 2)
+        );
+    }
+
+    #[test]
+    fn test_quote_marks_reparse_the_whole_text() {
+        let parser = Parser::new();
+        let text = "mi toki e ni: toki. sina pona. mi lape.";
+        assert_eq!(edit(&parser, text, (14, 14), "\""), splice(0, 3, 1));
+        assert_eq!(edit(&parser, text, (14, 14), "toki"), splice(0, 1, 1));
+
+        let mut parser = Parser::new();
+        parser.set_input_mode(InputMode::Markup);
+        edit(&parser, "(mi) li[moku]. (sina) li[pona].", (1, 3), "jan");
+    }
+
+    #[test]
+    fn test_moved_sentences_keep_diagnostics() {
+        let parser = Parser::new();
+        let text = "mi moku. sina xyz. ona li qqq.";
+        edit(&parser, text, (3, 7), "lape ala");
+        edit(&parser, text, (9, 13), "jan");
+        edit(&parser, text, (7, 8), "");
+
+        let mut parsed = parser.parse_incremental(text);
+        let invalid = TextEdit::new(Span::new(5, 40), "");
+        assert_eq!(
+            parser.apply_edit(&mut parsed, &invalid),
+            Err(EditError::InvalidRange { start: 5, end: 40 })
+        );
+    }
+
+    #[test]
+    fn test_preposition_overrides_after_edit() {
+        // Overrides stay at their byte offsets, so an edit before one can change the
+        // word it applies to
+        let mut parser = Parser::new();
+        parser.set_preposition_overrides(HashMap::from([(13, PrepositionUse::Preposition)]));
+        let text = "mi moku. jan poka mi li pona.";
+        assert_eq!(edit(&parser, text, (3, 7), "lape ala"), splice(0, 2, 2));
+        edit(&parser, text, (0, 2), "");
+        edit(&parser, text, (24, 28), "suli");
+    }
+}
@@ -10,9 +10,15 @@
 /// 5. Normalizes and deduplicates options based on their structure
 ///
 /// The algorithm uses a recursive backtracking approach, exploring all valid
-/// placement combinations while respecting size compatibility constraints.
+/// placement combinations while respecting size compatibility constraints. The
+/// [`SearchLimits`] of the engine bound that work for long sentences.
+use crate::lexicon::{Lexicon, SizeClass};
+use crate::linebreak::{self, Candidate};
+use crate::search::{Budget, LayoutScorer, SearchLimits, WeightedScorer};
 use crate::types::*;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 // Constants
 const INITIAL_MIN_SURFACE: f64 = 1_000_000.0;
@@ -20,13 +26,63 @@ const EPSILON: f64 = 1e-6;
 const MAX_SURFACE_RATIO: f64 = 2.0;
 const NORMALIZATION_MIN_SIZE: f64 = 1.0;
 
+/// Separator of the container that holds the conjuncts of a coordination
+///
+/// The renderer draws it as a shared frame when `RenderConfig::coordination_frame`
+/// is set, and as a plain container otherwise.
+pub const COORDINATION_SEPARATOR: &str = "coordination";
+
+/// Separator of the container that holds the sentences of a quotation
+///
+/// It has no glyph: the renderer draws it as a plain container. It marks where the
+/// quotation starts and ends, so that `Layout::to_text` can put the quote marks back.
+pub const QUOTATION_SEPARATOR: &str = "quotation";
+
+/// Separator of the container that holds a command: `o moku e kili`
+///
+/// The `o` of a command and the `o` of an address are the same word, but the command
+/// is read before its contents and the address after them. The renderer draws both
+/// with the `o` container glyph.
+pub const IMPERATIVE_SEPARATOR: &str = "imperative";
+
+/// Space between compounds on a row and between rows of a page, in glyphs
+const PAGE_GAP: f64 = 0.25;
+/// Space between the blocks of a page, in glyphs
+const BLOCK_GAP: f64 = 0.75;
+
 /// Layout engine for generating arrangement options for Sitelen Sitelen text
-pub struct LayoutEngine;
+pub struct LayoutEngine {
+    lexicon: Arc<Lexicon>,
+    limits: SearchLimits,
+    scorer: Arc<dyn LayoutScorer>,
+}
 
 impl LayoutEngine {
     /// Create a new layout engine
     pub fn new() -> Self {
-        Self
+        Self::with_lexicon(Arc::new(Lexicon::default()))
+    }
+
+    /// Create a layout engine that takes glyph size classes from the given lexicon
+    pub fn with_lexicon(lexicon: Arc<Lexicon>) -> Self {
+        Self {
+            lexicon,
+            limits: SearchLimits::default(),
+            scorer: Arc::new(WeightedScorer::default()),
+        }
+    }
+
+    /// Bound the search for layouts of long sentences
+    pub fn set_search_limits(&mut self, limits: SearchLimits) {
+        self.limits = SearchLimits {
+            beam_width: limits.beam_width.map(|width| width.max(1)),
+            ..limits
+        };
+    }
+
+    /// Rank layouts with another scorer when the search is limited
+    pub fn set_scorer(&mut self, scorer: impl LayoutScorer + 'static) {
+        self.scorer = Arc::new(scorer);
     }
 
     /// Layout a compound sentence into all possible arrangement options
@@ -42,9 +98,19 @@ impl LayoutEngine {
     /// # Returns
     /// A vector of layout options, each representing a different valid arrangement
     pub fn layout_compound(&self, sentence: &Sentence) -> Vec<LayoutOption> {
+        let mut budget = Budget::new(self.limits.max_nodes);
+        self.layout_parts(sentence, &mut budget)
+    }
+
+    /// Layout the parts of a sentence, spending placements from the compound's budget
+    ///
+    /// Every part is laid out with an equal share of the placements left, one share
+    /// being kept for combining the options of the parts.
+    fn layout_parts(&self, sentence: &Sentence, budget: &mut Budget) -> Vec<LayoutOption> {
         let mut hash_map = Vec::new();
 
-        for part in &sentence.parts {
+        for (index, part) in sentence.parts.iter().enumerate() {
+            let mut share = budget.share(sentence.parts.len() - index + 1);
             let (np_options, part_type, separator) = match part {
                 SentencePart::Subject {
                     parts,
@@ -54,14 +120,12 @@ impl LayoutEngine {
                 } => {
                     let options = if let Some(parts) = parts {
                         // Recursively layout parts
-                        let sub_sentence = Sentence {
-                            parts: parts.clone(),
-                        };
-                        self.layout_compound(&sub_sentence)
+                        let sub_sentence = Sentence::new(parts.clone());
+                        self.layout_parts(&sub_sentence, &mut share)
                     } else if separator.as_deref() == Some("cartouche") {
-                        self.convert_cartouche(tokens)
+                        self.convert_cartouche(tokens, &mut share)
                     } else {
-                        self.convert_noun_phrase(tokens)
+                        self.convert_noun_phrase(tokens, &mut share)
                     };
                     (options, "container", separator.clone())
                 }
@@ -72,16 +136,19 @@ impl LayoutEngine {
                     ..
                 } => {
                     let options = if let Some(parts) = parts {
-                        let sub_sentence = Sentence {
-                            parts: parts.clone(),
-                        };
-                        self.layout_compound(&sub_sentence)
+                        let sub_sentence = Sentence::new(parts.clone());
+                        self.layout_parts(&sub_sentence, &mut share)
                     } else if separator == "cartouche" {
-                        self.convert_cartouche(tokens)
+                        self.convert_cartouche(tokens, &mut share)
                     } else {
-                        self.convert_noun_phrase(tokens)
+                        self.convert_noun_phrase(tokens, &mut share)
                     };
-                    (options, "container", Some(separator.clone()))
+                    let separator = if separator == "o" {
+                        IMPERATIVE_SEPARATOR
+                    } else {
+                        separator
+                    };
+                    (options, "container", Some(separator.to_string()))
                 }
                 SentencePart::PrepPhrase {
                     parts,
@@ -90,32 +157,102 @@ impl LayoutEngine {
                     ..
                 } => {
                     let options = if let Some(parts) = parts {
-                        let sub_sentence = Sentence {
-                            parts: parts.clone(),
-                        };
-                        self.layout_compound(&sub_sentence)
+                        let sub_sentence = Sentence::new(parts.clone());
+                        self.layout_parts(&sub_sentence, &mut share)
                     } else if separator == "cartouche" {
-                        self.convert_cartouche(tokens)
+                        self.convert_cartouche(tokens, &mut share)
                     } else {
-                        self.convert_noun_phrase(tokens)
+                        self.convert_noun_phrase(tokens, &mut share)
                     };
                     (options, "container", Some(separator.clone()))
                 }
-                SentencePart::Punctuation { tokens } => {
-                    (self.convert_noun_phrase(tokens), "punctuation", None)
-                }
+                SentencePart::Punctuation { tokens, .. } => (
+                    self.convert_noun_phrase(tokens, &mut share),
+                    "punctuation",
+                    None,
+                ),
                 SentencePart::Address {
-                    tokens, separator, ..
-                } => (
-                    self.convert_noun_phrase(tokens),
+                    parts,
+                    tokens,
+                    separator,
+                    ..
+                } => {
+                    let options = if let Some(parts) = parts {
+                        let sub_sentence = Sentence::new(parts.clone());
+                        self.layout_parts(&sub_sentence, &mut share)
+                    } else {
+                        self.convert_noun_phrase(tokens, &mut share)
+                    };
+                    (options, "container", Some(separator.clone()))
+                }
+                SentencePart::Preverb {
+                    tokens,
+                    parts,
+                    span,
+                } => {
+                    // Group the preverb with its verb in a single container
+                    let mut grouped = vec![SentencePart::Subject {
+                        tokens: tokens.clone(),
+                        separator: None,
+                        parts: None,
+                        span: *span,
+                    }];
+                    grouped.extend(parts.iter().cloned());
+                    let sub_sentence = Sentence::new(grouped);
+                    (
+                        self.layout_parts(&sub_sentence, &mut share),
+                        "container",
+                        None,
+                    )
+                }
+                SentencePart::Coordination { tokens, parts, .. } => {
+                    // Each conjunct gets a container of its own, with the conjunctions in between
+                    let mut grouped = Vec::with_capacity(parts.len() + tokens.len());
+                    for (index, conjunct) in parts.iter().enumerate() {
+                        if let Some(conjunction) = index.checked_sub(1).map(|i| &tokens[i]) {
+                            grouped.push(SentencePart::Subject {
+                                tokens: vec![conjunction.clone()],
+                                separator: None,
+                                parts: None,
+                                span: conjunction.span,
+                            });
+                        }
+                        grouped.push(conjunct.clone());
+                    }
+                    let sub_sentence = Sentence::new(grouped);
+                    (
+                        self.layout_parts(&sub_sentence, &mut share),
+                        "container",
+                        Some(COORDINATION_SEPARATOR.to_string()),
+                    )
+                }
+                SentencePart::Quotation { sentences, .. } => {
+                    // Lay the quoted sentences out as one embedded block
+                    let parts = sentences
+                        .iter()
+                        .flat_map(|sentence| sentence.parts.iter().cloned())
+                        .collect();
+                    let sub_sentence = Sentence::new(parts);
+                    (
+                        self.layout_parts(&sub_sentence, &mut share),
+                        "container",
+                        Some(QUOTATION_SEPARATOR.to_string()),
+                    )
+                }
+                SentencePart::Negation { tokens, .. }
+                | SentencePart::AlaQuestion { tokens, .. }
+                | SentencePart::Interjection { tokens, .. } => (
+                    self.convert_noun_phrase(tokens, &mut share),
                     "container",
-                    Some(separator.clone()),
+                    None,
                 ),
-                SentencePart::Interjection { tokens } => {
-                    (self.convert_noun_phrase(tokens), "container", None)
+                SentencePart::Number { tokens, .. } => {
+                    (self.convert_number(tokens, &mut share), "container", None)
                 }
             };
 
+            budget.refund(share);
+
             hash_map.push(HashMapEntry {
                 part_type: part_type.to_string(),
                 separator,
@@ -123,11 +260,30 @@ impl LayoutEngine {
             });
         }
 
-        let mut compound_options = Vec::new();
-        if !hash_map.is_empty() {
-            self.combine_part_options(0, &hash_map, &mut Vec::new(), &mut compound_options);
+        if hash_map.is_empty() {
+            return Vec::new();
         }
+        self.combine_part_options(&hash_map, budget)
+    }
 
+    /// Combine the options of the parts of a sentence into compound layout options
+    ///
+    /// The full search lays out every combination of part options. A beam search only
+    /// lays out the `beam_width` combinations whose part options score best.
+    fn combine_part_options(
+        &self,
+        hash_map: &[HashMapEntry],
+        budget: &mut Budget,
+    ) -> Vec<LayoutOption> {
+        let mut compound_options = Vec::new();
+        match self.limits.beam_width {
+            None => self.combine_all_options(0, hash_map, &[], &mut compound_options, budget),
+            Some(width) => {
+                for units in self.best_combinations(hash_map, width) {
+                    self.layout_combination(&units, hash_map, &mut compound_options, budget);
+                }
+            }
+        }
         compound_options
     }
 
@@ -135,165 +291,277 @@ impl LayoutEngine {
     ///
     /// This function generates all combinations of layout options from different
     /// sentence parts. For each option of the current part, it recursively processes
-    /// the remaining parts, building up complete compound layouts.
+    /// the remaining parts, building up complete compound layouts. Once the budget is
+    /// spent no further combinations are tried; if none was laid out yet, the best
+    /// scored option of each remaining part is combined.
     ///
     /// # Arguments
     /// * `index` - Current part index in the hash_map
     /// * `hash_map` - Map of sentence parts to their layout options
     /// * `units` - Accumulated layout units from previous parts
     /// * `compound_options` - Output vector for completed compound options
-    fn combine_part_options(
+    /// * `budget` - Placements left for the compound
+    fn combine_all_options(
         &self,
         index: usize,
         hash_map: &[HashMapEntry],
-        units: &mut Vec<LayoutUnit>,
+        units: &[LayoutUnit],
         compound_options: &mut Vec<LayoutOption>,
+        budget: &mut Budget,
     ) {
-        for option in &hash_map[index].options {
-            let mut new_units = units.clone();
-
-            let layout_type = if hash_map[index].part_type == "punctuation" {
-                LayoutType::Punctuation
-            } else {
-                LayoutType::Container
-            };
-
-            let separator = hash_map[index].separator.clone();
-
-            // Create a layout unit for the container
-            let container_unit = LayoutUnit::Container {
-                units: option.state.units.clone(),
-                size: option.size,
-                separator: separator.clone(),
-                layout_type: layout_type.clone(),
-            };
+        if budget.exhausted() && !compound_options.is_empty() {
+            return;
+        }
+        let entry = &hash_map[index];
+        let options = if budget.exhausted() {
+            self.best_option(&entry.options)
+                .map(std::slice::from_ref)
+                .unwrap_or_default()
+        } else {
+            &entry.options[..]
+        };
 
-            new_units.push(container_unit);
+        for option in options {
+            let mut new_units = units.to_vec();
+            new_units.push(entry.container_unit(option));
 
             if index + 1 < hash_map.len() {
-                self.combine_part_options(index + 1, hash_map, &mut new_units, compound_options);
+                self.combine_all_options(index + 1, hash_map, &new_units, compound_options, budget);
             } else {
-                let container_options = self.layout_container(&new_units);
-                for mut opt in container_options {
-                    // Don't set separator on the final compound - separators only apply to individual parts
-                    opt.separator = None;
-                    opt.layout_type = layout_type;
-                    compound_options.push(opt);
+                self.layout_combination(&new_units, hash_map, compound_options, budget);
+            }
+        }
+    }
+
+    /// The `width` combinations of part options with the lowest sum of scores
+    fn best_combinations(&self, hash_map: &[HashMapEntry], width: usize) -> Vec<Vec<LayoutUnit>> {
+        let mut beam: Vec<(f64, Vec<LayoutUnit>)> = vec![(0.0, Vec::new())];
+        for entry in hash_map {
+            let scores: Vec<f64> = entry
+                .options
+                .iter()
+                .map(|option| self.scorer.score(option))
+                .collect();
+            let mut next = Vec::with_capacity(beam.len() * entry.options.len());
+            for (score, units) in &beam {
+                for (option, option_score) in entry.options.iter().zip(&scores) {
+                    let mut units = units.clone();
+                    units.push(entry.container_unit(option));
+                    next.push((score + option_score, units));
                 }
             }
+            next.sort_by(|a, b| a.0.total_cmp(&b.0));
+            next.truncate(width);
+            beam = next;
+        }
+        beam.into_iter().map(|(_, units)| units).collect()
+    }
+
+    /// Lay out one combination of part options as compound options
+    fn layout_combination(
+        &self,
+        units: &[LayoutUnit],
+        hash_map: &[HashMapEntry],
+        compound_options: &mut Vec<LayoutOption>,
+        budget: &mut Budget,
+    ) {
+        budget.spend();
+        let layout_type = hash_map
+            .last()
+            .map_or(LayoutType::Container, HashMapEntry::layout_type);
+        for mut opt in self.layout_container(units, budget) {
+            // Don't set separator on the final compound - separators only apply to individual parts
+            opt.separator = None;
+            opt.layout_type = layout_type;
+            compound_options.push(opt);
         }
     }
 
-    /// Generate all layout options for a container of units
+    /// Option with the lowest score, the first of equals
+    fn best_option<'a>(&self, options: &'a [LayoutOption]) -> Option<&'a LayoutOption> {
+        options
+            .iter()
+            .map(|option| (self.scorer.score(option), option))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, option)| option)
+    }
+
+    /// Generate layout options for a container of units
     ///
-    /// This function explores all possible arrangements of units within a container.
     /// Units can be placed either downward (vertically) or to the right (horizontally),
-    /// with constraints to ensure valid layouts.
+    /// with constraints to ensure valid layouts. Without a beam width every arrangement
+    /// is explored, depth first; with one, a beam search keeps the best partial layouts.
     ///
     /// # Arguments
     /// * `units` - The units to arrange within the container
+    /// * `budget` - Placements left for the compound
     ///
     /// # Returns
     /// A vector of layout options, each representing a different valid arrangement
-    fn layout_container(&self, units: &[LayoutUnit]) -> Vec<LayoutOption> {
+    fn layout_container(&self, units: &[LayoutUnit], budget: &mut Budget) -> Vec<LayoutOption> {
         if units.is_empty() {
             return Vec::new();
         }
 
-        let mut options = Vec::new();
-        let mut hash: HashMap<String, LayoutOption> = HashMap::new();
-        let mut min_surface = INITIAL_MIN_SURFACE;
+        // Place the first unit directly
+        let first_size = self.get_unit_size(&units[0]);
+        let first = LayoutState {
+            units: vec![PlacedUnit {
+                unit: units[0].clone(),
+                size: first_size,
+                position: Position::new(0.0, 0.0),
+            }],
+            size: first_size,
+            forbidden: vec![Position::new(first_size.width, first_size.height)],
+        };
 
-        self.layout_container_recursive(
-            units,
-            None,
-            None,
-            &mut options,
-            &mut hash,
-            &mut min_surface,
-        );
+        if units.len() == 1 {
+            return vec![self.create_option(&first, LayoutType::Container, None)];
+        }
 
-        options
+        let mut search = ContainerSearch {
+            options: Vec::new(),
+            hash: HashMap::new(),
+            min_surface: INITIAL_MIN_SURFACE,
+            budget,
+        };
+        match self.limits.beam_width {
+            None => self.extend_placement(units, &first, 1, &mut search),
+            Some(width) => self.beam_placement(units, first, width, &mut search),
+        }
+        search.options
     }
 
-    /// Recursively generate layout options by placing units
-    ///
-    /// This is the core recursive function that explores all valid placement combinations.
-    /// It handles three phases:
-    /// 1. Initialization: Place the first unit at origin
-    /// 2. Placement: Place groups of units either downward or to the right
-    /// 3. Continuation: Recursively place remaining units
+    /// Placements of the units from `index` on: groups of every length, to the right and downward
+    fn placements(&self, units: &[LayoutUnit], index: usize) -> Vec<PlacementStrategy> {
+        let mut placements = Vec::new();
+        for length in 1..=units.len() - index {
+            // Prevent punctuation elements to be placed to the right, alone
+            if !self.is_punctuation(&units[index]) {
+                placements.push(PlacementStrategy {
+                    goes_down: false,
+                    index,
+                    length,
+                });
+            }
+            placements.push(PlacementStrategy {
+                goes_down: true,
+                index,
+                length,
+            });
+        }
+        placements
+    }
+
+    /// Explore every placement of the units from `index` on, depth first
     ///
-    /// # Arguments
-    /// * `units` - All units to be placed
-    /// * `state` - Current layout state (None for initialization)
-    /// * `placement` - Placement strategy (None for initialization)
-    /// * `options` - Output vector for completed options
-    /// * `hash` - Hash map for deduplication
-    /// * `min_surface` - Track minimum surface area for filtering
-    fn layout_container_recursive(
+    /// Once the budget is spent, only the best scored placement is followed.
+    fn extend_placement(
         &self,
         units: &[LayoutUnit],
-        state: Option<&LayoutState>,
-        placement: Option<PlacementStrategy>,
-        options: &mut Vec<LayoutOption>,
-        hash: &mut HashMap<String, LayoutOption>,
-        min_surface: &mut f64,
+        state: &LayoutState,
+        index: usize,
+        search: &mut ContainerSearch,
     ) {
-        if state.is_none() {
-            // Place the first unit directly
-            let first_size = self.get_unit_size(&units[0]);
-            let new_state = LayoutState {
-                units: vec![PlacedUnit {
-                    unit: units[0].clone(),
-                    size: first_size,
-                    position: Position::new(0.0, 0.0),
-                }],
-                size: first_size,
-                forbidden: vec![Position::new(first_size.width, first_size.height)],
-            };
+        if search.budget.exhausted() {
+            let best = self
+                .placements(units, index)
+                .into_iter()
+                .filter_map(|placement| {
+                    let next = self.place_unit_group(units, state, placement)?;
+                    Some((self.state_score(&next), placement, next))
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+            if let Some((_, placement, next)) = best {
+                self.advance_placement(units, &next, index + placement.length, search);
+            }
+            return;
+        }
 
-            if units.len() == 1 {
-                let new_option = self.create_option(&new_state, LayoutType::Container, None);
-                options.push(new_option);
-                return;
+        for placement in self.placements(units, index) {
+            if let Some(next) = self.place_unit_group(units, state, placement) {
+                search.budget.spend();
+                self.advance_placement(units, &next, index + placement.length, search);
             }
+        }
+    }
 
-            for j in 1..units.len() {
-                // Prevent punctuation elements to be placed to the right, alone
-                if !self.is_punctuation(&units[1]) {
-                    self.try_place_units(
-                        units,
-                        &new_state,
-                        1,
-                        j,
-                        false,
-                        options,
-                        hash,
-                        min_surface,
-                    );
+    /// Finalize a state that placed every unit, or continue placing the rest
+    fn advance_placement(
+        &self,
+        units: &[LayoutUnit],
+        state: &LayoutState,
+        index: usize,
+        search: &mut ContainerSearch,
+    ) {
+        if index == units.len() {
+            self.finalize_option(state, search);
+        } else {
+            self.extend_placement(units, state, index, search);
+        }
+    }
+
+    /// Beam search over placements
+    ///
+    /// Partial layouts are grouped by the number of units they place, and only the
+    /// best `width` of a group are extended; one once the budget is spent.
+    fn beam_placement(
+        &self,
+        units: &[LayoutUnit],
+        first: LayoutState,
+        width: usize,
+        search: &mut ContainerSearch,
+    ) {
+        let mut frontier: Vec<Vec<LayoutState>> = vec![Vec::new(); units.len()];
+        frontier[1].push(first);
+
+        for index in 1..units.len() {
+            let mut states = std::mem::take(&mut frontier[index]);
+            let keep = if search.budget.exhausted() { 1 } else { width };
+            if states.len() > keep {
+                let mut scored: Vec<(f64, LayoutState)> = states
+                    .into_iter()
+                    .map(|state| (self.state_score(&state), state))
+                    .collect();
+                scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+                states = scored
+                    .into_iter()
+                    .take(keep)
+                    .map(|(_, state)| state)
+                    .collect();
+            }
+
+            for state in &states {
+                for placement in self.placements(units, index) {
+                    if let Some(next) = self.place_unit_group(units, state, placement) {
+                        search.budget.spend();
+                        let placed = index + placement.length;
+                        if placed == units.len() {
+                            self.finalize_option(&next, search);
+                        } else {
+                            frontier[placed].push(next);
+                        }
+                    }
                 }
-                self.try_place_units(units, &new_state, 1, j, true, options, hash, min_surface);
             }
-            return;
         }
+    }
 
-        let state = state.unwrap();
-        let placement = placement.unwrap();
-
-        self.place_unit_group(units, state, placement, options, hash, min_surface);
+    /// Score of a partial or complete layout state
+    fn state_score(&self, state: &LayoutState) -> f64 {
+        let option = self.create_option(state, LayoutType::Container, None);
+        self.scorer.score(&self.normalize_option(option))
     }
 
     /// Place a group of units according to the placement strategy
+    ///
+    /// Returns `None` when the units cannot be placed that way.
     fn place_unit_group(
         &self,
         units: &[LayoutUnit],
         state: &LayoutState,
         placement: PlacementStrategy,
-        options: &mut Vec<LayoutOption>,
-        hash: &mut HashMap<String, LayoutOption>,
-        min_surface: &mut f64,
-    ) {
+    ) -> Option<LayoutState> {
         let PlacementStrategy {
             goes_down,
             index,
@@ -310,17 +578,13 @@ impl LayoutEngine {
         let unit_position = self.calculate_placement_position(state, goes_down);
 
         // Check size compatibility and calculate size sum
-        let size_sum = match self
-            .check_size_compatibility_and_sum(units, index, length, goes_down, prev_size)
-        {
-            Some(sum) => sum,
-            None => return, // Incompatible sizes
-        };
+        let size_sum =
+            self.check_size_compatibility_and_sum(units, index, length, goes_down, prev_size)?;
 
         // Place units one by one
         let mut current_pos = unit_position;
-        for i in index..index + length {
-            let add_size = self.get_unit_size(&units[i]);
+        for (offset, unit) in units[index..index + length].iter().enumerate() {
+            let add_size = self.get_unit_size(unit);
             let glyph_size =
                 self.calculate_glyph_size(add_size, &new_state.size, &size_sum, goes_down);
             let add = if goes_down {
@@ -330,7 +594,7 @@ impl LayoutEngine {
             };
 
             // Update container size when placing first unit of group
-            if i == index {
+            if offset == 0 {
                 if goes_down {
                     new_state.size.height += add;
                 } else {
@@ -340,12 +604,12 @@ impl LayoutEngine {
 
             // Check forbidden position (only for downward placement)
             if goes_down && self.is_position_forbidden(&new_state.forbidden, &current_pos) {
-                return;
+                return None;
             }
 
             // Add unit to layout
             new_state.units.push(PlacedUnit {
-                unit: units[i].clone(),
+                unit: unit.clone(),
                 size: glyph_size,
                 position: current_pos,
             });
@@ -366,77 +630,21 @@ impl LayoutEngine {
             new_state.forbidden.push(forbidden_pos);
         }
 
-        // If all units are placed, finalize the option
-        if index + length == units.len() {
-            self.finalize_option(&new_state, options, hash, min_surface);
-            return;
-        }
-
-        // Continue placing remaining units
-        self.continue_placement(
-            units,
-            &new_state,
-            index + length,
-            options,
-            hash,
-            min_surface,
-        );
+        Some(new_state)
     }
 
     /// Finalize a completed layout option
-    fn finalize_option(
-        &self,
-        state: &LayoutState,
-        options: &mut Vec<LayoutOption>,
-        hash: &mut HashMap<String, LayoutOption>,
-        min_surface: &mut f64,
-    ) {
+    fn finalize_option(&self, state: &LayoutState, search: &mut ContainerSearch) {
         let mut new_option = self.create_option(state, LayoutType::Container, None);
         new_option = self.normalize_option(new_option);
 
-        *min_surface = (*min_surface).min(new_option.surface);
-        if new_option.surface / *min_surface < MAX_SURFACE_RATIO {
+        search.min_surface = search.min_surface.min(new_option.surface);
+        if new_option.surface / search.min_surface < MAX_SURFACE_RATIO {
             let key = self.option_key(&new_option);
-            if !hash.contains_key(&key) {
-                hash.insert(key, new_option.clone());
-                options.push(new_option);
-            }
-        }
-    }
-
-    /// Continue placing remaining units after a group has been placed
-    fn continue_placement(
-        &self,
-        units: &[LayoutUnit],
-        state: &LayoutState,
-        next_index: usize,
-        options: &mut Vec<LayoutOption>,
-        hash: &mut HashMap<String, LayoutOption>,
-        min_surface: &mut f64,
-    ) {
-        for j in 1..units.len() - next_index + 1 {
-            if !self.is_punctuation(&units[next_index]) {
-                self.try_place_units(
-                    units,
-                    state,
-                    next_index,
-                    j,
-                    false,
-                    options,
-                    hash,
-                    min_surface,
-                );
+            if let Entry::Vacant(entry) = search.hash.entry(key) {
+                entry.insert(new_option.clone());
+                search.options.push(new_option);
             }
-            self.try_place_units(
-                units,
-                state,
-                next_index,
-                j,
-                true,
-                options,
-                hash,
-                min_surface,
-            );
         }
     }
 
@@ -481,7 +689,8 @@ impl LayoutEngine {
         option
     }
 
-    fn option_key(&self, option: &LayoutOption) -> String {
+    /// Key of the arrangement of the units of an option, the same for duplicate options
+    pub(crate) fn option_key(&self, option: &LayoutOption) -> String {
         // Create a comprehensive key that includes the layout structure
         // This matches JavaScript's JSON.stringify behavior which includes the entire structure
         // We need to capture: type, size, ratio, and all units with their positions and types
@@ -545,6 +754,24 @@ impl LayoutEngine {
                         placed.size.height
                     )
                 }
+                LayoutUnit::Number { units, .. } => {
+                    // The arrangement inside a number block follows from its glyphs
+                    let tokens: Vec<&str> = units
+                        .iter()
+                        .filter_map(|glyph| match &glyph.unit {
+                            LayoutUnit::WordGlyph { token, .. } => Some(token.as_str()),
+                            _ => None,
+                        })
+                        .collect();
+                    format!(
+                        "num:{:?}:pos({:.4},{:.4}):size({:.4},{:.4})",
+                        tokens,
+                        placed.position.x,
+                        placed.position.y,
+                        placed.size.width,
+                        placed.size.height
+                    )
+                }
             };
             key_parts.push(unit_str);
         }
@@ -557,7 +784,8 @@ impl LayoutEngine {
             LayoutUnit::WordGlyph { size, .. }
             | LayoutUnit::SyllableGlyph { size, .. }
             | LayoutUnit::Container { size, .. }
-            | LayoutUnit::Punctuation { size, .. } => *size,
+            | LayoutUnit::Punctuation { size, .. }
+            | LayoutUnit::Number { size, .. } => *size,
         }
     }
 
@@ -582,8 +810,8 @@ impl LayoutEngine {
         prev_size: Size,
     ) -> Option<Size> {
         let mut size_sum = Size::new(0.0, 0.0);
-        for i in index..index + length {
-            let add_size = self.get_unit_size(&units[i]);
+        for unit in &units[index..index + length] {
+            let add_size = self.get_unit_size(unit);
             if (goes_down && (add_size.height - prev_size.height).abs() > EPSILON)
                 || (!goes_down && (add_size.width - prev_size.width).abs() > EPSILON)
             {
@@ -626,34 +854,6 @@ impl LayoutEngine {
         }
     }
 
-    /// Try to place a group of units with the given strategy
-    ///
-    /// Helper function to reduce duplication in recursive calls.
-    fn try_place_units(
-        &self,
-        units: &[LayoutUnit],
-        state: &LayoutState,
-        index: usize,
-        length: usize,
-        goes_down: bool,
-        options: &mut Vec<LayoutOption>,
-        hash: &mut HashMap<String, LayoutOption>,
-        min_surface: &mut f64,
-    ) {
-        self.layout_container_recursive(
-            units,
-            Some(state),
-            Some(PlacementStrategy {
-                goes_down,
-                index,
-                length,
-            }),
-            options,
-            hash,
-            min_surface,
-        );
-    }
-
     /// Check if a unit is punctuation (cannot be placed horizontally)
     fn is_punctuation(&self, unit: &LayoutUnit) -> bool {
         match unit {
@@ -664,42 +864,89 @@ impl LayoutEngine {
     }
 
     /// Convert noun phrase tokens to layout units
-    fn convert_noun_phrase(&self, tokens: &[String]) -> Vec<LayoutOption> {
-        const SMALL_MODIFIERS: &[&str] = &["kon", "lili", "mute", "sin"];
-        const NARROW_MODIFIERS: &[&str] = &["wan", "tu", "anu", "en", "kin"];
+    fn convert_noun_phrase(&self, tokens: &[Token], budget: &mut Budget) -> Vec<LayoutOption> {
         const PUNCTUATION: &[&str] = &["period", "exclamation", "question"];
         const SINGLE_PUNCTUATION: &[&str] = &["comma", "colon"];
         const LARGE_PUNCTUATION: &[&str] = &["la", "banner"];
 
-        fn get_size_of(token: &str) -> Size {
+        let get_size_of = |token: &str| {
             if SINGLE_PUNCTUATION.contains(&token) {
                 Size::new(4.0, 0.5)
             } else if PUNCTUATION.contains(&token) {
                 Size::new(4.0, 0.75)
             } else if LARGE_PUNCTUATION.contains(&token) {
                 Size::new(4.0, 1.0)
-            } else if SMALL_MODIFIERS.contains(&token) {
-                Size::new(1.0, 0.5)
-            } else if NARROW_MODIFIERS.contains(&token) {
-                Size::new(0.5, 1.0)
             } else {
-                Size::new(1.0, 1.0)
+                match self.lexicon.size_class(token) {
+                    SizeClass::Small => Size::new(1.0, 0.5),
+                    SizeClass::Narrow => Size::new(0.5, 1.0),
+                    SizeClass::Normal => Size::new(1.0, 1.0),
+                }
             }
-        }
+        };
 
         let units: Vec<LayoutUnit> = tokens
             .iter()
             .map(|token| LayoutUnit::WordGlyph {
-                token: token.clone(),
-                size: get_size_of(token),
+                token: token.text.clone(),
+                size: get_size_of(token.as_str()),
             })
             .collect();
 
-        self.layout_container(&units)
+        self.layout_container(&units, budget)
+    }
+
+    /// Convert number phrase tokens to a single compact block
+    ///
+    /// Number glyphs skip the combinatorial search: they are set side by side in reading
+    /// order, one glyph high, with small glyphs such as `mute` stacked in pairs.
+    fn convert_number(&self, tokens: &[Token], budget: &mut Budget) -> Vec<LayoutOption> {
+        let glyph = |token: &Token, size: Size, x: f64, y: f64| PlacedUnit {
+            unit: LayoutUnit::WordGlyph {
+                token: token.text.clone(),
+                size,
+            },
+            size,
+            position: Position::new(x, y),
+        };
+
+        let mut units = Vec::new();
+        let mut x = 0.0;
+        let mut i = 0;
+        while i < tokens.len() {
+            match self.lexicon.size_class(tokens[i].as_str()) {
+                SizeClass::Narrow => {
+                    units.push(glyph(&tokens[i], Size::new(0.5, 1.0), x, 0.0));
+                    x += 0.5;
+                }
+                SizeClass::Normal => {
+                    units.push(glyph(&tokens[i], Size::new(1.0, 1.0), x, 0.0));
+                    x += 1.0;
+                }
+                SizeClass::Small => {
+                    let size = Size::new(1.0, 0.5);
+                    let next = tokens
+                        .get(i + 1)
+                        .filter(|next| self.lexicon.size_class(next.as_str()) == SizeClass::Small);
+                    if let Some(next) = next {
+                        units.push(glyph(&tokens[i], size, x, 0.0));
+                        units.push(glyph(next, size, x, 0.5));
+                        i += 1;
+                    } else {
+                        units.push(glyph(&tokens[i], size, x, 0.25));
+                    }
+                    x += 1.0;
+                }
+            }
+            i += 1;
+        }
+
+        let size = Size::new(x, 1.0);
+        self.layout_container(&[LayoutUnit::Number { units, size }], budget)
     }
 
     /// Convert cartouche tokens to layout units
-    fn convert_cartouche(&self, tokens: &[String]) -> Vec<LayoutOption> {
+    fn convert_cartouche(&self, tokens: &[Token], budget: &mut Budget) -> Vec<LayoutOption> {
         const NARROW_SYLS: &[&str] = &[
             "li", "ni", "si", "lin", "nin", "sin", "le", "ne", "se", "len", "nen", "sen", "lo",
             "no", "so", "lon", "non", "son", "la", "na", "sa", "lan", "nan", "san", "lu", "nu",
@@ -717,12 +964,12 @@ impl LayoutEngine {
         let units: Vec<LayoutUnit> = tokens
             .iter()
             .map(|token| LayoutUnit::SyllableGlyph {
-                token: token.clone(),
-                size: get_size_of(token),
+                token: token.text.clone(),
+                size: get_size_of(token.as_str()),
             })
             .collect();
 
-        self.layout_container(&units)
+        self.layout_container(&units, budget)
     }
 }
 
@@ -732,6 +979,238 @@ impl Default for LayoutEngine {
     }
 }
 
+/// How the rows of a line are placed across the page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    /// Every row but the last of a line is stretched to the full width by widening
+    /// the space between its compounds
+    Justify,
+}
+
+/// Arranges compounds row by row on a page of fixed width
+///
+/// Compounds keep their size in glyph units, times the scale of their line. A line
+/// wraps before a compound that does not fit, and a compound wider than the page is
+/// shrunk to fit. Rows are as tall as their tallest compound; the others are
+/// centred vertically.
+///
+/// A page with a height is split into columns: rows fill a column from the top, and
+/// the next row that does not fit starts the next column. After the last column a
+/// new page starts below the previous one.
+pub struct PageBuilder {
+    width: f64,
+    /// Height of a column, if columns are filled
+    height: Option<f64>,
+    columns: usize,
+    compounds: Vec<PlacedCompound>,
+    /// Column and top of the page the rows are placed on
+    column: usize,
+    page_top: f64,
+    y: f64,
+    rows_in_block: usize,
+}
+
+impl PageBuilder {
+    /// Start an empty page `width` glyphs wide
+    pub fn new(width: f64) -> Self {
+        Self {
+            width,
+            height: None,
+            columns: 1,
+            compounds: Vec::new(),
+            column: 0,
+            page_top: 0.0,
+            y: 0.0,
+            rows_in_block: 0,
+        }
+    }
+
+    /// Start empty pages `width` by `height` glyphs, of `columns` columns (at least one)
+    pub fn with_columns(width: f64, height: f64, columns: usize) -> Self {
+        Self {
+            height: Some(height),
+            columns: columns.max(1),
+            ..Self::new(width)
+        }
+    }
+
+    /// Width of a column, in glyphs
+    fn column_width(&self) -> f64 {
+        let gaps = (self.columns - 1) as f64 * BLOCK_GAP;
+        ((self.width - gaps) / self.columns as f64).max(PAGE_GAP)
+    }
+
+    /// Start a new block, leaving space after the previous one
+    pub fn start_block(&mut self) {
+        if !self.compounds.is_empty() && self.y > self.page_top {
+            self.y += BLOCK_GAP;
+        }
+        self.rows_in_block = 0;
+    }
+
+    /// Size of a compound at a scale, shrunk to fit a column
+    fn fitted_size(&self, option: &LayoutOption, scale: f64) -> Size {
+        let width = self.column_width();
+        let size = Size::new(option.size.width * scale, option.size.height * scale);
+        if size.width > width {
+            let fit = width / size.width;
+            Size::new(width, size.height * fit)
+        } else {
+            size
+        }
+    }
+
+    /// Place a line of compounds, starting on a new row
+    pub fn push_line(&mut self, compounds: &[LayoutOption], scale: f64, align: Align) {
+        let width = self.column_width();
+        let sizes: Vec<Size> = compounds
+            .iter()
+            .map(|option| self.fitted_size(option, scale))
+            .collect();
+
+        let mut start = 0;
+        while start < sizes.len() {
+            // Take compounds while they fit on the row, but always at least one
+            let mut end = start + 1;
+            let mut row_width = sizes[start].width;
+            while end < sizes.len() && row_width + PAGE_GAP + sizes[end].width <= width {
+                row_width += PAGE_GAP + sizes[end].width;
+                end += 1;
+            }
+            let last = end == sizes.len();
+            self.place_row(
+                &compounds[start..end],
+                &sizes[start..end],
+                row_width,
+                align,
+                last,
+            );
+            start = end;
+        }
+    }
+
+    /// Place a line of compounds that may each be drawn with any of their options
+    ///
+    /// The rows and the option of every compound are chosen together, as the breaks of
+    /// a paragraph in the Knuth–Plass algorithm, to fill the rows evenly with well
+    /// scored layouts. Compounds without options are left out.
+    pub fn push_paragraph(
+        &mut self,
+        compounds: &[Vec<LayoutOption>],
+        scorer: &dyn LayoutScorer,
+        scale: f64,
+        align: Align,
+    ) {
+        let compounds: Vec<&Vec<LayoutOption>> = compounds
+            .iter()
+            .filter(|options| !options.is_empty())
+            .collect();
+        let candidates: Vec<Vec<Candidate>> = compounds
+            .iter()
+            .map(|options| {
+                linebreak::prune(
+                    options
+                        .iter()
+                        .enumerate()
+                        .map(|(index, option)| {
+                            let size = self.fitted_size(option, scale);
+                            Candidate {
+                                index,
+                                width: size.width,
+                                height: size.height,
+                                cost: scorer.score(option),
+                            }
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
+
+        let rows = linebreak::break_rows(&candidates, self.column_width(), PAGE_GAP);
+        let count = rows.len();
+        for (number, row) in rows.into_iter().enumerate() {
+            let options: Vec<LayoutOption> = row
+                .choices
+                .iter()
+                .enumerate()
+                .map(|(offset, &index)| compounds[row.start + offset][index].clone())
+                .collect();
+            let sizes: Vec<Size> = options
+                .iter()
+                .map(|option| self.fitted_size(option, scale))
+                .collect();
+            let width = sizes.iter().map(|size| size.width).sum::<f64>()
+                + PAGE_GAP * sizes.len().saturating_sub(1) as f64;
+            self.place_row(&options, &sizes, width, align, number + 1 == count);
+        }
+    }
+
+    fn place_row(
+        &mut self,
+        compounds: &[LayoutOption],
+        sizes: &[Size],
+        width: f64,
+        align: Align,
+        last: bool,
+    ) {
+        let height = sizes.iter().map(|size| size.height).fold(0.0, f64::max);
+        if self.rows_in_block > 0 {
+            self.y += PAGE_GAP;
+        }
+        self.rows_in_block += 1;
+        if let Some(column_height) = self.height {
+            // Continue in the next column, or on a new page, unless the column is empty
+            if self.y + height > self.page_top + column_height && self.y > self.page_top {
+                if self.column + 1 < self.columns {
+                    self.column += 1;
+                } else {
+                    self.column = 0;
+                    self.page_top += column_height + BLOCK_GAP;
+                }
+                self.y = self.page_top;
+            }
+        }
+
+        let column_width = self.column_width();
+        let left = self.column as f64 * (column_width + BLOCK_GAP);
+        let mut gap = PAGE_GAP;
+        let mut x = left
+            + match align {
+                Align::Left => 0.0,
+                Align::Center => (column_width - width) / 2.0,
+                Align::Justify => {
+                    if !last && sizes.len() > 1 {
+                        gap += (column_width - width).max(0.0) / (sizes.len() - 1) as f64;
+                    }
+                    0.0
+                }
+            };
+        for (option, size) in compounds.iter().zip(sizes) {
+            self.compounds.push(PlacedCompound {
+                option: option.clone(),
+                position: Position::new(x, self.y + (height - size.height) / 2.0),
+                size: *size,
+            });
+            x += size.width + gap;
+        }
+        self.y += height;
+    }
+
+    /// The finished page, as tall as its rows, or as its pages when columns are filled
+    pub fn finish(self) -> Page {
+        let height = match self.height {
+            Some(column_height) => self.page_top + column_height,
+            None => self.y,
+        };
+        Page {
+            size: Size::new(self.width, height),
+            compounds: self.compounds,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct HashMapEntry {
     part_type: String,
@@ -739,6 +1218,36 @@ struct HashMapEntry {
     options: Vec<LayoutOption>,
 }
 
+impl HashMapEntry {
+    fn layout_type(&self) -> LayoutType {
+        if self.part_type == "punctuation" {
+            LayoutType::Punctuation
+        } else {
+            LayoutType::Container
+        }
+    }
+
+    /// Container unit holding one of the options of the part
+    fn container_unit(&self, option: &LayoutOption) -> LayoutUnit {
+        LayoutUnit::Container {
+            units: option.state.units.clone(),
+            size: option.size,
+            separator: self.separator.clone(),
+            layout_type: self.layout_type(),
+        }
+    }
+}
+
+/// Options found so far while laying out a container
+struct ContainerSearch<'a> {
+    options: Vec<LayoutOption>,
+    /// Keys of the options, to drop duplicates
+    hash: HashMap<String, LayoutOption>,
+    /// Smallest surface of an option, to drop options much larger
+    min_surface: f64,
+    budget: &'a mut Budget,
+}
+
 /// Strategy for placing a group of units in the layout
 ///
 /// Describes how to place the next group of units: their starting index,
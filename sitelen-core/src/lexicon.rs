@@ -0,0 +1,443 @@
+//! Configurable lexicon of Toki Pona words
+//!
+//! The lexicon defines which words the parser accepts, their grammatical role,
+//! the size class the layout engine uses for their glyph and the glyph ID the
+//! renderer references. The built-in lexicon covers the nimi ku suli; additional
+//! words (nimi sin) can be loaded from TOML or JSON files at runtime.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Built-in Toki Pona words
+const WORDS: &[&str] = &[
+    "a",
+    "akesi",
+    "ala",
+    "alasa",
+    "ali",
+    "anpa",
+    "ante",
+    "anu",
+    "awen",
+    "e",
+    "en",
+    "epiku",
+    "esun",
+    "ijo",
+    "ike",
+    "ilo",
+    "insa",
+    "jaki",
+    "jan",
+    "jasima",
+    "jelo",
+    "jo",
+    "kala",
+    "kalama",
+    "kama",
+    "kasi",
+    "ken",
+    "kepeken",
+    "kili",
+    "kin",
+    "kipisi",
+    "kiwen",
+    "kijetesantakalu",
+    "ko",
+    "kokosila",
+    "kon",
+    "kule",
+    "kulupu",
+    "kute",
+    "la",
+    "lanpan",
+    "lape",
+    "laso",
+    "lawa",
+    "leko",
+    "len",
+    "lete",
+    "li",
+    "lili",
+    "linja",
+    "lipu",
+    "loje",
+    "lon",
+    "luka",
+    "lukin",
+    "lupa",
+    "ma",
+    "mama",
+    "mani",
+    "meli",
+    "meso",
+    "mi",
+    "mije",
+    "misikeke",
+    "moku",
+    "moli",
+    "monsi",
+    "monsuta",
+    "mu",
+    "mun",
+    "musi",
+    "mute",
+    "n",
+    "namako",
+    "nanpa",
+    "nasa",
+    "nasin",
+    "nena",
+    "ni",
+    "nimi",
+    "noka",
+    "o",
+    "oko",
+    "olin",
+    "ona",
+    "open",
+    "pakala",
+    "pali",
+    "palisa",
+    "pan",
+    "pana",
+    "pi",
+    "pilin",
+    "pimeja",
+    "pini",
+    "pipi",
+    "poka",
+    "poki",
+    "pona",
+    "pu",
+    "sama",
+    "seli",
+    "selo",
+    "seme",
+    "sewi",
+    "sijelo",
+    "sike",
+    "sin",
+    "sina",
+    "sinpin",
+    "sitelen",
+    "soko",
+    "sona",
+    "soweli",
+    "suli",
+    "suno",
+    "supa",
+    "suwi",
+    "tan",
+    "taso",
+    "tawa",
+    "telo",
+    "tenpo",
+    "toki",
+    "tomo",
+    "tonsi",
+    "tu",
+    "unpa",
+    "uta",
+    "utala",
+    "walo",
+    "wan",
+    "waso",
+    "wawa",
+    "weka",
+    "wile",
+    "ale",
+];
+
+/// Prepositions that create prepositional phrases
+const PREPOSITIONS: &[&str] = &["tawa", "tan", "lon", "kepeken", "sama", "poka"];
+
+/// Object markers
+const OBJECT_MARKERS: &[&str] = &["li", "e"];
+
+/// Preverbs that modify the verb that follows them
+const PREVERBS: &[&str] = &[
+    "wile", "ken", "kama", "awen", "sona", "alasa", "open", "pini",
+];
+
+/// Particles that open a modifier container
+const CONTAINER_MARKERS: &[&str] = &["pi"];
+
+/// Other grammatical particles
+const PARTICLES: &[&str] = &["a", "anu", "en", "la", "o"];
+
+/// Punctuation marks accepted as words
+const PUNCTUATION: &[&str] = &[".", "?", "!", ":", ","];
+
+/// Words drawn at full width and half height
+const SMALL_MODIFIERS: &[&str] = &["kon", "lili", "mute", "sin"];
+
+/// Words drawn at half width and full height
+const NARROW_MODIFIERS: &[&str] = &["wan", "tu", "anu", "en", "kin"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum LexiconError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("TOML error: {0}")]
+    Toml(String),
+    #[error("JSON error: {0}")]
+    Json(String),
+    #[error("Unsupported lexicon format: {0}")]
+    UnsupportedFormat(String),
+}
+
+/// Grammatical role of a word
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WordRole {
+    /// Regular content word
+    #[default]
+    Content,
+    /// Preposition that opens a prepositional phrase (lon, tawa, ...)
+    Preposition,
+    /// Marker that starts a predicate or object (li, e)
+    ObjectMarker,
+    /// Preverb that modifies a following verb (wile, ken, ...)
+    Preverb,
+    /// Particle that opens a modifier container (pi)
+    ContainerMarker,
+    /// Other grammatical particle (a, en, la, ...)
+    Particle,
+    /// Punctuation mark
+    Punctuation,
+}
+
+/// Size class of a word glyph in the layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeClass {
+    /// Square glyph
+    #[default]
+    Normal,
+    /// Full width, half height (lili, mute, ...)
+    Small,
+    /// Half width, full height (wan, tu, en, ...)
+    Narrow,
+}
+
+/// Lexicon entry for a single word
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WordEntry {
+    pub role: WordRole,
+    pub size: SizeClass,
+    /// Glyph symbol ID in the sprite (defaults to `tp-wg-<word>`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glyph: Option<String>,
+}
+
+impl WordEntry {
+    pub fn new(role: WordRole, size: SizeClass) -> Self {
+        Self {
+            role,
+            size,
+            glyph: None,
+        }
+    }
+}
+
+/// Set of known words and their properties
+///
+/// In files, words are listed in a `words` table keyed by the word:
+///
+/// ```toml
+/// [words.kiki]
+/// role = "content"
+/// size = "normal"
+/// glyph = "tp-wg-kiki"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lexicon {
+    #[serde(default)]
+    words: BTreeMap<String, WordEntry>,
+}
+
+impl Lexicon {
+    /// Create a lexicon without any words
+    pub fn empty() -> Self {
+        Self {
+            words: BTreeMap::new(),
+        }
+    }
+
+    /// Create the built-in lexicon (nimi ku suli)
+    pub fn builtin() -> Self {
+        let mut lexicon = Self::empty();
+
+        for word in WORDS {
+            let role = if PREPOSITIONS.contains(word) {
+                WordRole::Preposition
+            } else if OBJECT_MARKERS.contains(word) {
+                WordRole::ObjectMarker
+            } else if PREVERBS.contains(word) {
+                WordRole::Preverb
+            } else if CONTAINER_MARKERS.contains(word) {
+                WordRole::ContainerMarker
+            } else if PARTICLES.contains(word) {
+                WordRole::Particle
+            } else {
+                WordRole::Content
+            };
+            let size = if SMALL_MODIFIERS.contains(word) {
+                SizeClass::Small
+            } else if NARROW_MODIFIERS.contains(word) {
+                SizeClass::Narrow
+            } else {
+                SizeClass::Normal
+            };
+            lexicon.insert(word, WordEntry::new(role, size));
+        }
+        for mark in PUNCTUATION {
+            lexicon.insert(
+                mark,
+                WordEntry::new(WordRole::Punctuation, SizeClass::Normal),
+            );
+        }
+
+        lexicon
+    }
+
+    /// Parse a lexicon from TOML
+    pub fn from_toml(content: &str) -> Result<Self, LexiconError> {
+        toml::from_str(content).map_err(|e| LexiconError::Toml(e.to_string()))
+    }
+
+    /// Parse a lexicon from JSON
+    pub fn from_json(content: &str) -> Result<Self, LexiconError> {
+        serde_json::from_str(content).map_err(|e| LexiconError::Json(e.to_string()))
+    }
+
+    /// Load a lexicon from a `.toml` or `.json` file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, LexiconError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&content),
+            Some("json") => Self::from_json(&content),
+            other => Err(LexiconError::UnsupportedFormat(
+                other.unwrap_or_default().to_string(),
+            )),
+        }
+    }
+
+    /// Add or replace a word
+    pub fn insert(&mut self, word: &str, entry: WordEntry) {
+        self.words.insert(word.to_string(), entry);
+    }
+
+    /// Add or replace all words of another lexicon
+    pub fn extend(&mut self, other: Lexicon) {
+        self.words.extend(other.words);
+    }
+
+    pub fn get(&self, word: &str) -> Option<&WordEntry> {
+        self.words.get(word)
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains_key(word)
+    }
+
+    /// All known words in alphabetical order
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        self.words.keys().map(String::as_str)
+    }
+
+    pub fn role(&self, word: &str) -> Option<WordRole> {
+        self.get(word).map(|entry| entry.role)
+    }
+
+    pub fn is_preposition(&self, word: &str) -> bool {
+        self.role(word) == Some(WordRole::Preposition)
+    }
+
+    pub fn is_object_marker(&self, word: &str) -> bool {
+        self.role(word) == Some(WordRole::ObjectMarker)
+    }
+
+    pub fn is_preverb(&self, word: &str) -> bool {
+        self.role(word) == Some(WordRole::Preverb)
+    }
+
+    /// Whether a word opens a container (prepositions and pi)
+    pub fn opens_container(&self, word: &str) -> bool {
+        matches!(
+            self.role(word),
+            Some(WordRole::Preposition | WordRole::ContainerMarker)
+        )
+    }
+
+    /// Layout size class of a word (unknown words are `Normal`)
+    pub fn size_class(&self, word: &str) -> SizeClass {
+        self.get(word).map(|entry| entry.size).unwrap_or_default()
+    }
+
+    /// Glyph ID override for a word, if the lexicon defines one
+    pub fn glyph(&self, word: &str) -> Option<&str> {
+        self.get(word).and_then(|entry| entry.glyph.as_deref())
+    }
+}
+
+impl Default for Lexicon {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_roles_and_sizes() {
+        let lexicon = Lexicon::builtin();
+        assert!(lexicon.is_preposition("lon"));
+        assert!(lexicon.is_object_marker("li"));
+        assert!(lexicon.is_preverb("wile"));
+        assert!(lexicon.opens_container("pi"));
+        assert!(!lexicon.opens_container("en"));
+        assert_eq!(lexicon.size_class("lili"), SizeClass::Small);
+        assert_eq!(lexicon.size_class("en"), SizeClass::Narrow);
+        assert_eq!(lexicon.size_class("moku"), SizeClass::Normal);
+        assert_eq!(lexicon.role("."), Some(WordRole::Punctuation));
+    }
+
+    #[test]
+    fn test_load_toml_and_json() {
+        let toml = r#"
+            [words.kiki]
+            glyph = "tp-wg-kiki"
+
+            [words.lanpan]
+            role = "content"
+            size = "small"
+        "#;
+        let from_toml = Lexicon::from_toml(toml).unwrap();
+        assert_eq!(from_toml.role("kiki"), Some(WordRole::Content));
+        assert_eq!(from_toml.glyph("kiki"), Some("tp-wg-kiki"));
+        assert_eq!(from_toml.size_class("lanpan"), SizeClass::Small);
+
+        let json = r#"{"words": {"kiki": {"glyph": "tp-wg-kiki"}, "lanpan": {"size": "small"}}}"#;
+        assert_eq!(Lexicon::from_json(json).unwrap(), from_toml);
+    }
+
+    #[test]
+    fn test_extend_builtin() {
+        let mut lexicon = Lexicon::builtin();
+        assert!(!lexicon.contains("kiki"));
+        lexicon.extend(Lexicon::from_toml("[words.kiki]").unwrap());
+        assert!(lexicon.contains("kiki"));
+        assert!(lexicon.contains("moku"));
+    }
+
+    #[test]
+    fn test_unknown_role_is_rejected() {
+        assert!(Lexicon::from_toml("[words.kiki]\nrole = \"verb\"").is_err());
+    }
+}
@@ -1,21 +1,49 @@
 /// Sitelen Sitelen Renderer - Core Library
 ///
 /// This library converts Toki Pona text into the Sitelen Sitelen non-linear writing style.
+pub mod ast;
 pub mod config;
+pub mod document;
 pub mod glyphs;
+pub mod incremental;
 pub mod layout;
+pub mod lexicon;
+mod linearize;
+mod linebreak;
+pub mod lint;
+mod markup;
+mod noun_phrase;
+pub mod numbers;
 pub mod parser;
+pub mod prepositions;
+mod random;
 pub mod renderer;
+pub mod scoring;
+pub mod search;
+pub mod syllables;
 pub mod types;
+pub mod ucsur;
 
+pub use ast::{Ast, AstError, AST_VERSION};
 pub use config::{OutputFormat, RenderConfig};
+pub use document::{Block, Document};
 pub use glyphs::{init_glyph_registry, GlyphError, GlyphRegistry};
-pub use layout::LayoutEngine;
-pub use parser::{ParseError, Parser};
+pub use incremental::{EditError, LiveText, ParsedText, SentenceSplice, TextEdit};
+pub use layout::{Align, LayoutEngine, PageBuilder};
+pub use lexicon::{Lexicon, LexiconError, SizeClass, WordEntry, WordRole};
+pub use lint::{LintIssue, LintRule, Severity};
+pub use numbers::NumberSystem;
+pub use parser::{Diagnostic, DiagnosticKind, InputMode, NameDetection, ParseError, Parser};
+pub use prepositions::{PrepositionDecision, PrepositionRule, PrepositionUse};
 pub use renderer::{RenderError, Renderer};
-pub use types::{Layout, Sentence, SentencePart};
+pub use scoring::{AestheticScorer, CriterionScores, LayoutSampling, LayoutWeights, RankedLayout};
+pub use search::{LayoutScorer, SearchLimits, WeightedScorer};
+pub use syllables::{Syllabifier, SyllableMode};
+pub use types::{Layout, Page, Sentence, SentencePart, Span, Token, TokenKind};
 
 
+use std::collections::HashMap;
+
 /// Main pipeline for rendering Toki Pona text
 pub struct Pipeline {
     parser: Parser,
@@ -32,17 +60,69 @@ impl Pipeline {
 
     /// Create a new pipeline with custom configuration
     pub fn with_config(config: RenderConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_lexicon(config, Lexicon::default())
+    }
+
+    /// Create a new pipeline with custom configuration and lexicon
+    pub fn with_lexicon(
+        config: RenderConfig,
+        lexicon: Lexicon,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         // Embed the sprite file at compile time
         const SPRITE_CONTENT: &str = include_str!("../../images/glyphs.svg");
         init_glyph_registry(SPRITE_CONTENT)?;
 
+        let lexicon = std::sync::Arc::new(lexicon);
+        let mut layout_engine = LayoutEngine::with_lexicon(lexicon.clone());
+        layout_engine.set_scorer(WeightedScorer::new(config.optimal_ratio));
         Ok(Self {
-            parser: Parser::new(),
-            layout_engine: LayoutEngine::new(),
-            renderer: std::cell::RefCell::new(Renderer::new(config)),
+            parser: Parser::with_lexicon(lexicon.clone()),
+            layout_engine,
+            renderer: std::cell::RefCell::new(Renderer::with_lexicon(config, lexicon)),
         })
     }
 
+    /// Use another syllabifier for proper names (strict by default)
+    pub fn set_syllabifier(&mut self, syllabifier: impl Syllabifier + 'static) {
+        self.parser.set_syllabifier(syllabifier);
+    }
+
+    /// Choose how proper names are recognised (capitalised words by default)
+    pub fn set_name_detection(&mut self, detection: NameDetection) {
+        self.parser.set_name_detection(detection);
+    }
+
+    /// Recognise number phrases in the given counting system (off by default)
+    pub fn set_number_system(&mut self, system: NumberSystem) {
+        self.parser.set_number_system(system);
+    }
+
+    /// Read input as plain text (the default) or as structure markup
+    pub fn set_input_mode(&mut self, mode: InputMode) {
+        self.parser.set_input_mode(mode);
+    }
+
+    /// Bound the layout search for long sentences (every arrangement is tried by default,
+    /// up to `search::DEFAULT_MAX_NODES` placements per compound)
+    pub fn set_search_limits(&mut self, limits: SearchLimits) {
+        self.layout_engine.set_search_limits(limits);
+    }
+
+    /// Rank partial layouts with another scorer when the search is limited
+    pub fn set_layout_scorer(&mut self, scorer: impl LayoutScorer + 'static) {
+        self.layout_engine.set_scorer(scorer);
+    }
+
+    /// Force the use of preposition words, keyed by the byte offset where the word starts
+    pub fn set_preposition_overrides(&mut self, overrides: HashMap<usize, PrepositionUse>) {
+        self.parser.set_preposition_overrides(overrides);
+    }
+
+    /// How every preposition word in the text is used, and which rule decided it
+    pub fn preposition_decisions(&self, text: &str) -> Vec<PrepositionDecision> {
+        self.parser.preposition_decisions(text)
+    }
+
     /// Parse Toki Pona text into structured sentences
     pub fn parse(&self, text: &str) -> Result<Vec<Sentence>, ParseError> {
         self.parser.parse(text)
@@ -53,69 +133,167 @@ impl Pipeline {
         self.layout_engine.layout_compound(sentence)
     }
 
-    /// Select the best layout option based on optimal ratio
+    /// Select the best layout option for the optimal ratio, scored with
+    /// `RenderConfig::layout_weights` among the options within the ratio bounds
     pub fn select_best_layout<'a>(
         &self,
         options: &'a [LayoutOption],
         optimal_ratio: f64,
     ) -> Option<&'a LayoutOption> {
-        options.iter().min_by(|a, b| {
-            let a_diff = (a.ratio - optimal_ratio).abs();
-            let b_diff = (b.ratio - optimal_ratio).abs();
-            a_diff
-                .partial_cmp(&b_diff)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        })
+        self.aesthetic_scorer(optimal_ratio)
+            .best(self.acceptable_layouts(options))
     }
 
-    /// Render a layout to bytes
-    pub fn render(&self, layout: &Layout, format: OutputFormat) -> Result<Vec<u8>, RenderError> {
-        self.renderer.borrow_mut().render(layout, format)
+    /// The `count` best layout options within the ratio bounds, with the score of every
+    /// criterion, best first
+    pub fn rank_layouts<'a>(
+        &self,
+        options: &'a [LayoutOption],
+        count: usize,
+    ) -> Vec<RankedLayout<'a>> {
+        let optimal_ratio = self.renderer.borrow().config.optimal_ratio;
+        self.aesthetic_scorer(optimal_ratio)
+            .rank(self.acceptable_layouts(options), count)
     }
 
-    /// Render a single parsed sentence to bytes
-    pub fn render_sentence(
+    fn aesthetic_scorer(&self, optimal_ratio: f64) -> AestheticScorer {
+        AestheticScorer::new(optimal_ratio, self.renderer.borrow().config.layout_weights)
+    }
+
+    /// Draw up to `sampling.count` different layouts of the text, for many renderings of it
+    ///
+    /// The layout of every compound is drawn at random among those within the ratio
+    /// bounds, better scored ones more often, and never with the same arrangement twice.
+    /// A compound with fewer arrangements than layouts repeats them in turn, and no more
+    /// layouts are returned than the compound with the most arrangements has. The same
+    /// text and seed always give the same layouts.
+    pub fn sample_layouts(
         &self,
-        sentence: &Sentence,
-        format: OutputFormat,
-    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        let mut compounds = Vec::new();
+        text: &str,
+        sampling: &LayoutSampling,
+    ) -> Result<Vec<Layout>, ParseError> {
         let optimal_ratio = self.renderer.borrow().config.optimal_ratio;
+        let scorer = self.aesthetic_scorer(optimal_ratio);
+        let compounds = self.compound_options(text)?;
+        let picks: Vec<Vec<&LayoutOption>> = compounds
+            .iter()
+            .filter(|options| !options.is_empty())
+            .map(|options| {
+                let acceptable = self.acceptable_layouts(options);
+                let mut rng = Rng::for_text(sampling.seed, &acceptable[0].to_text());
+                scoring::sample(
+                    &scorer,
+                    acceptable,
+                    |option| self.layout_engine.option_key(option),
+                    sampling,
+                    &mut rng,
+                )
+            })
+            .collect();
 
-        // Split sentence into compounds at punctuation and select best layout per compound
-        let mut sentence_compound = Vec::new();
-        for part in &sentence.parts {
-            sentence_compound.push(part.clone());
-            if matches!(part, SentencePart::Punctuation { .. }) {
-                let compound_sentence = Sentence {
-                    parts: sentence_compound.clone(),
-                };
-                let options = self.layout(&compound_sentence);
-                if let Some(best) = self.select_best_layout(&options, optimal_ratio) {
-                    compounds.push(best.clone());
-                } else if let Some(first) = options.first() {
-                    compounds.push(first.clone());
-                }
-                sentence_compound.clear();
-            }
+        let count = picks.iter().map(Vec::len).max().unwrap_or(0);
+        Ok((0..count)
+            .map(|index| Layout {
+                compounds: picks
+                    .iter()
+                    .map(|options| options[index % options.len()].clone())
+                    .collect(),
+            })
+            .collect())
+    }
+
+    /// Options within `RenderConfig::min_ratio` and `max_ratio`, or the nearest ones
+    fn acceptable_layouts<'a>(&self, options: &'a [LayoutOption]) -> Vec<&'a LayoutOption> {
+        let renderer = self.renderer.borrow();
+        scoring::within_ratio_bounds(
+            options,
+            renderer.config.min_ratio,
+            renderer.config.max_ratio,
+        )
+    }
+
+    /// Layout drawn for a compound: the best one, or with `RenderConfig::random` one of
+    /// the acceptable layouts picked by a generator seeded with `RenderConfig::seed` and
+    /// the text of the compound
+    fn choose_layout<'a>(&self, options: &'a [LayoutOption]) -> Option<&'a LayoutOption> {
+        let (optimal_ratio, random, seed) = {
+            let config = &self.renderer.borrow().config;
+            (config.optimal_ratio, config.random, config.seed)
+        };
+        if !random {
+            return self.select_best_layout(options, optimal_ratio);
         }
-        if !sentence_compound.is_empty() {
-            let compound_sentence = Sentence {
-                parts: sentence_compound,
-            };
-            let options = self.layout(&compound_sentence);
-            if let Some(best) = self.select_best_layout(&options, optimal_ratio) {
+        let acceptable = self.acceptable_layouts(options);
+        let text = acceptable.first()?.to_text();
+        let index = Rng::for_text(seed, &text).below(acceptable.len());
+        Some(acceptable[index])
+    }
+
+    /// Render a layout to bytes
+    pub fn render(&self, layout: &Layout, format: OutputFormat) -> Result<Vec<u8>, RenderError> {
+        self.renderer.borrow_mut().render(layout, format)
+    }
+
+    /// Split a sentence into compounds at punctuation and choose the layout of every compound
+    fn layout_sentence(&self, sentence: &Sentence, compounds: &mut Vec<LayoutOption>) {
+        for compound in split_compounds(sentence) {
+            let options = self.layout(&compound);
+            if let Some(best) = self.choose_layout(&options) {
                 compounds.push(best.clone());
             } else if let Some(first) = options.first() {
                 compounds.push(first.clone());
             }
         }
+    }
+
+    /// Every layout option of every compound of the text, in the order they are drawn
+    ///
+    /// Pass the options of a compound to `rank_layouts` to compare them.
+    pub fn compound_options(&self, text: &str) -> Result<Vec<Vec<LayoutOption>>, ParseError> {
+        Ok(self
+            .parse(text)?
+            .iter()
+            .flat_map(split_compounds)
+            .map(|compound| self.layout(&compound))
+            .collect())
+    }
 
-        let layout = Layout { compounds };
+    /// Select the best layout of every compound of the sentences
+    fn layout_sentences(&self, sentences: &[Sentence]) -> Layout {
+        let mut compounds = Vec::new();
+        for sentence in sentences {
+            self.layout_sentence(sentence, &mut compounds);
+        }
+        Layout { compounds }
+    }
+
+    /// Parse text and select the layout that `render_text` draws
+    ///
+    /// `Layout::to_text` reads the layout back, for instance as alt text.
+    pub fn layout_text(&self, text: &str) -> Result<Layout, ParseError> {
+        Ok(self.layout_sentences(&self.parse(text)?))
+    }
+
+    /// Layout and render parsed sentences to bytes
+    fn render_sentences(
+        &self,
+        sentences: &[Sentence],
+        format: OutputFormat,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let layout = self.layout_sentences(sentences);
         let bytes = self.render(&layout, format)?;
         Ok(bytes)
     }
 
+    /// Render a single parsed sentence to bytes
+    pub fn render_sentence(
+        &self,
+        sentence: &Sentence,
+        format: OutputFormat,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.render_sentences(std::slice::from_ref(sentence), format)
+    }
+
     /// Complete pipeline: parse, layout, and render
     pub fn render_text(
         &self,
@@ -123,50 +301,200 @@ impl Pipeline {
         format: OutputFormat,
     ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
         let sentences = self.parse(text)?;
+        self.render_sentences(&sentences, format)
+    }
+
+    /// Check text for common mistakes and phrases that render poorly
+    pub fn lint(&self, text: &str) -> Vec<LintIssue> {
+        self.parser.lint(text)
+    }
+
+    /// Parse and lay out text that will be edited live
+    ///
+    /// Illegal words are drawn as placeholders, as with `render_text_with_diagnostics`;
+    /// the diagnostics are kept in `LiveText::parsed`.
+    pub fn live_text(&self, text: &str) -> LiveText {
+        let parsed = self.parser.parse_incremental(text);
+        let layouts = parsed
+            .sentences()
+            .iter()
+            .map(|sentence| self.layout_sentence_compounds(sentence))
+            .collect();
+        LiveText { parsed, layouts }
+    }
+
+    /// Apply an edit to live text, laying out only the sentences it replaced
+    pub fn edit_live_text(
+        &self,
+        live: &mut LiveText,
+        edit: &TextEdit,
+    ) -> Result<SentenceSplice, EditError> {
+        let splice = self.parser.apply_edit(&mut live.parsed, edit)?;
+        let layouts: Vec<_> = live.parsed.sentences()[splice.changed()]
+            .iter()
+            .map(|sentence| self.layout_sentence_compounds(sentence))
+            .collect();
+        splice.apply(&mut live.layouts, layouts);
+        Ok(splice)
+    }
 
+    /// Render live text from the layouts kept for its sentences
+    pub fn render_live_text(
+        &self,
+        live: &LiveText,
+        format: OutputFormat,
+    ) -> Result<Vec<u8>, RenderError> {
+        let layout = Layout {
+            compounds: live.compounds().cloned().collect(),
+        };
+        self.render(&layout, format)
+    }
+
+    fn layout_sentence_compounds(&self, sentence: &Sentence) -> Vec<LayoutOption> {
         let mut compounds = Vec::new();
-        let optimal_ratio = self.renderer.borrow().config.optimal_ratio;
+        self.layout_sentence(sentence, &mut compounds);
+        compounds
+    }
 
-        for sentence in sentences {
-            // Split sentence into compounds at punctuation marks (like JavaScript does)
-            let mut sentence_compound = Vec::new();
-
-            for part in &sentence.parts {
-                sentence_compound.push(part.clone());
-
-                // When we encounter punctuation, finalize current compound and start a new one
-                if matches!(part, SentencePart::Punctuation { .. }) {
-                    let compound_sentence = Sentence {
-                        parts: sentence_compound.clone(),
-                    };
-                    let options = self.layout(&compound_sentence);
-                    if let Some(best) = self.select_best_layout(&options, optimal_ratio) {
-                        compounds.push(best.clone());
-                    } else if let Some(first) = options.first() {
-                        compounds.push(first.clone());
+    /// Parse text into a document of headings, paragraphs and stanzas
+    pub fn parse_document(&self, text: &str) -> Result<Document, ParseError> {
+        self.parser.parse_document(text)
+    }
+
+    /// Parse a document, collecting diagnostics instead of failing on illegal words
+    pub fn parse_document_with_diagnostics(&self, text: &str) -> (Document, Vec<Diagnostic>) {
+        self.parser.parse_document_with_diagnostics(text)
+    }
+
+    /// Arrange a document on a page `RenderConfig::page_width` glyphs wide
+    ///
+    /// Paragraphs flow from left to right and wrap, every line of a stanza starts a
+    /// new row, and headings are centred and drawn larger, the more so the lower
+    /// their level. With a `RenderConfig::page_height`, rows fill the page column by
+    /// column. With `RenderConfig::fill_lines`, the layouts of the compounds are chosen
+    /// with the line breaks so that rows are filled evenly, and paragraphs are justified.
+    pub fn layout_document(&self, document: &Document) -> Page {
+        let (width, height, columns, fill_lines, optimal_ratio) = {
+            let config = &self.renderer.borrow().config;
+            (
+                config.page_width,
+                config.page_height,
+                config.columns,
+                config.fill_lines,
+                config.optimal_ratio,
+            )
+        };
+        let mut page = match height {
+            Some(height) => PageBuilder::with_columns(width, height, columns),
+            None => PageBuilder::new(width),
+        };
+        let scorer = self.aesthetic_scorer(optimal_ratio);
+        for block in &document.blocks {
+            let (scale, align) = match block {
+                Block::Heading { level, .. } => (1.0 + 0.5 / *level as f64, Align::Center),
+                Block::Paragraph { .. } if fill_lines => (1.0, Align::Justify),
+                _ => (1.0, Align::Left),
+            };
+            page.start_block();
+            for line in block.lines() {
+                if fill_lines {
+                    let compounds: Vec<Vec<LayoutOption>> = line
+                        .iter()
+                        .flat_map(split_compounds)
+                        .map(|compound| {
+                            let options = self.layout(&compound);
+                            self.acceptable_layouts(&options)
+                                .into_iter()
+                                .cloned()
+                                .collect()
+                        })
+                        .collect();
+                    page.push_paragraph(&compounds, &scorer, scale, align);
+                } else {
+                    let mut compounds = Vec::new();
+                    for sentence in line {
+                        self.layout_sentence(sentence, &mut compounds);
                     }
-                    sentence_compound.clear();
+                    page.push_line(&compounds, scale, align);
                 }
             }
+        }
+        page.finish()
+    }
 
-            // If there are remaining parts (no trailing punctuation), add them as a final compound
-            if !sentence_compound.is_empty() {
-                let compound_sentence = Sentence {
-                    parts: sentence_compound,
-                };
-                let options = self.layout(&compound_sentence);
-                if let Some(best) = self.select_best_layout(&options, optimal_ratio) {
-                    compounds.push(best.clone());
-                } else if let Some(first) = options.first() {
-                    compounds.push(first.clone());
-                }
-            }
+    /// Layout and render a document as a single page
+    pub fn render_document(
+        &self,
+        document: &Document,
+        format: OutputFormat,
+    ) -> Result<Vec<u8>, RenderError> {
+        let page = self.layout_document(document);
+        self.renderer.borrow_mut().render_page(&page, format)
+    }
+
+    /// Complete pipeline for whole texts: parse a document, lay it out and render the page
+    pub fn render_document_text(
+        &self,
+        text: &str,
+        format: OutputFormat,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let document = self.parse_document(text)?;
+        Ok(self.render_document(&document, format)?)
+    }
+
+    /// Parse text and export the sentences as a versioned JSON AST
+    pub fn parse_to_json(&self, text: &str) -> Result<String, ParseError> {
+        Ok(Ast::new(self.parse(text)?).to_json())
+    }
+
+    /// Render sentences read from a JSON AST, skipping the text parser
+    pub fn render_json(
+        &self,
+        json: &str,
+        format: OutputFormat,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let ast = Ast::from_json(json)?;
+        self.render_sentences(&ast.sentences, format)
+    }
+
+    /// Parse text, collecting diagnostics instead of failing on illegal words
+    pub fn parse_with_diagnostics(&self, text: &str) -> (Vec<Sentence>, Vec<Diagnostic>) {
+        self.parser.parse_with_diagnostics(text)
+    }
+
+    /// Complete pipeline that renders a best-effort result for text with illegal words
+    ///
+    /// Illegal words and syllables are drawn as placeholder glyphs and reported
+    /// in the returned diagnostics.
+    pub fn render_text_with_diagnostics(
+        &self,
+        text: &str,
+        format: OutputFormat,
+    ) -> Result<(Vec<u8>, Vec<Diagnostic>), Box<dyn std::error::Error>> {
+        let (sentences, diagnostics) = self.parse_with_diagnostics(text);
+        let bytes = self.render_sentences(&sentences, format)?;
+        Ok((bytes, diagnostics))
+    }
+}
+
+/// Split a sentence into compounds, each ending at punctuation
+fn split_compounds(sentence: &Sentence) -> Vec<Sentence> {
+    let mut compounds = Vec::new();
+    let mut sentence_compound = Vec::new();
+    for part in &sentence.parts {
+        sentence_compound.push(part.clone());
+
+        // When we encounter punctuation, finalize current compound and start a new one
+        if matches!(part, SentencePart::Punctuation { .. }) {
+            compounds.push(Sentence::new(std::mem::take(&mut sentence_compound)));
         }
+    }
 
-        let layout = Layout { compounds };
-        let bytes = self.render(&layout, format)?;
-        Ok(bytes)
+    // If there are remaining parts (no trailing punctuation), add them as a final compound
+    if !sentence_compound.is_empty() {
+        compounds.push(Sentence::new(sentence_compound));
     }
+    compounds
 }
 
 impl Default for Pipeline {
@@ -175,4 +503,5 @@ impl Default for Pipeline {
     }
 }
 
+use crate::random::Rng;
 use crate::types::LayoutOption;
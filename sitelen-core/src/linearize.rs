@@ -0,0 +1,205 @@
+//! Reading laid out glyphs back as Toki Pona text
+//!
+//! `Layout::to_text` and `LayoutOption::to_text` walk the container tree in reading
+//! order, which is the order of the units in each container. Separators are written
+//! before the words they hold (`li`, `e`, prepositions, `pi`, the `o` of a command),
+//! except the `o` of an address, which follows them. Cartouches become capitalised names, punctuation
+//! glyphs become marks, and quotations are put back between quote marks.
+//!
+//! The text reads the same as the source but is not a copy of it: spelling such as
+//! UCSUR or `{Name}` markup is not kept, and a comma the parser dropped stays dropped.
+
+use crate::layout::{COORDINATION_SEPARATOR, IMPERATIVE_SEPARATOR, QUOTATION_SEPARATOR};
+use crate::parser::QUOTE_SEPARATOR;
+use crate::types::{LayoutOption, LayoutUnit, PlacedUnit};
+
+/// Punctuation glyphs and the marks they are written as
+const MARKS: &[(&str, &str)] = &[
+    ("period", "."),
+    ("exclamation", "!"),
+    ("question", "?"),
+    ("comma", ","),
+    ("colon", ":"),
+    ("banner", "#"),
+];
+
+enum Piece {
+    Word(String),
+    Mark(&'static str),
+    OpenQuote,
+    CloseQuote,
+}
+
+/// Text of compounds drawn one after another
+pub(crate) fn to_text(compounds: &[LayoutOption]) -> String {
+    let mut reader = Reader { pieces: Vec::new() };
+    for compound in compounds {
+        reader.units(&compound.state.units, None);
+    }
+    join(&reader.pieces)
+}
+
+struct Reader {
+    pieces: Vec<Piece>,
+}
+
+impl Reader {
+    /// Read the units of a container with the given separator
+    fn units(&mut self, units: &[PlacedUnit], separator: Option<&str>) {
+        for placed in units {
+            self.unit(&placed.unit, separator);
+        }
+    }
+
+    /// Read a unit inside a container with the separator `enclosing`
+    ///
+    /// Parts split from a phrase, such as a name and the words before it, keep the
+    /// separator of the phrase; it is written once, by the phrase.
+    fn unit(&mut self, unit: &LayoutUnit, enclosing: Option<&str>) {
+        let (inner, separator) = match unit {
+            LayoutUnit::WordGlyph { token, .. } | LayoutUnit::SyllableGlyph { token, .. } => {
+                self.pieces.push(word(token));
+                return;
+            }
+            LayoutUnit::Punctuation { tokens, .. } => {
+                self.pieces.extend(tokens.iter().map(|token| word(token)));
+                return;
+            }
+            LayoutUnit::Number { units, .. } => {
+                self.units(units, enclosing);
+                return;
+            }
+            LayoutUnit::Container {
+                units, separator, ..
+            } => (units, separator.as_deref()),
+        };
+
+        match separator {
+            Some("cartouche") => self.pieces.push(Piece::Word(name(inner))),
+            Some(QUOTATION_SEPARATOR) => {
+                self.pieces.push(Piece::OpenQuote);
+                self.units(inner, None);
+                self.pieces.push(Piece::CloseQuote);
+            }
+            Some("o") => {
+                self.units(inner, separator);
+                self.pieces.push(Piece::Word("o".to_string()));
+            }
+            // The quote container holds the quotations of a clause and the words around them
+            Some(QUOTE_SEPARATOR) | Some(COORDINATION_SEPARATOR) | None => {
+                self.units(inner, separator)
+            }
+            Some(word) => {
+                // A command right after an address shares its o: `jan Ali o kama`
+                let shared = word == IMPERATIVE_SEPARATOR
+                    && matches!(self.pieces.last(), Some(Piece::Word(last)) if last == "o");
+                if separator != enclosing && !shared {
+                    let word = if word == IMPERATIVE_SEPARATOR {
+                        "o"
+                    } else {
+                        word
+                    };
+                    self.pieces.push(Piece::Word(word.to_string()));
+                }
+                self.units(inner, separator);
+            }
+        }
+    }
+}
+
+fn word(token: &str) -> Piece {
+    match MARKS.iter().find(|(glyph, _)| *glyph == token) {
+        Some((_, mark)) => Piece::Mark(mark),
+        None => Piece::Word(token.to_string()),
+    }
+}
+
+/// Name spelled by the syllables of a cartouche
+fn name(units: &[PlacedUnit]) -> String {
+    let mut name = String::new();
+    for placed in units {
+        if let LayoutUnit::SyllableGlyph { token, .. } = &placed.unit {
+            name.push_str(token);
+        }
+    }
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name,
+    }
+}
+
+/// Write words separated by spaces, with marks and closing quotes attached to the word before
+fn join(pieces: &[Piece]) -> String {
+    let mut text = String::new();
+    let mut after_open_quote = false;
+    for piece in pieces {
+        match piece {
+            Piece::Word(word) => {
+                if !text.is_empty() && !after_open_quote {
+                    text.push(' ');
+                }
+                text.push_str(word);
+            }
+            Piece::Mark(mark) => text.push_str(mark),
+            Piece::OpenQuote => {
+                if !text.is_empty() {
+                    text.push(' ');
+                }
+                text.push('"');
+            }
+            Piece::CloseQuote => text.push('"'),
+        }
+        after_open_quote = matches!(piece, Piece::OpenQuote);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::layout::LayoutEngine;
+    use crate::parser::Parser;
+    use crate::types::Layout;
+
+    fn round_trip(text: &str) -> String {
+        let engine = LayoutEngine::new();
+        let compounds = Parser::new()
+            .parse(text)
+            .unwrap()
+            .iter()
+            .map(|sentence| engine.layout_compound(sentence).remove(0))
+            .collect();
+        Layout { compounds }.to_text()
+    }
+
+    #[test]
+    fn test_to_text() {
+        let cases = [
+            ("mi moku e kili.", "mi moku e kili."),
+            (
+                "ona li lon tomo pi jan pona!",
+                "ona li lon tomo pi jan pona!",
+            ),
+            ("tenpo ni la, mi tawa", "tenpo ni la mi tawa"),
+            ("jan Ali o, moku.", "jan Ali o, moku."),
+            ("o moku e kili a!", "o moku e kili a!"),
+            ("toki, jan Ali o!", "toki, jan Ali o!"),
+            ("toki, jan Ali o kama.", "toki, jan Ali o kama."),
+            ("jan Ali o, o kama.", "jan Ali o, o kama."),
+            ("mi en sina li wile moku.", "mi en sina li wile moku."),
+            (
+                "jan Pona li toki e ni: \"mi pona.\"",
+                "jan Pona li toki e ni: \"mi pona.\"",
+            ),
+            ("«toki» li pona. sina pona?", "\"toki\" li pona. sina pona?"),
+            (
+                "ona li toki e \"toki\" tawa meli.",
+                "ona li toki e \"toki\" tawa meli.",
+            ),
+            ("mi jan [o-la-pu].", "mi jan Olapu."),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(round_trip(text), expected, "{text:?}");
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Breaking a paragraph of compounds into rows
+//!
+//! Every compound of a paragraph can be drawn with any of its layout options, each
+//! with its own width. As in the Knuth–Plass algorithm for the words of a paragraph,
+//! the rows and the option of every compound are chosen together, for the whole
+//! paragraph, to minimise the sum of the demerits of the rows:
+//!
+//! - a row with space left at its end has a badness of `100 * slack^3`, the slack
+//!   being the fraction of the row left empty; the last row of a paragraph has none;
+//! - a row costs `(LINE_PENALTY + badness)^2`, so fewer rows are better;
+//! - every compound adds `OPTION_DEMERITS` times the score of its option, so a
+//!   poor layout is only chosen when it fills a row much better.
+//!
+//! The widths of the rows explored from one break are merged into small buckets,
+//! keeping the cheapest choice of options for each, which keeps the search linear in
+//! the length of the paragraph.
+
+use std::collections::hash_map::{Entry, HashMap};
+
+/// Demerits of every row
+const LINE_PENALTY: f64 = 10.0;
+/// Demerits per unit of the score of a compound's option
+const OPTION_DEMERITS: f64 = 400.0;
+/// Options considered for every compound, the best scored with distinct sizes
+const MAX_CANDIDATES: usize = 12;
+/// Row widths closer than this fraction of the row are merged
+const WIDTH_BUCKET: f64 = 0.005;
+
+/// Layout option a compound can be drawn with, by index into its options
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Candidate {
+    pub(crate) index: usize,
+    pub(crate) width: f64,
+    pub(crate) height: f64,
+    /// Score of the option; lower is better
+    pub(crate) cost: f64,
+}
+
+/// Row of compounds `start..start + choices.len()` and the options they are drawn with
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Row {
+    pub(crate) start: usize,
+    /// Index of the option chosen for every compound of the row
+    pub(crate) choices: Vec<usize>,
+}
+
+/// Best candidates of every compound: the lowest costs, one per size
+pub(crate) fn prune(mut candidates: Vec<Candidate>) -> Vec<Candidate> {
+    candidates.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+    let mut kept: Vec<Candidate> = Vec::new();
+    for candidate in candidates {
+        let same_size = kept.iter().any(|other| {
+            (other.width - candidate.width).abs() < 1e-6
+                && (other.height - candidate.height).abs() < 1e-6
+        });
+        if !same_size {
+            kept.push(candidate);
+            if kept.len() == MAX_CANDIDATES {
+                break;
+            }
+        }
+    }
+    kept
+}
+
+/// Rows of compounds `width` wide with `gap` between compounds, and their options
+///
+/// Every compound needs at least one candidate. A candidate wider than the row is only
+/// set alone on a row.
+pub(crate) fn break_rows(compounds: &[Vec<Candidate>], width: f64, gap: f64) -> Vec<Row> {
+    let count = compounds.len();
+
+    // Cheapest way to break before every compound: demerits, start of the last row and
+    // options of that row
+    let mut best: Vec<Option<(f64, usize, Vec<usize>)>> = vec![None; count + 1];
+    best[0] = Some((0.0, 0, Vec::new()));
+
+    for start in 0..count {
+        let Some((before, _, _)) = best[start].clone() else {
+            continue;
+        };
+        // Partial rows from `start`: width, demerits of the options and their choices
+        let mut rows: Vec<(f64, f64, Vec<usize>)> = vec![(0.0, 0.0, Vec::new())];
+        for end in start..count {
+            let mut next: Vec<(f64, f64, Vec<usize>)> = Vec::new();
+            let mut buckets: HashMap<i64, usize> = HashMap::new();
+            for (row_width, demerits, choices) in &rows {
+                for candidate in &compounds[end] {
+                    let first = choices.is_empty();
+                    let new_width = if first {
+                        candidate.width
+                    } else {
+                        row_width + gap + candidate.width
+                    };
+                    if !first && new_width > width + 1e-9 {
+                        continue;
+                    }
+                    let new_demerits = demerits + OPTION_DEMERITS * candidate.cost;
+                    let bucket = (new_width / (width * WIDTH_BUCKET)).round() as i64;
+                    let row = (new_width, new_demerits, extend(choices, candidate.index));
+                    match buckets.entry(bucket) {
+                        Entry::Occupied(entry) => {
+                            let known = &mut next[*entry.get()];
+                            if new_demerits < known.1 {
+                                *known = row;
+                            }
+                        }
+                        Entry::Vacant(entry) => {
+                            entry.insert(next.len());
+                            next.push(row);
+                        }
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            rows = next;
+
+            let last = end + 1 == count;
+            let row = rows
+                .iter()
+                .map(|(row_width, demerits, choices)| {
+                    (demerits + row_demerits(*row_width, width, last), choices)
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+            if let Some((demerits, choices)) = row {
+                let total = before + demerits;
+                if best[end + 1]
+                    .as_ref()
+                    .is_none_or(|(known, _, _)| total < *known)
+                {
+                    best[end + 1] = Some((total, start, choices.clone()));
+                }
+            }
+        }
+    }
+
+    // Follow the breaks back from the end of the paragraph
+    let mut rows = Vec::new();
+    let mut end = count;
+    while end > 0 {
+        let Some((_, start, choices)) = best[end].clone() else {
+            break;
+        };
+        rows.push(Row { start, choices });
+        end = start;
+    }
+    rows.reverse();
+    rows
+}
+
+fn extend(choices: &[usize], index: usize) -> Vec<usize> {
+    let mut choices = choices.to_vec();
+    choices.push(index);
+    choices
+}
+
+/// Demerits of a row `row_width` wide on a row `width` wide
+fn row_demerits(row_width: f64, width: f64, last: bool) -> f64 {
+    let slack = ((width - row_width) / width).clamp(0.0, 1.0);
+    let badness = if last { 0.0 } else { 100.0 * slack.powi(3) };
+    (LINE_PENALTY + badness).powi(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(sizes: &[(f64, f64)]) -> Vec<Candidate> {
+        sizes
+            .iter()
+            .enumerate()
+            .map(|(index, &(width, cost))| Candidate {
+                index,
+                width,
+                height: 1.0,
+                cost,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_break_rows() {
+        // Three compounds of width 3 fit two to a row of 7
+        let fixed = vec![candidates(&[(3.0, 0.0)]); 3];
+        let rows = break_rows(&fixed, 7.0, 1.0);
+        assert_eq!(
+            rows,
+            vec![
+                Row {
+                    start: 0,
+                    choices: vec![0, 0]
+                },
+                Row {
+                    start: 2,
+                    choices: vec![0]
+                },
+            ]
+        );
+
+        // A slightly worse, narrower option lets all three share a row
+        let flexible = vec![candidates(&[(3.0, 0.0), (2.0, 0.1)]); 3];
+        let rows = break_rows(&flexible, 9.0, 1.0);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].choices.iter().filter(|&&i| i == 1).count(), 2);
+
+        // The first row is filled rather than the last
+        let rows = break_rows(&vec![candidates(&[(4.0, 0.0), (6.0, 0.05)]); 3], 10.0, 0.0);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].choices.len(), 2);
+
+        // A compound wider than the row is set alone
+        let wide = vec![candidates(&[(2.0, 0.0)]), candidates(&[(12.0, 0.0)])];
+        assert_eq!(break_rows(&wide, 10.0, 0.5).len(), 2);
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut many = candidates(&[(1.0, 0.5), (1.0, 0.1), (2.0, 0.2)]);
+        many.extend((0..20).map(|i| Candidate {
+            index: 3 + i,
+            width: 3.0 + i as f64,
+            height: 1.0,
+            cost: 1.0,
+        }));
+        let kept = prune(many);
+        assert_eq!(kept.len(), MAX_CANDIDATES);
+        assert_eq!(kept[0].index, 1);
+        assert_eq!(kept[1].index, 2);
+        assert!(kept.iter().all(|candidate| candidate.index != 0));
+    }
+}
@@ -0,0 +1,371 @@
+//! Style checks for Toki Pona text
+//!
+//! `Parser::lint` parses text and runs the checks below on the sentences, so they see
+//! the same structure that is drawn. Every [`LintIssue`] names its [`LintRule`], a
+//! [`Severity`] and the source span it is about. Illegal words are not lint issues;
+//! they are reported by `Parser::parse_with_diagnostics`.
+
+use crate::types::{Sentence, SentencePart, Span, Token};
+use serde::Serialize;
+
+/// Verbs that read as incomplete without an object introduced by `e`
+const NEEDS_OBJECT: &[&str] = &["jo", "lanpan", "olin", "pana"];
+
+/// Words a noun phrase can hold before its glyphs are drawn too small to read
+pub const MAX_PHRASE_WORDS: usize = 4;
+
+/// How serious an issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Valid, but likely to render poorly
+    Hint,
+    /// Probably not what was meant
+    Warning,
+    /// Not grammatical Toki Pona
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Severity::Hint => "hint",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// The check behind an issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintRule {
+    /// `li` after a bare `mi` or `sina` subject: `mi li moku`
+    SubjectLi,
+    /// Verb that needs an object without `e`: `ona li pana`
+    MissingObject,
+    /// Name that is not the modifier of a head noun: `Sonja li pona` for `jan Sonja li pona`
+    NameWithoutHead,
+    /// More than `MAX_PHRASE_WORDS` words drawn in one container
+    LongNounPhrase,
+}
+
+impl LintRule {
+    pub fn severity(&self) -> Severity {
+        match self {
+            LintRule::SubjectLi => Severity::Error,
+            LintRule::MissingObject | LintRule::NameWithoutHead => Severity::Warning,
+            LintRule::LongNounPhrase => Severity::Hint,
+        }
+    }
+}
+
+/// A style problem found in parsed text
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LintIssue {
+    pub rule: LintRule,
+    pub severity: Severity,
+    pub message: String,
+    /// Location of the problem in the input
+    pub span: Span,
+    /// Index of the sentence in the parse result
+    pub sentence: usize,
+}
+
+impl std::fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} at {}..{} in sentence {}",
+            self.severity,
+            self.message,
+            self.span.start,
+            self.span.end,
+            self.sentence + 1
+        )
+    }
+}
+
+/// Check sentences parsed from `text`, returning the issues in text order
+pub fn lint(text: &str, sentences: &[Sentence]) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    for (index, sentence) in sentences.iter().enumerate() {
+        let mut linter = Linter {
+            text,
+            sentence: index,
+            issues: &mut issues,
+        };
+        linter.sentence(&sentence.parts);
+    }
+    issues.sort_by_key(|issue| (issue.sentence, issue.span.start));
+    issues
+}
+
+struct Linter<'a> {
+    text: &'a str,
+    sentence: usize,
+    issues: &'a mut Vec<LintIssue>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: LintRule, span: Span, message: String) {
+        self.issues.push(LintIssue {
+            rule,
+            severity: rule.severity(),
+            message,
+            span,
+            sentence: self.sentence,
+        });
+    }
+
+    /// Check the clauses of a sentence, separated by `la`, commas and other punctuation
+    fn sentence(&mut self, parts: &[SentencePart]) {
+        for clause in parts.split(|part| matches!(part, SentencePart::Punctuation { .. })) {
+            self.clause(clause);
+        }
+        self.siblings(parts, true);
+    }
+
+    fn clause(&mut self, parts: &[SentencePart]) {
+        for (i, part) in parts.iter().enumerate() {
+            let predicate = match part {
+                SentencePart::Subject { .. } if i == 0 => {
+                    if let Some(pronoun) = bare_pronoun(part) {
+                        if let Some(li @ SentencePart::ObjectMarker { separator, .. }) =
+                            parts.get(1)
+                        {
+                            if separator == "li" {
+                                self.report(
+                                    LintRule::SubjectLi,
+                                    part.span().join(li.span()),
+                                    format!("'li' is not used after a bare '{}'", pronoun.text),
+                                );
+                            }
+                        }
+                    }
+                    pronoun_predicate(part)
+                }
+                SentencePart::ObjectMarker { separator, .. }
+                    if separator == "li" || separator == "o" =>
+                {
+                    verb(part)
+                }
+                _ => None,
+            };
+            let Some(verb) = predicate else {
+                continue;
+            };
+
+            // The object follows the verb phrase, up to the next predicate
+            let has_object = parts[i + 1..]
+                .iter()
+                .take_while(|part| !is_marker(part, "li") && !is_marker(part, "o"))
+                .any(|part| is_marker(part, "e"));
+            if !has_object && NEEDS_OBJECT.contains(&verb.text.as_str()) {
+                self.report(
+                    LintRule::MissingObject,
+                    verb.span,
+                    format!("'{}' needs an object introduced by 'e'", verb.text),
+                );
+            }
+        }
+    }
+
+    /// Check names and long phrases among parts drawn side by side, and in nested parts
+    ///
+    /// A name directly after `li` needs no head: `nimi mi li Sonja`.
+    fn siblings(&mut self, parts: &[SentencePart], names_need_head: bool) {
+        for (i, part) in parts.iter().enumerate() {
+            if i == 0 && names_need_head && is_name(part) {
+                let span = part.span();
+                let name = self.text.get(span.start..span.end).unwrap_or_default();
+                self.report(
+                    LintRule::NameWithoutHead,
+                    span,
+                    format!("name '{}' has no head noun such as jan, ma or toki", name),
+                );
+            }
+
+            match part {
+                SentencePart::Subject { parts: None, .. }
+                | SentencePart::ObjectMarker { parts: None, .. }
+                | SentencePart::PrepPhrase { parts: None, .. }
+                    if !is_name(part) =>
+                {
+                    self.long_phrase(part)
+                }
+                SentencePart::Subject {
+                    parts: Some(parts), ..
+                }
+                | SentencePart::PrepPhrase {
+                    parts: Some(parts), ..
+                }
+                | SentencePart::Preverb { parts, .. }
+                | SentencePart::Coordination { parts, .. } => self.siblings(parts, true),
+                SentencePart::ObjectMarker {
+                    separator,
+                    parts: Some(parts),
+                    ..
+                } => self.siblings(parts, separator != "li"),
+                SentencePart::Quotation { sentences, .. } => {
+                    for sentence in sentences {
+                        self.sentence(&sentence.parts);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn long_phrase(&mut self, part: &SentencePart) {
+        let words = part.tokens().len();
+        if words > MAX_PHRASE_WORDS {
+            self.report(
+                LintRule::LongNounPhrase,
+                part.span(),
+                format!("{words} words in one container are drawn small; regroup them with 'pi'"),
+            );
+        }
+    }
+}
+
+/// Whether a part is an object marker container of the given word
+fn is_marker(part: &SentencePart, word: &str) -> bool {
+    matches!(part, SentencePart::ObjectMarker { separator, .. } if separator == word)
+}
+
+/// Subject that is only `mi` or `sina`
+fn bare_pronoun(part: &SentencePart) -> Option<&Token> {
+    match part.tokens() {
+        [pronoun] if pronoun.is_word("mi") || pronoun.is_word("sina") => Some(pronoun),
+        _ => None,
+    }
+}
+
+/// Verb of a `mi` or `sina` clause, which the parser keeps in the subject container
+fn pronoun_predicate(part: &SentencePart) -> Option<&Token> {
+    let is_pronoun = |token: &Token| token.is_word("mi") || token.is_word("sina");
+    match part {
+        SentencePart::Subject {
+            tokens,
+            parts: None,
+            ..
+        } => match tokens.as_slice() {
+            [pronoun, verb, ..] if is_pronoun(pronoun) => Some(verb),
+            _ => None,
+        },
+        SentencePart::Subject {
+            parts: Some(parts), ..
+        } => match parts.as_slice() {
+            [subject, predicate, ..] if subject.tokens().first().is_some_and(is_pronoun) => {
+                verb(predicate)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// First word of a verb phrase, looking past preverbs
+fn verb(part: &SentencePart) -> Option<&Token> {
+    match part {
+        SentencePart::Preverb { parts, .. } => parts.iter().find_map(verb),
+        SentencePart::Subject {
+            parts: Some(parts), ..
+        }
+        | SentencePart::ObjectMarker {
+            parts: Some(parts), ..
+        } => parts.first().and_then(verb),
+        SentencePart::Subject { tokens, .. }
+        | SentencePart::ObjectMarker { tokens, .. }
+        | SentencePart::Negation { tokens, .. }
+        | SentencePart::AlaQuestion { tokens, .. } => tokens.first(),
+        _ => None,
+    }
+}
+
+/// Whether a part is a name drawn in a cartouche
+fn is_name(part: &SentencePart) -> bool {
+    match part {
+        SentencePart::Subject {
+            separator: Some(separator),
+            ..
+        }
+        | SentencePart::ObjectMarker { separator, .. }
+        | SentencePart::PrepPhrase { separator, .. } => separator == "cartouche",
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn rules(text: &str) -> Vec<(LintRule, &str)> {
+        Parser::new()
+            .lint(text)
+            .into_iter()
+            .map(|issue| (issue.rule, &text[issue.span.start..issue.span.end]))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_rules() {
+        let cases: &[(&str, &[(LintRule, &str)])] = &[
+            ("mi li moku.", &[(LintRule::SubjectLi, "mi li moku")]),
+            (
+                "sina li wile pana e kili.",
+                &[(LintRule::SubjectLi, "sina li wile pana")],
+            ),
+            ("ona li pana.", &[(LintRule::MissingObject, "pana")]),
+            ("mi wile olin.", &[(LintRule::MissingObject, "olin")]),
+            ("tenpo ni la o pana.", &[(LintRule::MissingObject, "pana")]),
+            (
+                "ona li pana e kili li jo.",
+                &[(LintRule::MissingObject, "jo")],
+            ),
+            ("Sonja li pona.", &[(LintRule::NameWithoutHead, "Sonja")]),
+            (
+                "mi toki tawa Sonja.",
+                &[(LintRule::NameWithoutHead, "Sonja")],
+            ),
+            (
+                "jan pona mute lili suli li kama.",
+                &[(LintRule::LongNounPhrase, "jan pona mute lili suli")],
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(rules(text), *expected, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn test_good_text_has_no_issues() {
+        for text in [
+            "mi moku.",
+            "ona li pana e kili tawa sina.",
+            "jan Sonja li pona.",
+            "mi toki tawa ma Kanata.",
+            "jan pi ma tomo suli li kama.",
+            "mi pana e ni: \"mi jo e tomo.\"",
+            "nimi mi li Sonja.",
+        ] {
+            assert_eq!(rules(text), [], "{text:?}");
+        }
+    }
+
+    #[test]
+    fn test_issues_carry_severity_and_sentence() {
+        let issues = Parser::new().lint("mi moku. mi li pona. ona li pana.");
+        let found: Vec<_> = issues
+            .iter()
+            .map(|issue| (issue.sentence, issue.severity))
+            .collect();
+        assert_eq!(found, [(1, Severity::Error), (2, Severity::Warning)]);
+        assert_eq!(
+            issues[0].to_string(),
+            "error: 'li' is not used after a bare 'mi' at 9..19 in sentence 2"
+        );
+    }
+}
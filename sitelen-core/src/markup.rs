@@ -0,0 +1,307 @@
+//! Bracket markup that spells out the structure of a sentence
+//!
+//! In markup input mode the author groups words explicitly instead of relying on
+//! the automatic parse:
+//!
+//! ```text
+//! (mi) li[moku] e(kili) lon[tomo].
+//! ```
+//!
+//! A group is written with `(...)` or `[...]`; both mean the same. A word directly
+//! before the opening bracket is the separator of the group, which decides the
+//! part it becomes (see `Parser::set_input_mode`). Groups nest, names are written
+//! as `{Ana}`, and `,`, `:` and a `la` outside any group are punctuation. This
+//! module only reads the brackets; the parser turns the groups into sentence parts.
+
+use crate::types::{Span, Token, TokenKind};
+
+/// A word, punctuation mark or group in markup
+#[derive(Debug, Clone)]
+pub(crate) enum Node {
+    /// Word or `{Name}`
+    Word(Token),
+    /// Comma, colon or sentence terminator
+    Mark(Token),
+    /// Bracketed group with the separator word written before it
+    Group {
+        separator: Option<Token>,
+        children: Vec<Node>,
+        span: Span,
+    },
+}
+
+impl Node {
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            Node::Word(token) | Node::Mark(token) => token.span,
+            Node::Group { span, .. } => *span,
+        }
+    }
+}
+
+/// A sentence of markup, including its terminator if it has one
+#[derive(Debug)]
+pub(crate) struct MarkupSentence {
+    pub nodes: Vec<Node>,
+    pub span: Span,
+}
+
+/// A syntax problem; reading continues as if the markup had been written correctly
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MarkupError {
+    pub message: String,
+    pub span: Span,
+}
+
+/// Read the markup in `text[start..end]` into sentences of nodes
+///
+/// Unbalanced brackets are reported and repaired: a stray closing bracket is
+/// skipped and groups still open at the end of a sentence are closed there.
+pub(crate) fn parse(
+    text: &str,
+    start: usize,
+    end: usize,
+) -> (Vec<MarkupSentence>, Vec<MarkupError>) {
+    let mut reader = Reader::default();
+
+    for (offset, c) in text[start..end].char_indices() {
+        let pos = start + offset;
+        if reader.in_name(text, pos, c) {
+            continue;
+        }
+        match c {
+            '(' | '[' => {
+                // A word written directly before the bracket is its separator
+                let separator = reader.take_word(text, pos);
+                let close = if c == '(' { ')' } else { ']' };
+                reader.frames.push(Frame {
+                    close,
+                    separator,
+                    children: Vec::new(),
+                    start: pos,
+                });
+            }
+            ')' | ']' => {
+                reader.flush_word(text, pos);
+                match reader.frames.last() {
+                    Some(frame) if frame.close == c => reader.close_group(pos + 1),
+                    Some(frame) => {
+                        let expected = frame.close;
+                        reader.error(format!("expected '{expected}'"), pos, pos + 1);
+                        reader.close_group(pos + 1);
+                    }
+                    None => reader.error(format!("unexpected '{c}'"), pos, pos + 1),
+                }
+            }
+            '{' => {
+                reader.flush_word(text, pos);
+                reader.name_start = Some(pos);
+            }
+            ',' | ':' | '.' | '!' | '?' | '#' => {
+                reader.flush_word(text, pos);
+                let span = Span::new(pos, pos + 1);
+                if !reader.frames.is_empty() {
+                    reader.error(format!("'{c}' inside a group"), pos, pos + 1);
+                    if matches!(c, ',' | ':') {
+                        continue;
+                    }
+                    reader.close_all(pos);
+                }
+                let name = match c {
+                    ',' => "comma",
+                    ':' => "colon",
+                    '.' => "period",
+                    '!' => "exclamation",
+                    '?' => "question",
+                    _ => "banner",
+                };
+                if matches!(c, ',' | ':') || !reader.nodes.is_empty() {
+                    reader.push(Node::Mark(Token::with_kind(
+                        name,
+                        span,
+                        TokenKind::Punctuation,
+                    )));
+                }
+                if !matches!(c, ',' | ':') {
+                    reader.end_sentence();
+                }
+            }
+            c if c.is_whitespace() => reader.flush_word(text, pos),
+            _ => {
+                reader.word_start.get_or_insert(pos);
+            }
+        }
+    }
+
+    if let Some(start) = reader.name_start.take() {
+        reader.error("unclosed '{'".to_string(), start, end);
+    }
+    reader.flush_word(text, end);
+    reader.close_all(end);
+    reader.end_sentence();
+
+    (reader.sentences, reader.errors)
+}
+
+/// Group being read
+#[derive(Debug)]
+struct Frame {
+    close: char,
+    separator: Option<Token>,
+    children: Vec<Node>,
+    start: usize,
+}
+
+#[derive(Debug, Default)]
+struct Reader {
+    sentences: Vec<MarkupSentence>,
+    errors: Vec<MarkupError>,
+    /// Top-level nodes of the current sentence
+    nodes: Vec<Node>,
+    frames: Vec<Frame>,
+    word_start: Option<usize>,
+    name_start: Option<usize>,
+}
+
+impl Reader {
+    /// Collect the letters of a `{Name}`, returning whether `c` was part of it
+    fn in_name(&mut self, text: &str, pos: usize, c: char) -> bool {
+        let Some(start) = self.name_start else {
+            return false;
+        };
+        if c == '}' {
+            self.name_start = None;
+            let inner = &text[start + 1..pos];
+            let trimmed = inner.trim();
+            if !trimmed.is_empty() {
+                let offset = start + 1 + inner.len() - inner.trim_start().len();
+                let span = Span::new(offset, offset + trimmed.len());
+                self.push(Node::Word(Token::with_kind(trimmed, span, TokenKind::Name)));
+            }
+        }
+        true
+    }
+
+    fn take_word(&mut self, text: &str, pos: usize) -> Option<Token> {
+        self.word_start
+            .take()
+            .map(|start| Token::new(&text[start..pos], Span::new(start, pos)))
+    }
+
+    fn flush_word(&mut self, text: &str, pos: usize) {
+        if let Some(token) = self.take_word(text, pos) {
+            self.push(Node::Word(token));
+        }
+    }
+
+    fn push(&mut self, node: Node) {
+        match self.frames.last_mut() {
+            Some(frame) => frame.children.push(node),
+            None => self.nodes.push(node),
+        }
+    }
+
+    fn close_group(&mut self, end: usize) {
+        if let Some(frame) = self.frames.pop() {
+            let start = frame
+                .separator
+                .as_ref()
+                .map_or(frame.start, |separator| separator.span.start);
+            self.push(Node::Group {
+                separator: frame.separator,
+                children: frame.children,
+                span: Span::new(start, end),
+            });
+        }
+    }
+
+    /// Close every open group, reporting the first one as unclosed
+    fn close_all(&mut self, end: usize) {
+        if let Some(frame) = self.frames.first() {
+            let (start, close) = (frame.start, frame.close);
+            self.error(format!("unclosed group, expected '{close}'"), start, end);
+        }
+        while !self.frames.is_empty() {
+            self.close_group(end);
+        }
+    }
+
+    fn end_sentence(&mut self) {
+        let nodes = std::mem::take(&mut self.nodes);
+        if let (Some(first), Some(last)) = (nodes.first(), nodes.last()) {
+            let span = first.span().join(last.span());
+            self.sentences.push(MarkupSentence { nodes, span });
+        }
+    }
+
+    fn error(&mut self, message: String, start: usize, end: usize) {
+        self.errors.push(MarkupError {
+            message,
+            span: Span::new(start, end),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(node: &Node) -> String {
+        match node {
+            Node::Word(token) | Node::Mark(token) => token.text.clone(),
+            Node::Group {
+                separator,
+                children,
+                ..
+            } => {
+                let children: Vec<String> = children.iter().map(shape).collect();
+                format!(
+                    "{}({})",
+                    separator.as_ref().map_or("", |s| s.as_str()),
+                    children.join(" ")
+                )
+            }
+        }
+    }
+
+    #[test]
+    fn test_groups_and_separators() {
+        let text = "(mi) li[moku] e(kili pi(telo nasa)) lon[tomo]. {Ana} li[pona]";
+        let (sentences, errors) = parse(text, 0, text.len());
+        assert!(errors.is_empty());
+        let shapes: Vec<Vec<String>> = sentences
+            .iter()
+            .map(|sentence| sentence.nodes.iter().map(shape).collect())
+            .collect();
+        assert_eq!(
+            shapes,
+            vec![
+                vec![
+                    "(mi)",
+                    "li(moku)",
+                    "e(kili pi(telo nasa))",
+                    "lon(tomo)",
+                    "period"
+                ],
+                vec!["Ana", "li(pona)"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unbalanced_brackets_are_repaired() {
+        let text = "(mi)) li[moku e(kili]";
+        let (sentences, errors) = parse(text, 0, text.len());
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "unexpected ')'",
+                "expected ')'",
+                "unclosed group, expected ']'"
+            ]
+        );
+        assert_eq!(errors[0].span, Span::new(4, 5));
+        assert_eq!(sentences[0].nodes.len(), 2);
+    }
+}
@@ -0,0 +1,202 @@
+//! Noun phrase grammar for `pi` and prepositional phrases
+//!
+//! The words of a container are read with a small recursive-descent grammar:
+//!
+//! ```text
+//! phrase      := (words | pi_phrase | prep_phrase)*
+//! pi_phrase   := "pi" words
+//! prep_phrase := preposition object
+//! object      := (words | pi_phrase)+
+//! ```
+//!
+//! A `pi` phrase regroups the words after it into one modifier of the head and ends
+//! at the next `pi`, so `jan pi ma tomo pi telo suli` has two `pi` phrases that both
+//! modify `jan`. The object of a preposition is a noun phrase of its own and ends at
+//! the next preposition. `en`, `anu`, `li` and `e` end any phrase; the words after
+//! them start a new run. A marker that is not followed by a plain word is read as a
+//! plain word itself.
+
+use crate::types::Token;
+
+/// Role of a token in the grammar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Class {
+    /// Head or modifier
+    Word,
+    /// `pi`, which regroups the words after it
+    Regroup,
+    /// Preposition that opens a phrase
+    Preposition,
+    /// Word that ends any phrase: `en`, `anu`, `li` and `e`
+    Boundary,
+}
+
+/// Node of the modifier tree
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Phrase {
+    /// Run of words without a marker
+    Words(Vec<Token>),
+    /// `pi` phrase or prepositional phrase, with the phrases it holds
+    Marked { marker: Token, phrases: Vec<Phrase> },
+}
+
+/// Read tokens into a modifier tree
+pub(crate) fn parse(tokens: &[Token], classify: impl Fn(&Token) -> Class) -> Vec<Phrase> {
+    let classes: Vec<Class> = tokens.iter().map(classify).collect();
+    let mut reader = Reader {
+        tokens,
+        classes,
+        pos: 0,
+    };
+    reader.phrase()
+}
+
+struct Reader<'a> {
+    tokens: &'a [Token],
+    classes: Vec<Class>,
+    pos: usize,
+}
+
+impl Reader<'_> {
+    /// `phrase := (words | pi_phrase | prep_phrase)*`
+    fn phrase(&mut self) -> Vec<Phrase> {
+        let mut phrases = Vec::new();
+        while self.pos < self.tokens.len() {
+            let phrase = match self.marker() {
+                Some(Class::Regroup) => self.pi_phrase(),
+                Some(Class::Preposition) => self.prep_phrase(),
+                _ => self.words(true),
+            };
+            phrases.push(phrase);
+        }
+        phrases
+    }
+
+    /// `pi_phrase := "pi" words`
+    fn pi_phrase(&mut self) -> Phrase {
+        let marker = self.next();
+        Phrase::Marked {
+            marker,
+            phrases: vec![self.words(false)],
+        }
+    }
+
+    /// `prep_phrase := preposition object`, `object := (words | pi_phrase)+`
+    fn prep_phrase(&mut self) -> Phrase {
+        let marker = self.next();
+        let mut phrases = vec![self.words(false)];
+        while self.marker() == Some(Class::Regroup) {
+            phrases.push(self.pi_phrase());
+        }
+        Phrase::Marked { marker, phrases }
+    }
+
+    /// Words up to the next marker, or also up to a boundary word unless `across_boundaries`
+    ///
+    /// The grammar only calls this where at least one word follows.
+    fn words(&mut self, across_boundaries: bool) -> Phrase {
+        let mut words = vec![self.next()];
+        while self.pos < self.tokens.len()
+            && self.marker().is_none()
+            && (across_boundaries || self.classes[self.pos] != Class::Boundary)
+        {
+            words.push(self.next());
+        }
+        Phrase::Words(words)
+    }
+
+    /// Class of the marker at the current position, if it opens a phrase
+    ///
+    /// A marker opens a phrase when a plain word follows it.
+    fn marker(&self) -> Option<Class> {
+        let class = *self.classes.get(self.pos)?;
+        let opens = matches!(class, Class::Regroup | Class::Preposition)
+            && self.classes.get(self.pos + 1) == Some(&Class::Word);
+        opens.then_some(class)
+    }
+
+    fn next(&mut self) -> Token {
+        self.pos += 1;
+        self.tokens[self.pos - 1].clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Span;
+
+    /// Tokens for the words of a text, classified by their spelling
+    fn read(text: &str) -> Vec<Phrase> {
+        let tokens: Vec<Token> = text
+            .split(' ')
+            .map(|word| Token::new(word, Span::default()))
+            .collect();
+        parse(&tokens, |token| match token.as_str() {
+            "pi" => Class::Regroup,
+            "lon" | "tawa" => Class::Preposition,
+            "en" | "li" => Class::Boundary,
+            _ => Class::Word,
+        })
+    }
+
+    fn shape(phrases: &[Phrase]) -> String {
+        let shapes: Vec<String> = phrases
+            .iter()
+            .map(|phrase| match phrase {
+                Phrase::Words(tokens) => {
+                    let words: Vec<&str> = tokens.iter().map(Token::as_str).collect();
+                    format!("[{}]", words.join(" "))
+                }
+                Phrase::Marked { marker, phrases } => {
+                    format!("{}({})", marker.as_str(), shape(phrases))
+                }
+            })
+            .collect();
+        shapes.join(" ")
+    }
+
+    #[test]
+    fn test_modifier_tree() {
+        let cases = [
+            ("jan pona", "[jan pona]"),
+            (
+                "jan pi ma tomo pi telo suli",
+                "[jan] pi([ma tomo]) pi([telo suli])",
+            ),
+            (
+                "tomo lon ma pi telo suli tawa jan",
+                "[tomo] lon([ma] pi([telo suli])) tawa([jan])",
+            ),
+            (
+                "jan pi pona en jan pi ike",
+                "[jan] pi([pona]) [en jan] pi([ike])",
+            ),
+            ("mi lon en sina", "[mi lon en sina]"),
+            ("jan pi lon ma", "[jan pi] lon([ma])"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(shape(&read(text)), expected, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn test_every_token_is_kept_in_order() {
+        fn words(phrases: &[Phrase], out: &mut Vec<String>) {
+            for phrase in phrases {
+                match phrase {
+                    Phrase::Words(tokens) => out.extend(tokens.iter().map(|t| t.text.clone())),
+                    Phrase::Marked { marker, phrases } => {
+                        out.push(marker.text.clone());
+                        words(phrases, out);
+                    }
+                }
+            }
+        }
+
+        let text = "pi jan pi lon pi ma tawa pi en pi li tomo lon";
+        let mut out = Vec::new();
+        words(&read(text), &mut out);
+        assert_eq!(out.join(" "), text);
+    }
+}
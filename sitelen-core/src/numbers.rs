@@ -0,0 +1,107 @@
+//! Number phrases in the pu and nasin nanpa pona counting systems
+//!
+//! By default number words are ordinary words. With a [`NumberSystem`] enabled the
+//! parser groups runs of number words into number phrases, which the layout
+//! engine draws as a compact block instead of arranging the glyphs one by one.
+
+/// Counting system used to recognise number phrases
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumberSystem {
+    /// Number words are ordinary words
+    #[default]
+    Off,
+    /// The simple system from pu: `wan` and `tu` are added up, `tu wan` is three
+    Pu,
+    /// nasin nanpa pona: `wan` 1, `tu` 2, `luka` 5, `mute` 20, and `ale` (or `ali`)
+    /// multiplies everything before it by 100, so `tu ale luka` is 205
+    Pona,
+}
+
+impl NumberSystem {
+    /// Whether a word is a number word in this system
+    pub fn is_number_word(&self, word: &str) -> bool {
+        self.digit(word).is_some() || self.is_hundred(word)
+    }
+
+    /// Value of a run of number words, or `None` when they do not form a number
+    ///
+    /// Numbers are written from the largest word to the smallest; a run such as
+    /// `wan tu` is not a number.
+    pub fn value(&self, words: &[&str]) -> Option<u64> {
+        if words.is_empty() {
+            return None;
+        }
+
+        let mut total: u64 = 0;
+        let mut group: u64 = 0;
+        let mut last = u64::MAX;
+        for word in words {
+            if self.is_hundred(word) {
+                total = total.saturating_add(group).max(1).saturating_mul(100);
+                group = 0;
+                last = u64::MAX;
+            } else {
+                let digit = self.digit(word)?;
+                if digit > last {
+                    return None;
+                }
+                last = digit;
+                group = group.saturating_add(digit);
+            }
+        }
+
+        Some(total.saturating_add(group))
+    }
+
+    fn digit(&self, word: &str) -> Option<u64> {
+        match (self, word) {
+            (NumberSystem::Off, _) => None,
+            (_, "wan") => Some(1),
+            (_, "tu") => Some(2),
+            (NumberSystem::Pona, "luka") => Some(5),
+            (NumberSystem::Pona, "mute") => Some(20),
+            _ => None,
+        }
+    }
+
+    fn is_hundred(&self, word: &str) -> bool {
+        *self == NumberSystem::Pona && matches!(word, "ale" | "ali")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pu_numbers() {
+        let pu = NumberSystem::Pu;
+        assert_eq!(pu.value(&["tu", "wan"]), Some(3));
+        assert_eq!(pu.value(&["tu", "tu", "tu"]), Some(6));
+        assert_eq!(pu.value(&["wan", "tu"]), None);
+        assert_eq!(pu.value(&["luka"]), None);
+        assert!(!pu.is_number_word("mute"));
+    }
+
+    #[test]
+    fn test_pona_numbers() {
+        let pona = NumberSystem::Pona;
+        assert_eq!(pona.value(&["luka", "tu"]), Some(7));
+        assert_eq!(
+            pona.value(&["mute", "mute", "luka", "luka", "tu", "wan"]),
+            Some(53)
+        );
+        assert_eq!(pona.value(&["ale"]), Some(100));
+        assert_eq!(pona.value(&["tu", "ale", "luka"]), Some(205));
+        assert_eq!(pona.value(&["wan", "ale", "ale"]), Some(10_000));
+        assert_eq!(pona.value(&["tu", "luka"]), None);
+        assert_eq!(NumberSystem::Off.value(&["tu", "wan"]), None);
+    }
+
+    #[test]
+    fn test_huge_numbers_saturate() {
+        let mut words = vec!["ale"; 10];
+        words.extend(["wan", "ale"]);
+        assert_eq!(NumberSystem::Pona.value(&words), Some(u64::MAX));
+    }
+}
@@ -1,162 +1,19 @@
+use crate::document::{self, Block, BlockKind, Document};
+use crate::incremental::{EditError, ParsedText, SentenceSplice, TextEdit};
+use crate::lexicon::{Lexicon, WordRole};
+use crate::lint::{self, LintIssue};
+use crate::markup::{self, Node};
+use crate::noun_phrase::{self, Phrase};
+use crate::numbers::NumberSystem;
+use crate::prepositions::{self, PrepositionDecision, PrepositionRule, PrepositionUse};
+use crate::syllables::{Syllabifier, SyllableMode};
 use crate::types::Sentence;
 /// Toki Pona text parser
 use crate::types::SentencePart;
-use std::collections::HashSet;
-
-/// Allowed Toki Pona words
-const ALLOWED_WORDS: &[&str] = &[
-    "a",
-    "akesi",
-    "ala",
-    "alasa",
-    "ali",
-    "anpa",
-    "ante",
-    "anu",
-    "awen",
-    "e",
-    "en",
-    "epiku",
-    "esun",
-    "ijo",
-    "ike",
-    "ilo",
-    "insa",
-    "jaki",
-    "jan",
-    "jasima",
-    "jelo",
-    "jo",
-    "kala",
-    "kalama",
-    "kama",
-    "kasi",
-    "ken",
-    "kepeken",
-    "kili",
-    "kin",
-    "kipisi",
-    "kiwen",
-    "kijetesantakalu",
-    "ko",
-    "kokosila",
-    "kon",
-    "kule",
-    "kulupu",
-    "kute",
-    "la",
-    "lanpan",
-    "lape",
-    "laso",
-    "lawa",
-    "leko",
-    "len",
-    "lete",
-    "li",
-    "lili",
-    "linja",
-    "lipu",
-    "loje",
-    "lon",
-    "luka",
-    "lukin",
-    "lupa",
-    "ma",
-    "mama",
-    "mani",
-    "meli",
-    "meso",
-    "mi",
-    "mije",
-    "misikeke",
-    "moku",
-    "moli",
-    "monsi",
-    "monsuta",
-    "mu",
-    "mun",
-    "musi",
-    "mute",
-    "n",
-    "namako",
-    "nanpa",
-    "nasa",
-    "nasin",
-    "nena",
-    "ni",
-    "nimi",
-    "noka",
-    "o",
-    "oko",
-    "olin",
-    "ona",
-    "open",
-    "pakala",
-    "pali",
-    "palisa",
-    "pan",
-    "pana",
-    "pi",
-    "pilin",
-    "pimeja",
-    "pini",
-    "pipi",
-    "poka",
-    "poki",
-    "pona",
-    "pu",
-    "sama",
-    "seli",
-    "selo",
-    "seme",
-    "sewi",
-    "sijelo",
-    "sike",
-    "sin",
-    "sina",
-    "sinpin",
-    "sitelen",
-    "soko",
-    "sona",
-    "soweli",
-    "suli",
-    "suno",
-    "supa",
-    "suwi",
-    "tan",
-    "taso",
-    "tawa",
-    "telo",
-    "tenpo",
-    "toki",
-    "tomo",
-    "tonsi",
-    "tu",
-    "unpa",
-    "uta",
-    "utala",
-    "walo",
-    "wan",
-    "waso",
-    "wawa",
-    "weka",
-    "wile",
-    "ale",
-    ".",
-    "?",
-    "!",
-    ":",
-    ",",
-];
-
-/// Prepositions that create prepositional phrases
-const PREPOSITIONS: &[&str] = &["tawa", "tan", "lon", "kepeken", "sama", "poka"];
-
-/// Object markers
-const OBJECT_MARKERS: &[&str] = &["li", "e"];
-
-/// Preposition containers (including pi)
-const PREPOSITION_CONTAINERS: &[&str] = &["lon", "tan", "kepeken", "tawa", "sama", "poka", "pi"];
+use crate::types::{Span, Token, TokenKind};
+use crate::ucsur;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 /// Allowed Toki Pona syllables
 const ALLOWED_SYLLABLES: &[&str] = &[
@@ -169,138 +26,494 @@ const ALLOWED_SYLLABLES: &[&str] = &[
     "jon", "jun", "jan", "jen",
 ];
 
+/// Token used in place of illegal words and syllables when parsing with diagnostics
+pub const PLACEHOLDER: &str = "unknown";
+
+/// Separator of the container drawn around a quotation that is not the object of a verb
+pub const QUOTE_SEPARATOR: &str = "quote";
+
+/// Words that make a sentence of their own as an exclamation: `toki!`, `pona a!`
+const INTERJECTIONS: &[&str] = &["a", "ike", "mu", "n", "o", "pona", "toki"];
+
+/// How the parser recognises proper names written in Latin letters
+///
+/// Names marked up as `{Pona}`, spelled as `[o-la-pu]` or written as a UCSUR cartouche
+/// are recognised in every mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NameDetection {
+    /// Words starting with an upper case letter are names, except a sentence-initial word
+    /// from the lexicon
+    #[default]
+    Capitalized,
+    /// Only marked up names are names; capitalised words are read as ordinary words
+    Explicit,
+}
+
+/// How the parser reads its input
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputMode {
+    /// Toki Pona text, structured automatically
+    #[default]
+    Text,
+    /// Bracket markup spelling out the structure, such as `(mi) li[moku] e(kili)`
+    ///
+    /// See `Parser::set_input_mode` for the parts the groups become.
+    Markup,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ParseError {
     #[error("Illegal token: {0}")]
     IllegalToken(String),
     #[error("Illegal syllable: {0}")]
     IllegalSyllable(String),
+    #[error("Invalid markup: {0}")]
+    InvalidMarkup(String),
+}
+
+/// Kind of problem reported by `Parser::parse_with_diagnostics`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    IllegalToken,
+    IllegalSyllable,
+    /// Unbalanced bracket or unknown separator in markup input
+    InvalidMarkup,
+}
+
+/// A recoverable problem found while parsing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    /// The offending word or syllable (lower case), or what is wrong with the markup
+    pub text: String,
+    /// Location of the offending text in the input
+    pub span: Span,
+    /// Index of the sentence in the parse result
+    pub sentence: usize,
+    /// Closest allowed word or syllable, if any is near enough
+    pub suggestion: Option<String>,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            DiagnosticKind::IllegalToken => write!(f, "illegal token '{}'", self.text)?,
+            DiagnosticKind::IllegalSyllable => write!(f, "illegal syllable '{}'", self.text)?,
+            DiagnosticKind::InvalidMarkup => write!(f, "invalid markup: {}", self.text)?,
+        }
+        write!(
+            f,
+            " at {}..{} in sentence {}",
+            self.span.start,
+            self.span.end,
+            self.sentence + 1
+        )?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean '{}'?)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&Diagnostic> for ParseError {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        match diagnostic.kind {
+            DiagnosticKind::IllegalToken => ParseError::IllegalToken(diagnostic.text.clone()),
+            DiagnosticKind::IllegalSyllable => ParseError::IllegalSyllable(diagnostic.text.clone()),
+            DiagnosticKind::InvalidMarkup => ParseError::InvalidMarkup(diagnostic.text.clone()),
+        }
+    }
 }
 
 /// Parser for Toki Pona text
 pub struct Parser {
-    allowed_words: HashSet<String>,
+    lexicon: Arc<Lexicon>,
     allowed_syllables: HashSet<String>,
+    syllabifier: Arc<dyn Syllabifier>,
+    name_detection: NameDetection,
+    number_system: NumberSystem,
+    input_mode: InputMode,
+    preposition_overrides: HashMap<usize, PrepositionUse>,
 }
 
 impl Parser {
     pub fn new() -> Self {
-        let mut allowed_words = HashSet::new();
-        for word in ALLOWED_WORDS {
-            allowed_words.insert(word.to_string());
-        }
-        allowed_words.insert("'Name'".to_string());
+        Self::with_lexicon(Arc::new(Lexicon::default()))
+    }
 
+    /// Create a parser that accepts the words of the given lexicon
+    pub fn with_lexicon(lexicon: Arc<Lexicon>) -> Self {
         let mut allowed_syllables = HashSet::new();
         for syllable in ALLOWED_SYLLABLES {
             allowed_syllables.insert(syllable.to_string());
         }
 
         Self {
-            allowed_words,
+            lexicon,
             allowed_syllables,
+            syllabifier: Arc::new(SyllableMode::default()),
+            name_detection: NameDetection::default(),
+            number_system: NumberSystem::default(),
+            input_mode: InputMode::default(),
+            preposition_overrides: HashMap::new(),
         }
     }
 
+    /// Use another syllabifier for proper names (strict by default)
+    ///
+    /// Cartouches spelled explicitly with `[o-la-pu]` are not passed to the syllabifier.
+    pub fn set_syllabifier(&mut self, syllabifier: impl Syllabifier + 'static) {
+        self.syllabifier = Arc::new(syllabifier);
+    }
+
+    /// Choose how proper names are recognised (capitalised words by default)
+    pub fn set_name_detection(&mut self, detection: NameDetection) {
+        self.name_detection = detection;
+    }
+
+    /// Recognise number phrases in the given counting system (off by default)
+    pub fn set_number_system(&mut self, system: NumberSystem) {
+        self.number_system = system;
+    }
+
+    /// Read input as plain text (the default) or as structure markup
+    ///
+    /// In markup mode every group becomes a sentence part and the automatic structuring
+    /// is skipped. The word written directly before a group decides its part:
+    ///
+    /// - `li[...]`, `e[...]`: object marker
+    /// - a preposition, `lon[...]`: prepositional phrase
+    /// - `pi[...]`: container with that separator
+    /// - a preverb, `wile[...]`: preverb modifying the group
+    /// - `o[...]`: address
+    /// - no word, `(...)`: plain part
+    ///
+    /// Words outside any group form plain parts, and a `la` outside any group separates
+    /// contexts. Names are recognised as in text mode. Unbalanced brackets and unknown
+    /// separators are reported as `DiagnosticKind::InvalidMarkup`.
+    pub fn set_input_mode(&mut self, mode: InputMode) {
+        self.input_mode = mode;
+    }
+
+    /// Force the use of preposition words, keyed by the byte offset where the word starts
+    ///
+    /// Replaces earlier overrides. The last word of a clause is always a content word.
+    pub fn set_preposition_overrides(&mut self, overrides: HashMap<usize, PrepositionUse>) {
+        self.preposition_overrides = overrides;
+    }
+
+    /// How every preposition word in the text is used, and which rule decided it
+    ///
+    /// Decisions are listed in text order. Text that does not parse is decided as far
+    /// as it can be, as with `parse_with_diagnostics`.
+    pub fn preposition_decisions(&self, text: &str) -> Vec<PrepositionDecision> {
+        let mut context = ParseContext::collecting();
+        self.parse_with_context(text, &mut context)
+            .expect("collecting parser does not fail");
+        let mut decisions: Vec<PrepositionDecision> = context.prepositions.into_values().collect();
+        decisions.sort_by_key(|decision| decision.span.start);
+        decisions
+    }
+
+    /// The lexicon used to recognise words
+    pub fn lexicon(&self) -> &Lexicon {
+        &self.lexicon
+    }
+
     /// Parse text into structured sentences
+    ///
+    /// Every sentence, part and token carries a `Span` with byte offsets into `text`.
     pub fn parse(&self, text: &str) -> Result<Vec<Sentence>, ParseError> {
-        let normalized = text.replace("  ", " ");
-        let preformatted = self.preformat(&normalized)?;
+        self.parse_with_context(text, &mut ParseContext::strict())
+    }
 
-        preformatted
-            .iter()
-            .map(|sentence| self.parse_sentence(sentence))
-            .collect()
+    /// Parse text, collecting every illegal token or syllable instead of failing
+    ///
+    /// Illegal words and syllables are replaced by `PLACEHOLDER` tokens, so the
+    /// returned sentences can still be laid out and rendered.
+    pub fn parse_with_diagnostics(&self, text: &str) -> (Vec<Sentence>, Vec<Diagnostic>) {
+        let mut context = ParseContext::collecting();
+        let sentences = self
+            .parse_with_context(text, &mut context)
+            .expect("collecting parser does not fail");
+        (sentences, context.diagnostics.unwrap_or_default())
     }
 
-    /// Preformat text, splitting on punctuation
-    fn preformat(&self, text: &str) -> Result<Vec<ParsableSentence>, ParseError> {
-        use regex::Regex;
-        let re = Regex::new(r"[^.!?#]+[.!?#]+").unwrap();
+    /// Parse text into a document of headings, paragraphs and stanzas
+    ///
+    /// Blocks are separated by blank lines and headings start with `#` (see
+    /// [`crate::document`]). Spans refer to the whole text and sentences are counted
+    /// across blocks.
+    pub fn parse_document(&self, text: &str) -> Result<Document, ParseError> {
+        self.parse_document_with_context(text, &mut ParseContext::strict())
+    }
 
-        let matches: Vec<&str> = if let Some(_) = re.find(text) {
-            let match_ranges: Vec<(usize, usize)> =
-                re.find_iter(text).map(|m| (m.start(), m.end())).collect();
+    /// Parse a document, collecting every illegal token or syllable instead of failing
+    pub fn parse_document_with_diagnostics(&self, text: &str) -> (Document, Vec<Diagnostic>) {
+        let mut context = ParseContext::collecting();
+        let document = self
+            .parse_document_with_context(text, &mut context)
+            .expect("collecting parser does not fail");
+        (document, context.diagnostics.unwrap_or_default())
+    }
 
-            let mut result: Vec<&str> = match_ranges
-                .iter()
-                .map(|(start, end)| &text[*start..*end])
-                .collect();
+    /// Check text for common mistakes and phrases that render poorly
+    ///
+    /// The text is parsed as with `parse_with_diagnostics`; illegal words are left to
+    /// its diagnostics. See [`crate::lint`] for the checks.
+    pub fn lint(&self, text: &str) -> Vec<LintIssue> {
+        let (sentences, _) = self.parse_with_diagnostics(text);
+        lint::lint(text, &sentences)
+    }
 
-            // Check if there's remaining text after the last match
-            if let Some(&(_, last_end)) = match_ranges.last() {
-                if last_end < text.len() {
-                    let remaining = text[last_end..].trim();
-                    if !remaining.is_empty() {
-                        // Add the remaining text as an additional sentence (without punctuation)
-                        result.push(remaining);
-                    }
-                }
+    /// Parse text for live editing, collecting diagnostics as `parse_with_diagnostics` does
+    ///
+    /// Edits are applied to the result with `apply_edit`.
+    pub fn parse_incremental(&self, text: &str) -> ParsedText {
+        let (sentences, diagnostics) = self.parse_with_diagnostics(text);
+        ParsedText::new(text, sentences, diagnostics)
+    }
+
+    /// Apply an edit to parsed text, reparsing only the sentences it touches
+    ///
+    /// The result equals `parse_incremental` of the edited text; the returned splice
+    /// tells which sentences were replaced (see [`crate::incremental`]). Preposition
+    /// overrides are keyed by byte offset, so with overrides set every edit reparses the
+    /// whole text.
+    pub fn apply_edit(
+        &self,
+        parsed: &mut ParsedText,
+        edit: &TextEdit,
+    ) -> Result<SentenceSplice, EditError> {
+        let full = self.input_mode == InputMode::Markup || !self.preposition_overrides.is_empty();
+        parsed.apply(edit, full, |text, start, end| {
+            let mut context = ParseContext::collecting();
+            let sentences = self
+                .parse_range(text, start, end, &mut context)
+                .expect("collecting parser does not fail");
+            (sentences, context.diagnostics.unwrap_or_default())
+        })
+    }
+
+    fn parse_document_with_context(
+        &self,
+        text: &str,
+        context: &mut ParseContext,
+    ) -> Result<Document, ParseError> {
+        let mut sentence_count = 0;
+        let mut parse_line = |line: Span, context: &mut ParseContext| {
+            context.sentence = sentence_count;
+            let sentences = self.parse_range(text, line.start, line.end, context)?;
+            sentence_count += sentences.len();
+            Ok::<_, ParseError>(sentences)
+        };
+
+        let mut blocks = Vec::new();
+        for block in document::split_blocks(text) {
+            let span = block.lines[0].join(block.lines[block.lines.len() - 1]);
+            blocks.push(match block.kind {
+                BlockKind::Heading(level) => Block::Heading {
+                    level,
+                    sentences: parse_line(block.lines[0], context)?,
+                    span,
+                },
+                BlockKind::Paragraph => Block::Paragraph {
+                    sentences: parse_line(block.lines[0], context)?,
+                    span,
+                },
+                BlockKind::Stanza => Block::Stanza {
+                    lines: block
+                        .lines
+                        .iter()
+                        .map(|line| parse_line(*line, context))
+                        .collect::<Result<_, _>>()?,
+                    span,
+                },
+            });
+        }
+        Ok(Document { blocks })
+    }
+
+    fn parse_with_context(
+        &self,
+        text: &str,
+        context: &mut ParseContext,
+    ) -> Result<Vec<Sentence>, ParseError> {
+        self.parse_range(text, 0, text.len(), context)
+    }
+
+    /// Parse `text[start..end]`, numbering sentences from `context.sentence`
+    fn parse_range(
+        &self,
+        text: &str,
+        start: usize,
+        end: usize,
+        context: &mut ParseContext,
+    ) -> Result<Vec<Sentence>, ParseError> {
+        if self.input_mode == InputMode::Markup {
+            return self.parse_markup(text, start, end, context);
+        }
+
+        let preformatted = self.preformat(text, start, end, context)?;
+
+        let first = context.sentence;
+        let mut sentences = Vec::with_capacity(preformatted.len());
+        for (index, sentence) in preformatted.iter().enumerate() {
+            context.sentence = first + index;
+            sentences.push(self.parse_sentence(sentence, context)?);
+        }
+        Ok(sentences)
+    }
+
+    /// Report an illegal word or syllable
+    ///
+    /// Fails in strict mode; otherwise records a diagnostic and lets parsing continue.
+    fn report(
+        &self,
+        context: &mut ParseContext,
+        kind: DiagnosticKind,
+        text: &str,
+        span: Span,
+    ) -> Result<(), ParseError> {
+        let diagnostic = Diagnostic {
+            kind,
+            text: text.to_string(),
+            span,
+            sentence: context.sentence,
+            suggestion: None,
+        };
+        match &mut context.diagnostics {
+            None => Err(ParseError::from(&diagnostic)),
+            Some(diagnostics) => {
+                diagnostics.push(Diagnostic {
+                    suggestion: self.suggest(kind, text),
+                    ..diagnostic
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Find the closest allowed word or syllable for a misspelling
+    fn suggest(&self, kind: DiagnosticKind, text: &str) -> Option<String> {
+        let candidates: Vec<&str> = match kind {
+            DiagnosticKind::IllegalToken => self
+                .lexicon
+                .words()
+                .filter(|word| self.lexicon.role(word) != Some(WordRole::Punctuation))
+                .collect(),
+            DiagnosticKind::IllegalSyllable => {
+                self.allowed_syllables.iter().map(String::as_str).collect()
             }
+            DiagnosticKind::InvalidMarkup => return None,
+        };
+        let max_distance = (text.chars().count() / 3).clamp(1, 2);
 
-            result
+        candidates
+            .into_iter()
+            .map(|candidate| (edit_distance(text, candidate), candidate))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min()
+            .map(|(_, candidate)| candidate.to_string())
+    }
+
+    /// Preformat text, splitting on punctuation
+    ///
+    /// Only `text[start..end]` is split, so quotations can be preformatted in place and keep
+    /// spans into the full source text.
+    fn preformat(
+        &self,
+        text: &str,
+        start: usize,
+        end: usize,
+        context: &mut ParseContext,
+    ) -> Result<Vec<ParsableSentence>, ParseError> {
+        let mut ranges = sentence_ranges(text, start, end);
+
+        if let Some(&(_, last_end)) = ranges.last() {
+            // Add the remaining text as an additional sentence (without punctuation)
+            if last_end < end {
+                ranges.push((last_end, end));
+            }
         } else {
             // Allow sentence fractions without punctuation
-            vec![text]
-        };
+            ranges.push((start, end));
+        }
 
         let mut result = Vec::new();
-        for sentence in matches {
+        for (start, end) in ranges {
+            let sentence = &text[start..end];
             let trimmed = sentence.trim();
             if trimmed.is_empty() {
                 continue;
             }
+            let trimmed_start = start + sentence.len() - sentence.trim_start().len();
+            let trimmed_end = trimmed_start + trimmed.len();
 
-            let mut parsable = ParsableSentence::new();
-            // Only remove last character if it's punctuation
-            // Check if the sentence ends with punctuation
-            let has_punctuation = trimmed
+            let mut parsable = ParsableSentence::new(Span::new(trimmed_start, trimmed_end));
+
+            // Only remove last character if it's punctuation (a closing quote ends the body)
+            let terminator = trimmed
                 .chars()
                 .last()
-                .map(|c| matches!(c, '.' | ':' | '!' | '?' | '#'))
-                .unwrap_or(false);
+                .filter(|&c| matches!(latin_mark(c), '.' | ':' | '!' | '?' | '#'));
 
-            let body = if has_punctuation && trimmed.len() > 1 {
-                &trimmed[..trimmed.len() - 1]
-            } else {
-                trimmed
+            let body_end = match terminator {
+                Some(c) if trimmed.len() > 1 => trimmed_end - c.len_utf8(),
+                _ => trimmed_end,
             };
 
-            // Remove comma before and after  la-clause and before repeating li clause
-            let body = body.replace(", la ", " la ").replace("la, ", "la ").replace(", li ", " li ");
+            // Remove comma before and after la-clause and before repeating li clause
+            let lexemes = drop_redundant_commas(self.tag_names(lex(text, trimmed_start, body_end)));
 
             // Split on context separators
-            let la_parts: Vec<&str> = body.split(" la ").collect();
-            for (i, la_part) in la_parts.iter().enumerate() {
-                let colon_parts: Vec<&str> = la_part.split(':').collect();
-                for (j, colon_part) in colon_parts.iter().enumerate() {
-                    let comma_parts: Vec<&str> = colon_part.split(',').collect();
-                    for (k, comma_part) in comma_parts.iter().enumerate() {
-                        let trimmed_part = comma_part.trim();
-                        if !trimmed_part.is_empty() {
-                            parsable.push_content(trimmed_part);
+            let (la_parts, la_tokens) = split_on_la(lexemes);
+            for (i, la_part) in la_parts.into_iter().enumerate() {
+                let mut content = Vec::new();
+                for lexeme in la_part {
+                    match lexeme {
+                        Lexeme::Word(token) => content.push(token),
+                        Lexeme::Quote { inner, span } => {
+                            // Stand-in for the quotation until the words around it are structured
+                            content.push(Token::with_kind(
+                                &text[span.start..span.end],
+                                span,
+                                TokenKind::Quote,
+                            ));
+                            parsable.quotes.push(self.preformat(
+                                text,
+                                inner.start,
+                                inner.end,
+                                context,
+                            )?);
                         }
-                        if k < comma_parts.len() - 1 {
-                            parsable.push_punctuation("comma");
+                        Lexeme::Name(token, spelling) => {
+                            context.spellings.insert(token.span.start, spelling);
+                            content.push(token);
+                        }
+                        Lexeme::Mark(mark, span) => {
+                            parsable.push_content(std::mem::take(&mut content));
+                            let name = if mark == ',' { "comma" } else { "colon" };
+                            parsable.push_punctuation(name, span);
                         }
-                    }
-                    if j < colon_parts.len() - 1 {
-                        parsable.push_punctuation("colon");
                     }
                 }
-                if la_parts.len() == 2 && i == 0 {
-                    parsable.push_punctuation("la");
+                parsable.push_content(content);
+                // Every context clause is closed by its own la marker
+                if let Some(la) = la_tokens.get(i) {
+                    parsable.push_punctuation("la", la.span);
                 }
             }
 
             // Add terminator
-            if let Some(terminator) = trimmed.chars().last() {
-                match terminator {
-                    '.' => parsable.push_punctuation("period"),
-                    ':' => parsable.push_punctuation("colon"),
-                    '!' => parsable.push_punctuation("exclamation"),
-                    '?' => parsable.push_punctuation("question"),
-                    '#' => parsable.push_punctuation("banner"),
+            if let Some(terminator) = terminator {
+                let span = Span::new(trimmed_end - terminator.len_utf8(), trimmed_end);
+                match latin_mark(terminator) {
+                    '.' => parsable.push_punctuation("period", span),
+                    ':' => parsable.push_punctuation("colon", span),
+                    '!' => parsable.push_punctuation("exclamation", span),
+                    '?' => parsable.push_punctuation("question", span),
+                    '#' => parsable.push_punctuation("banner", span),
                     _ => {}
                 }
             }
@@ -311,523 +524,984 @@ impl Parser {
         Ok(result)
     }
 
+    /// Tag capitalised words as proper names, when names are detected by capitalisation
+    ///
+    /// A capitalised first word that is in the lexicon only starts the sentence: `Mi pona.`
+    fn tag_names(&self, mut lexemes: Vec<Lexeme>) -> Vec<Lexeme> {
+        if self.name_detection != NameDetection::Capitalized {
+            return lexemes;
+        }
+        for (index, lexeme) in lexemes.iter_mut().enumerate() {
+            if let Lexeme::Word(token) = lexeme {
+                let starts_sentence =
+                    index == 0 && self.lexicon.contains(&token.text.to_lowercase());
+                if is_proper_name(&token.text) && !starts_sentence {
+                    token.kind = TokenKind::Name;
+                }
+            }
+        }
+        lexemes
+    }
+
     /// Parse a single sentence
-    fn parse_sentence(&self, sentence: &ParsableSentence) -> Result<Sentence, ParseError> {
+    fn parse_sentence(
+        &self,
+        sentence: &ParsableSentence,
+        context: &mut ParseContext,
+    ) -> Result<Sentence, ParseError> {
         let mut structured = Vec::new();
+        let exclaimed = matches!(
+            sentence.parts.last(),
+            Some(ParsablePart::Punctuation(token)) if token.text == "exclamation"
+        );
 
-        for part in &sentence.parts {
+        for (index, part) in sentence.parts.iter().enumerate() {
             match part {
-                ParsablePart::Content(content) => {
-                    // Find proper names (capitalized words)
-                    let mut proper_names = Vec::new();
-                    let mut content_with_placeholders = content.clone();
-
-                    use regex::Regex;
-                    let re = Regex::new(r"([A-Z][\w-]*)").unwrap();
-                    for cap in re.captures_iter(content) {
-                        if let Some(m) = cap.get(0) {
-                            proper_names.push(m.as_str().to_string());
-                            content_with_placeholders =
-                                content_with_placeholders.replace(m.as_str(), "'Name'");
-                        }
-                    }
-
-                    let value = self.get_simple_structured_sentence(&content_with_placeholders)?;
-
-                    // Replace placeholders with actual names
-                    // Reverse the vector so we can pop in the correct order
-                    proper_names.reverse();
-                    let mut value = value;
-                    for part in &mut value {
-                        match part {
-                            SentencePart::Subject { tokens, .. }
-                            | SentencePart::ObjectMarker { tokens, .. }
-                            | SentencePart::PrepPhrase { tokens, .. } => {
-                                for token in tokens {
-                                    if *token == "'Name'" {
-                                        if let Some(name) = proper_names.pop() {
-                                            *token = name;
-                                        }
-                                    }
-                                }
-                            }
-                            _ => {}
+                ParsablePart::Content(tokens) => {
+                    // A condition before la is not an exclamation: `pona la mi kama`
+                    let before_la = matches!(
+                        sentence.parts.get(index + 1),
+                        Some(ParsablePart::Punctuation(token)) if token.text == "la"
+                    );
+                    match exclamation(tokens, exclaimed) {
+                        Some(part) if index == 0 && !before_la => structured.push(part),
+                        _ => {
+                            structured.extend(self.get_simple_structured_sentence(tokens, context)?)
                         }
                     }
-
-                    structured.extend(value);
                 }
-                ParsablePart::Punctuation(punct) => {
+                ParsablePart::Punctuation(token) => {
                     structured.push(SentencePart::Punctuation {
-                        tokens: vec![punct.clone()],
+                        tokens: vec![token.clone()],
+                        span: token.span,
                     });
                 }
             }
         }
 
-        let structured = self.postprocess(structured)?;
-        Ok(Sentence { parts: structured })
+        let mut quotes = sentence.quotes.iter();
+        for part in &mut structured {
+            self.embed_quotes(part, &mut quotes, context)?;
+        }
+
+        self.structure_predicates(&mut structured);
+        let structured = self.postprocess(structured, context)?;
+        Ok(Sentence {
+            parts: structured,
+            span: sentence.span,
+        })
     }
 
-    /// Get simple structured sentence from parseable text
+    /// Get simple structured sentence from parseable tokens
+    ///
+    /// Vocatives, commands and interjections take these forms:
+    ///
+    /// - `o` after words addresses them: `jan Ali o` is an [`SentencePart::Address`], and
+    ///   the words after it are what is said to them: `jan Ali o, moku`; words after it in
+    ///   the same clause are a command to them: `jan Ali o kama`
+    /// - `o` opening a clause starts a command, an `o` object marker that holds the
+    ///   predicate as `li` does: `o moku e kili`, `tenpo ni la o lape`
+    /// - `o` on its own calls out: `o!` is an [`SentencePart::Interjection`]
+    /// - `a`, and a run of them, is an interjection after the words it follows:
+    ///   `mi moku a`, `a a a!`
+    ///
+    /// A sentence that opens with an exclamation such as `toki!` or `pona kin!` is
+    /// recognised before this, by `parse_sentence`. `kin` emphasises the words before it
+    /// and stays with them: `ona li pona kin`.
     fn get_simple_structured_sentence(
         &self,
-        parseable: &str,
+        tokens: &[Token],
+        context: &mut ParseContext,
     ) -> Result<Vec<SentencePart>, ParseError> {
-        let tokens: Vec<&str> = parseable.split_whitespace().collect();
         let mut sentence = vec![SentencePart::Subject {
             tokens: Vec::new(),
             separator: None,
             parts: None,
+            span: Span::default(),
         }];
         let mut current_part = 0;
+        let decisions =
+            prepositions::disambiguate(&self.lexicon, tokens, &self.preposition_overrides);
+        for decision in decisions.iter().flatten() {
+            context
+                .prepositions
+                .insert(decision.span.start, decision.clone());
+        }
+        let is_object_marker = |token: &Token| {
+            token.kind == TokenKind::Word
+                && self.lexicon.is_object_marker(&token.text.to_lowercase())
+        };
 
         for (index, token) in tokens.iter().enumerate() {
-            let token_lower = token.to_lowercase();
+            // Words after an interjection start a part of their own
+            if matches!(sentence[current_part], SentencePart::Interjection { .. })
+                && !token.is_word("a")
+            {
+                sentence.push(plain_part(Vec::new()));
+                current_part = sentence.len() - 1;
+            }
+
+            // Names and quotations are kept as they are
+            if token.kind != TokenKind::Word {
+                push_token(&mut sentence[current_part], token.clone());
+                continue;
+            }
+            let token_lower = token.text.to_lowercase();
 
-            if OBJECT_MARKERS.contains(&token_lower.as_str()) && index < tokens.len() - 1 {
+            if is_object_marker(token) && index < tokens.len() - 1 {
                 sentence.push(SentencePart::ObjectMarker {
                     separator: token_lower.clone(),
                     tokens: Vec::new(),
                     parts: None,
+                    span: token.span,
                 });
                 current_part = sentence.len() - 1;
                 continue;
-            } else if PREPOSITIONS.contains(&token_lower.as_str())
-                && (index == 0
-                    || !OBJECT_MARKERS.contains(&tokens[index - 1].to_lowercase().as_str()))
-                && index < tokens.len() - 1
-                && !OBJECT_MARKERS.contains(&tokens[index + 1].to_lowercase().as_str())
-            {
+            } else if decisions[index].as_ref().is_some_and(|decision| {
+                // Phrases after li or e are drawn inside that container
+                decision.usage == PrepositionUse::Preposition
+                    && decision.rule != PrepositionRule::AfterObjectMarker
+            }) {
                 sentence.push(SentencePart::PrepPhrase {
                     separator: token_lower.clone(),
                     tokens: Vec::new(),
                     parts: None,
+                    span: token.span,
                 });
                 current_part = sentence.len() - 1;
                 continue;
             } else if token_lower == "o" {
-                if let SentencePart::Subject { tokens, .. } = &sentence[current_part] {
-                    if !tokens.is_empty() {
+                if let SentencePart::Subject {
+                    tokens: words,
+                    span,
+                    ..
+                } = &sentence[current_part]
+                {
+                    if !words.is_empty() {
                         // Convert to address
                         sentence[current_part] = SentencePart::Address {
                             separator: "o".to_string(),
-                            tokens: tokens.clone(),
+                            tokens: words.clone(),
+                            parts: None,
+                            span: span.join(token.span),
                         };
-                        sentence.push(SentencePart::Subject {
+                        if index < tokens.len() - 1 {
+                            // The words after it are what they are told to do
+                            sentence.push(SentencePart::ObjectMarker {
+                                separator: token_lower,
+                                tokens: Vec::new(),
+                                parts: None,
+                                span: token.span,
+                            });
+                        } else {
+                            sentence.push(SentencePart::Subject {
+                                tokens: Vec::new(),
+                                separator: None,
+                                parts: None,
+                                span: Span::default(),
+                            });
+                        }
+                    } else if index < tokens.len() - 1 {
+                        // A command, marked by o as a predicate is by li
+                        sentence.push(SentencePart::ObjectMarker {
+                            separator: token_lower,
                             tokens: Vec::new(),
-                            separator: None,
                             parts: None,
+                            span: token.span,
+                        });
+                    } else {
+                        sentence.push(SentencePart::Interjection {
+                            tokens: vec![Token::new(token_lower, token.span)],
+                            span: token.span,
                         });
-                        current_part = sentence.len() - 1;
-                        continue;
                     }
+                    current_part = sentence.len() - 1;
+                    continue;
                 }
             } else if token_lower == "a" {
-                if current_part < sentence.len() {
-                    let has_separator = match &sentence[current_part] {
-                        SentencePart::Subject {
-                            tokens, separator, ..
-                        } => !tokens.is_empty() && separator.is_some(),
-                        SentencePart::ObjectMarker { tokens, .. } => !tokens.is_empty(),
-                        SentencePart::PrepPhrase { tokens, .. } => !tokens.is_empty(),
-                        _ => false,
-                    };
-                    if has_separator {
+                match &mut sentence[current_part] {
+                    // Laughter and repeated emphasis: a a a
+                    SentencePart::Interjection { tokens, span } => {
+                        *span = span.join(token.span);
+                        tokens.push(Token::new(token_lower, token.span));
+                    }
+                    _ => {
                         sentence.push(SentencePart::Interjection {
-                            tokens: vec![token_lower.clone()],
+                            tokens: vec![Token::new(token_lower, token.span)],
+                            span: token.span,
                         });
                         current_part = sentence.len() - 1;
-                        continue;
                     }
                 }
+                continue;
             }
 
-            // Validate token (preserve 'Name' placeholder case)
-            let is_name_placeholder = *token == "'Name'";
-            if !self.allowed_words.contains(&token_lower) && token_lower != "'name'" {
-                return Err(ParseError::IllegalToken(token_lower));
-            }
+            // Validate token and add it to the current part
+            let text = if self.lexicon.contains(&token_lower) {
+                token_lower
+            } else {
+                self.report(
+                    context,
+                    DiagnosticKind::IllegalToken,
+                    &token_lower,
+                    token.span,
+                )?;
+                PLACEHOLDER.to_string()
+            };
+            push_token(&mut sentence[current_part], Token::new(text, token.span));
+        }
 
-            // Add token to current part (preserve case for 'Name' placeholder)
-            match &mut sentence[current_part] {
-                SentencePart::Subject { tokens, .. }
-                | SentencePart::ObjectMarker { tokens, .. }
-                | SentencePart::PrepPhrase { tokens, .. }
-                | SentencePart::Address { tokens, .. } => {
-                    if is_name_placeholder {
-                        tokens.push(token.to_string()); // Preserve 'Name' capitalization
-                    } else {
-                        tokens.push(token_lower.clone());
-                    }
-                }
-                _ => {}
+        // Filter out empty parts
+        sentence.retain(|part| !part.tokens().is_empty());
+
+        Ok(sentence)
+    }
+
+    /// Split a proper name token into syllable tokens, each spanning its part of the name
+    fn syllable_tokens(
+        &self,
+        name: &Token,
+        context: &mut ParseContext,
+    ) -> Result<Vec<Token>, ParseError> {
+        let mut offset = name.span.start;
+        let mut letter = 0;
+        let mut tokens = Vec::new();
+
+        let spelling = context.spellings.get(&name.span.start).cloned();
+        let syllables = match spelling
+            .as_ref()
+            .and_then(|spelling| spelling.syllables.clone())
+        {
+            Some(syllables) => syllables,
+            None => self.syllabifier.syllabify(&name.text),
+        };
+
+        // Syllables map onto the source letter by letter unless the syllabifier respelled the name
+        let letter_count = syllables.iter().map(String::len).sum::<usize>();
+        let letter_by_letter = match &spelling {
+            Some(spelling) => spelling.letters.len() == letter_count,
+            None => name.span.len() == letter_count,
+        };
+
+        for syllable in syllables {
+            // Names spelled by UCSUR glyphs or explicit syllables take their spans from the letters
+            let span = match &spelling {
+                _ if !letter_by_letter => name.span,
+                Some(spelling) => spelling.letters[letter..letter + syllable.len()]
+                    .iter()
+                    .fold(Span::default(), |span, letter| span.join(*letter)),
+                None => Span::new(offset, offset + syllable.len()),
+            };
+            offset = span.end;
+            letter += syllable.len();
+
+            let text = if self.allowed_syllables.contains(&syllable) {
+                syllable
+            } else {
+                self.report(context, DiagnosticKind::IllegalSyllable, &syllable, span)?;
+                PLACEHOLDER.to_string()
+            };
+            tokens.push(Token::with_kind(text, span, TokenKind::Syllable));
+        }
+
+        Ok(tokens)
+    }
+
+    /// Split tokens into runs of words and cartouches for the proper names at `name_indices`
+    ///
+    /// `make_part` builds the part for a run of tokens; it receives `Some("cartouche")`
+    /// as separator for proper names and `None` for regular words.
+    fn split_proper_names(
+        &self,
+        tokens: &[Token],
+        name_indices: &[usize],
+        context: &mut ParseContext,
+        make_part: impl Fn(Vec<Token>, Option<&str>, Span) -> SentencePart,
+    ) -> Result<Vec<SentencePart>, ParseError> {
+        let mut new_parts = Vec::new();
+        let mut last = 0;
+
+        for &idx in name_indices {
+            if idx > last {
+                let run = tokens[last..idx].to_vec();
+                let span = tokens_span(&run);
+                new_parts.push(make_part(run, None, span));
             }
+            let syllables = self.syllable_tokens(&tokens[idx], context)?;
+            new_parts.push(make_part(syllables, Some("cartouche"), tokens[idx].span));
+            last = idx + 1;
+        }
+        if last < tokens.len() {
+            let run = tokens[last..].to_vec();
+            let span = tokens_span(&run);
+            new_parts.push(make_part(run, None, span));
         }
 
-        // Filter out empty parts
-        sentence.retain(|part| match part {
+        Ok(new_parts)
+    }
+
+    /// Replace quote placeholders in a part by the parsed quotations
+    ///
+    /// A quotation inside an object marker or prepositional phrase is nested in that part;
+    /// a quotation standing on its own is wrapped in a quote container.
+    fn embed_quotes<'a>(
+        &self,
+        part: &mut SentencePart,
+        quotes: &mut impl Iterator<Item = &'a Vec<ParsableSentence>>,
+        context: &mut ParseContext,
+    ) -> Result<(), ParseError> {
+        if !part
+            .tokens()
+            .iter()
+            .any(|token| token.kind == TokenKind::Quote)
+        {
+            return Ok(());
+        }
+
+        let tokens = match part {
             SentencePart::Subject { tokens, .. }
             | SentencePart::ObjectMarker { tokens, .. }
-            | SentencePart::PrepPhrase { tokens, .. }
-            | SentencePart::Address { tokens, .. } => !tokens.is_empty(),
-            SentencePart::Interjection { tokens, .. }
-            | SentencePart::Punctuation { tokens, .. } => !tokens.is_empty(),
-        });
+            | SentencePart::PrepPhrase { tokens, .. } => std::mem::take(tokens),
+            _ => return Ok(()),
+        };
 
-        Ok(sentence)
+        let mut nested = Vec::new();
+        let mut words = Vec::new();
+        for token in tokens {
+            if token.kind != TokenKind::Quote {
+                words.push(token);
+                continue;
+            }
+            if !words.is_empty() {
+                nested.push(plain_part(std::mem::take(&mut words)));
+            }
+            let mut sentences = Vec::new();
+            for quoted in quotes.next().into_iter().flatten() {
+                sentences.push(self.parse_sentence(quoted, context)?);
+            }
+            nested.push(SentencePart::Quotation {
+                sentences,
+                span: token.span,
+            });
+        }
+        if !words.is_empty() {
+            nested.push(plain_part(words));
+        }
+
+        match part {
+            SentencePart::Subject { span, .. } => {
+                *part = SentencePart::ObjectMarker {
+                    separator: QUOTE_SEPARATOR.to_string(),
+                    tokens: Vec::new(),
+                    parts: Some(nested),
+                    span: *span,
+                };
+            }
+            SentencePart::ObjectMarker { parts, .. } | SentencePart::PrepPhrase { parts, .. } => {
+                *parts = Some(nested);
+            }
+            _ => {}
+        }
+        Ok(())
     }
 
-    /// Split proper name into syllables
-    fn split_proper_into_syllables(&self, proper_name: &str) -> Result<Vec<String>, ParseError> {
-        if proper_name.is_empty() {
-            return Ok(Vec::new());
-        }
-
-        let name = proper_name.to_lowercase();
-        let chars: Vec<char> = name.chars().collect();
-        let mut syllables = Vec::new();
-
-        let mut i = 0;
-        while i < chars.len() {
-            let first = chars.get(i).copied().unwrap_or(' ');
-            let second = chars.get(i + 1).copied().unwrap_or(' ');
-            let third = chars.get(i + 2).copied().unwrap_or(' ');
-            let fourth = chars.get(i + 3).copied().unwrap_or(' ');
-
-            let is_vowel = matches!(first, 'a' | 'e' | 'i' | 'o' | 'u');
-            let is_n = second == 'n';
-            let third_is_n = third == 'n';
-            let third_is_vowel = matches!(third, 'a' | 'e' | 'i' | 'o' | 'u');
-            let fourth_is_vowel = matches!(fourth, 'a' | 'e' | 'i' | 'o' | 'u');
-
-            if !is_vowel {
-                // Consonant start
-                if third_is_n && !fourth_is_vowel {
-                    // 3-character syllable: MONsi
-                    if i + 3 <= chars.len() {
-                        let syllable: String = chars[i..i + 3].iter().collect();
-                        if !self.allowed_syllables.contains(&syllable) {
-                            return Err(ParseError::IllegalSyllable(syllable));
-                        }
-                        syllables.push(syllable);
-                        i += 3;
-                    } else {
-                        break;
-                    }
-                } else {
-                    // 2-character syllable: POnoman, POki
-                    if i + 2 <= chars.len() {
-                        let syllable: String = chars[i..i + 2].iter().collect();
-                        if !self.allowed_syllables.contains(&syllable) {
-                            return Err(ParseError::IllegalSyllable(syllable));
-                        }
-                        syllables.push(syllable);
-                        i += 2;
-                    } else {
-                        break;
+    /// Structure the predicates of a sentence into preverbs, negations and `X ala X` questions
+    ///
+    /// Predicates are the parts introduced by `li`, and the words following a bare `mi` or
+    /// `sina` subject. Plain predicates are left untouched.
+    fn structure_predicates(&self, sentence: &mut [SentencePart]) {
+        let followed_by_li: Vec<bool> = (0..sentence.len())
+            .map(|i| {
+                matches!(
+                    sentence.get(i + 1),
+                    Some(SentencePart::ObjectMarker { separator, .. }) if separator == "li"
+                )
+            })
+            .collect();
+
+        for (part, followed_by_li) in sentence.iter_mut().zip(followed_by_li) {
+            match part {
+                SentencePart::ObjectMarker {
+                    separator,
+                    tokens,
+                    parts: parts @ None,
+                    ..
+                } if separator == "li" || separator == "o" => {
+                    if let Some(structured) = self.structure_predicate(tokens) {
+                        tokens.clear();
+                        *parts = Some(structured);
                     }
                 }
-            } else {
-                // Vowel start
-                if chars.len() - i == 2 {
-                    // 2-character word
-                    let syllable: String = chars[i..].iter().collect();
-                    if !self.allowed_syllables.contains(&syllable) {
-                        return Err(ParseError::IllegalSyllable(syllable));
-                    }
-                    syllables.push(syllable);
-                    break;
-                } else if is_n && !third_is_vowel {
-                    // 2-character syllable: UNpa
-                    if i + 2 <= chars.len() {
-                        let syllable: String = chars[i..i + 2].iter().collect();
-                        if !self.allowed_syllables.contains(&syllable) {
-                            return Err(ParseError::IllegalSyllable(syllable));
-                        }
-                        syllables.push(syllable);
-                        i += 2;
-                    } else {
-                        break;
+                // mi and sina are followed directly by their predicate
+                SentencePart::Subject {
+                    separator: None,
+                    tokens,
+                    parts: parts @ None,
+                    ..
+                } if !followed_by_li
+                    && tokens.len() > 1
+                    && (tokens[0].is_word("mi") || tokens[0].is_word("sina")) =>
+                {
+                    if let Some(structured) = self.structure_predicate(&tokens[1..]) {
+                        let mut new_parts = vec![plain_part(tokens[..1].to_vec())];
+                        new_parts.extend(structured);
+                        tokens.clear();
+                        *parts = Some(new_parts);
                     }
-                } else {
-                    // 1-character syllable
-                    let syllable = first.to_string();
-                    if !self.allowed_syllables.contains(&syllable) {
-                        return Err(ParseError::IllegalSyllable(syllable));
-                    }
-                    syllables.push(syllable);
-                    i += 1;
                 }
+                _ => {}
             }
         }
+    }
+
+    /// Structure a single predicate, returning `None` when it has no preverb, negation or question
+    fn structure_predicate(&self, tokens: &[Token]) -> Option<Vec<SentencePart>> {
+        let head = tokens.first()?;
+        let is_ala = |i: usize| tokens.get(i).map(|t| t.is_word("ala")).unwrap_or(false);
+
+        // The head word, optionally followed by ala (negation) or ala and itself (question)
+        let head_len = if !is_ala(1) {
+            1
+        } else if tokens.get(2).map(|t| t.text == head.text).unwrap_or(false) {
+            3
+        } else {
+            2
+        };
+
+        if head.kind == TokenKind::Word
+            && self.lexicon.is_preverb(head.as_str())
+            && head_len < tokens.len()
+        {
+            let verb = &tokens[head_len..];
+            let parts = self
+                .structure_predicate(verb)
+                .unwrap_or_else(|| vec![plain_part(verb.to_vec())]);
+            return Some(vec![SentencePart::Preverb {
+                tokens: tokens[..head_len].to_vec(),
+                parts,
+                span: tokens_span(tokens),
+            }]);
+        }
 
-        Ok(syllables)
+        let head_tokens = tokens[..head_len].to_vec();
+        let span = tokens_span(&head_tokens);
+        let mut parts = match head_len {
+            3 => vec![SentencePart::AlaQuestion {
+                tokens: head_tokens,
+                span,
+            }],
+            2 => vec![SentencePart::Negation {
+                tokens: head_tokens,
+                span,
+            }],
+            _ => return None,
+        };
+        if head_len < tokens.len() {
+            parts.push(plain_part(tokens[head_len..].to_vec()));
+        }
+        Some(parts)
     }
 
     /// Postprocess structured sentence
     fn postprocess(
         &self,
         mut sentence: Vec<SentencePart>,
+        context: &mut ParseContext,
     ) -> Result<Vec<SentencePart>, ParseError> {
+        // Group words joined by en or anu, so each conjunct is structured on its own
+        for part in sentence.iter_mut() {
+            if let SentencePart::Subject {
+                tokens,
+                parts: parts @ None,
+                ..
+            }
+            | SentencePart::ObjectMarker {
+                tokens,
+                parts: parts @ None,
+                ..
+            }
+            | SentencePart::PrepPhrase {
+                tokens,
+                parts: parts @ None,
+                ..
+            } = part
+            {
+                if let Some(coordination) = coordinate(tokens) {
+                    tokens.clear();
+                    *parts = Some(vec![coordination]);
+                }
+            }
+        }
+
         // Split prepositional phrases inside containers
-        for i in 0..sentence.len() {
-            let mut new_parts = None;
-            match &sentence[i] {
+        for part in sentence.iter_mut() {
+            let (tokens, parts) = match part {
                 SentencePart::Subject { tokens, parts, .. }
                 | SentencePart::ObjectMarker { tokens, parts, .. }
-                | SentencePart::PrepPhrase { tokens, parts, .. } => {
-                    // Skip parts that already have nested structures
-                    if parts.is_some() {
-                        continue;
-                    }
-                    // Find all container markers and their end positions
-                    let mut container_ranges = Vec::new();
-                    let mut j = 0;
-                    while j < tokens.len() {
-                        if PREPOSITION_CONTAINERS.contains(&tokens[j].as_str()) && j < tokens.len() - 1 {
-                            let container_start = j;
-                            let container_token = tokens[j].clone();
-                            
-                            // Find where this container ends
-                            // It ends at: next separator (en, li, e), next preposition, or end of tokens
-                            let mut container_end = tokens.len();
-                            for k in (j + 1)..tokens.len() {
-                                let token_lower = tokens[k].to_lowercase();
-                                // Stop at separators
-                                if token_lower == "en" || token_lower == "anu" || token_lower == "li" || token_lower == "e" {
-                                    container_end = k;
-                                    break;
-                                }
-                                // Stop at next preposition (but not pi, as pi can nest)
-                                if PREPOSITION_CONTAINERS.contains(&token_lower.as_str()) && token_lower != "pi" {
-                                    container_end = k;
-                                    break;
-                                }
-                            }
-                            
-                            container_ranges.push((container_start, container_end, container_token));
-                            j = container_end;
-                        } else {
-                            j += 1;
-                        }
-                    }
-
-                    if !container_ranges.is_empty() {
-                        let mut parts = Vec::new();
-                        let mut last_end = 0;
-                        
-                        for (start, end, container_token) in container_ranges {
-                            // Add tokens before this container
-                            if start > last_end {
-                                parts.push(SentencePart::Subject {
-                                    tokens: tokens[last_end..start].to_vec(),
-                                    separator: None,
-                                    parts: None,
-                                });
-                            }
-                            
-                            // Add the container with its contents
-                            parts.push(SentencePart::Subject {
-                                tokens: tokens[start + 1..end].to_vec(),
-                                separator: Some(container_token),
-                                parts: None,
-                            });
-                            
-                            last_end = end;
-                        }
-                        
-                        // Add remaining tokens after last container
-                        if last_end < tokens.len() {
-                            parts.push(SentencePart::Subject {
-                                tokens: tokens[last_end..].to_vec(),
-                                separator: None,
-                                parts: None,
-                            });
-                        }
-                        
-                        // Recursively process the new parts for nested containers
-                        let mut processed_parts = Vec::new();
-                        for part in parts {
-                            let temp_sentence = vec![part];
-                            let processed = self.postprocess(temp_sentence)?;
-                            processed_parts.extend(processed);
-                        }
-                        
-                        new_parts = Some(processed_parts);
-                    }
+                | SentencePart::PrepPhrase { tokens, parts, .. } => (tokens, parts),
+                SentencePart::Preverb { parts, .. } | SentencePart::Coordination { parts, .. } => {
+                    *parts = self.postprocess(std::mem::take(parts), context)?;
+                    continue;
                 }
-                _ => {}
+                _ => continue,
+            };
+
+            // Parts that already have nested structures only need their children processed
+            if let Some(nested) = parts {
+                *nested = self.postprocess(std::mem::take(nested), context)?;
+                continue;
             }
 
-            if let Some(parts) = new_parts {
-                match &sentence[i] {
-                    SentencePart::Subject { separator, .. } => {
-                        sentence[i] = SentencePart::Subject {
-                            tokens: Vec::new(),
-                            separator: separator.clone(),
-                            parts: Some(parts),
-                        };
-                    }
-                    SentencePart::ObjectMarker { separator, .. } => {
-                        sentence[i] = SentencePart::ObjectMarker {
-                            separator: separator.clone(),
-                            tokens: Vec::new(),
-                            parts: Some(parts),
-                        };
-                    }
-                    SentencePart::PrepPhrase { separator, .. } => {
-                        sentence[i] = SentencePart::PrepPhrase {
-                            separator: separator.clone(),
-                            tokens: Vec::new(),
-                            parts: Some(parts),
-                        };
-                    }
-                    _ => {}
-                }
+            // Read the words into a modifier tree, leaving plain runs flat
+            let phrases = noun_phrase::parse(tokens, |token| self.classify(token, context));
+            if matches!(&phrases[..], [] | [Phrase::Words(_)]) {
+                continue;
             }
+
+            // Names and numbers inside the new parts are split further down
+            let mut processed_parts = Vec::new();
+            for phrase in phrases {
+                processed_parts.extend(self.postprocess(vec![phrase_part(phrase)], context)?);
+            }
+
+            tokens.clear();
+            *parts = Some(processed_parts);
         }
 
-        // Split proper names inside containers
-        for i in 0..sentence.len() {
-            let name_indices: Vec<usize> = match &sentence[i] {
-                SentencePart::Subject { tokens, .. }
-                | SentencePart::ObjectMarker { tokens, .. }
-                | SentencePart::PrepPhrase { tokens, .. } => tokens
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(idx, token)| {
-                        if token
-                            .chars()
-                            .next()
-                            .map(|c| c.is_uppercase())
-                            .unwrap_or(false)
-                        {
-                            Some(idx)
-                        } else {
-                            None
-                        }
-                    })
-                    .collect(),
+        // Group number phrases
+        for part in sentence.iter_mut() {
+            let (tokens, parts) = match part {
+                SentencePart::Subject {
+                    tokens,
+                    parts: parts @ None,
+                    ..
+                }
+                | SentencePart::ObjectMarker {
+                    tokens,
+                    parts: parts @ None,
+                    ..
+                }
+                | SentencePart::PrepPhrase {
+                    tokens,
+                    parts: parts @ None,
+                    ..
+                } => (tokens, parts),
                 _ => continue,
             };
 
-            if !name_indices.is_empty() {
-                let mut new_parts = Vec::new();
-                let mut last = 0;
-
-                match &sentence[i] {
-                    SentencePart::Subject {
-                        tokens, separator, ..
-                    } => {
-                        for &idx in &name_indices {
-                            if idx > last {
-                                new_parts.push(SentencePart::Subject {
-                                    tokens: tokens[last..idx].to_vec(),
-                                    separator: None,
-                                    parts: None,
-                                });
-                            }
-                            let name = tokens[idx].clone();
-                            let syllables = self.split_proper_into_syllables(&name)?;
-                            new_parts.push(SentencePart::Subject {
-                                tokens: syllables,
-                                separator: Some("cartouche".to_string()),
-                                parts: None,
-                            });
-                            last = idx + 1;
-                        }
-                        if let Some(&last_idx) = name_indices.last() {
-                            if last_idx < tokens.len() - 1 {
-                                new_parts.push(SentencePart::Subject {
-                                    tokens: tokens[last_idx + 1..].to_vec(),
-                                    separator: None,
-                                    parts: None,
-                                });
-                            }
-                        }
-                        sentence[i] = SentencePart::Subject {
-                            tokens: Vec::new(),
-                            separator: separator.clone(),
-                            parts: Some(new_parts),
-                        };
-                    }
-                    SentencePart::ObjectMarker {
-                        tokens, separator, ..
-                    } => {
-                        for &idx in &name_indices {
-                            if idx > last {
-                                new_parts.push(SentencePart::ObjectMarker {
-                                    separator: separator.clone(),
-                                    tokens: tokens[last..idx].to_vec(),
-                                    parts: None,
-                                });
-                            }
-                            let name = tokens[idx].clone();
-                            let syllables = self.split_proper_into_syllables(&name)?;
-                            new_parts.push(SentencePart::ObjectMarker {
-                                separator: "cartouche".to_string(),
-                                tokens: syllables,
-                                parts: None,
-                            });
-                            last = idx + 1;
-                        }
-                        if let Some(&last_idx) = name_indices.last() {
-                            if last_idx < tokens.len() - 1 {
-                                new_parts.push(SentencePart::ObjectMarker {
-                                    separator: separator.clone(),
-                                    tokens: tokens[last_idx + 1..].to_vec(),
-                                    parts: None,
-                                });
-                            }
-                        }
-                        sentence[i] = SentencePart::ObjectMarker {
-                            separator: separator.clone(),
-                            tokens: Vec::new(),
-                            parts: Some(new_parts),
-                        };
-                    }
-                    SentencePart::PrepPhrase {
-                        tokens, separator, ..
-                    } => {
-                        for &idx in &name_indices {
-                            if idx > last {
-                                new_parts.push(SentencePart::PrepPhrase {
-                                    separator: separator.clone(),
-                                    tokens: tokens[last..idx].to_vec(),
-                                    parts: None,
-                                });
-                            }
-                            let name = tokens[idx].clone();
-                            let syllables = self.split_proper_into_syllables(&name)?;
-                            new_parts.push(SentencePart::PrepPhrase {
-                                separator: "cartouche".to_string(),
-                                tokens: syllables,
-                                parts: None,
-                            });
-                            last = idx + 1;
-                        }
-                        if let Some(&last_idx) = name_indices.last() {
-                            if last_idx < tokens.len() - 1 {
-                                new_parts.push(SentencePart::PrepPhrase {
-                                    separator: separator.clone(),
-                                    tokens: tokens[last_idx + 1..].to_vec(),
-                                    parts: None,
-                                });
-                            }
-                        }
-                        sentence[i] = SentencePart::PrepPhrase {
-                            separator: separator.clone(),
-                            tokens: Vec::new(),
-                            parts: Some(new_parts),
-                        };
+            let phrases = self.number_phrases(tokens);
+            if phrases.is_empty() {
+                continue;
+            }
+
+            let mut new_parts = Vec::new();
+            let mut last = 0;
+            for (start, end, value) in phrases {
+                if start > last {
+                    new_parts.push(plain_part(tokens[last..start].to_vec()));
+                }
+                new_parts.push(SentencePart::Number {
+                    tokens: tokens[start..end].to_vec(),
+                    value,
+                    span: tokens_span(&tokens[start..end]),
+                });
+                last = end;
+            }
+            if last < tokens.len() {
+                new_parts.push(plain_part(tokens[last..].to_vec()));
+            }
+
+            // Names among the remaining words are split in their own parts
+            tokens.clear();
+            *parts = Some(self.postprocess(new_parts, context)?);
+        }
+
+        // Split proper names inside containers
+        self.split_names(&mut sentence, context)?;
+
+        Ok(sentence)
+    }
+
+    /// Split the proper names inside parts into cartouches
+    fn split_names(
+        &self,
+        sentence: &mut [SentencePart],
+        context: &mut ParseContext,
+    ) -> Result<(), ParseError> {
+        for part in sentence.iter_mut() {
+            let name_indices: Vec<usize> = part
+                .tokens()
+                .iter()
+                .enumerate()
+                .filter(|(_, token)| token.kind == TokenKind::Name)
+                .map(|(idx, _)| idx)
+                .collect();
+
+            if name_indices.is_empty() {
+                continue;
+            }
+
+            match part {
+                SentencePart::Subject { tokens, parts, .. }
+                | SentencePart::Address { tokens, parts, .. } => {
+                    let new_parts = self.split_proper_names(
+                        tokens,
+                        &name_indices,
+                        context,
+                        |tokens, separator, span| SentencePart::Subject {
+                            tokens,
+                            separator: separator.map(str::to_string),
+                            parts: None,
+                            span,
+                        },
+                    )?;
+                    tokens.clear();
+                    *parts = Some(new_parts);
+                }
+                SentencePart::ObjectMarker {
+                    tokens,
+                    separator,
+                    parts,
+                    ..
+                } => {
+                    let new_parts = self.split_proper_names(
+                        tokens,
+                        &name_indices,
+                        context,
+                        |tokens, cartouche, span| SentencePart::ObjectMarker {
+                            separator: cartouche.unwrap_or(separator).to_string(),
+                            tokens,
+                            parts: None,
+                            span,
+                        },
+                    )?;
+                    tokens.clear();
+                    *parts = Some(new_parts);
+                }
+                SentencePart::PrepPhrase {
+                    tokens,
+                    separator,
+                    parts,
+                    ..
+                } => {
+                    let new_parts = self.split_proper_names(
+                        tokens,
+                        &name_indices,
+                        context,
+                        |tokens, cartouche, span| SentencePart::PrepPhrase {
+                            separator: cartouche.unwrap_or(separator).to_string(),
+                            tokens,
+                            parts: None,
+                            span,
+                        },
+                    )?;
+                    tokens.clear();
+                    *parts = Some(new_parts);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parse structure markup into sentences without structuring them automatically
+    fn parse_markup(
+        &self,
+        text: &str,
+        start: usize,
+        end: usize,
+        context: &mut ParseContext,
+    ) -> Result<Vec<Sentence>, ParseError> {
+        let (sentences, errors) = markup::parse(text, start, end);
+        let mut errors = errors.into_iter().peekable();
+
+        let first = context.sentence;
+        let mut parsed = Vec::with_capacity(sentences.len());
+        for (index, sentence) in sentences.iter().enumerate() {
+            context.sentence = first + index;
+            while let Some(error) = errors.next_if(|error| error.span.start < sentence.span.end) {
+                self.report(
+                    context,
+                    DiagnosticKind::InvalidMarkup,
+                    &error.message,
+                    error.span,
+                )?;
+            }
+
+            let initial = first_word(&sentence.nodes);
+            let mut parts = Vec::new();
+            let mut words = Vec::new();
+            for node in &sentence.nodes {
+                match node {
+                    Node::Word(token) if !token.is_word("la") => {
+                        words.push(self.markup_word(token, initial, context)?);
+                        continue;
                     }
                     _ => {}
                 }
+                if !words.is_empty() {
+                    parts.push(plain_part(std::mem::take(&mut words)));
+                }
+                match node {
+                    Node::Word(token) => parts.push(SentencePart::Punctuation {
+                        tokens: vec![Token::with_kind("la", token.span, TokenKind::Punctuation)],
+                        span: token.span,
+                    }),
+                    Node::Mark(token) => parts.push(SentencePart::Punctuation {
+                        tokens: vec![token.clone()],
+                        span: token.span,
+                    }),
+                    Node::Group {
+                        separator,
+                        children,
+                        span,
+                    } => parts.extend(self.markup_group(
+                        separator.as_ref(),
+                        children,
+                        *span,
+                        initial,
+                        context,
+                    )?),
+                }
             }
+            if !words.is_empty() {
+                parts.push(plain_part(words));
+            }
+            self.split_names(&mut parts, context)?;
+
+            parsed.push(Sentence {
+                parts,
+                span: sentence.span,
+            });
         }
 
-        Ok(sentence)
+        // Errors after the last sentence, such as a stray closing bracket
+        for error in errors {
+            self.report(
+                context,
+                DiagnosticKind::InvalidMarkup,
+                &error.message,
+                error.span,
+            )?;
+        }
+
+        Ok(parsed)
+    }
+
+    /// Turn a markup group into the part chosen by its separator
+    ///
+    /// Returns `None` for an empty group.
+    fn markup_group(
+        &self,
+        separator: Option<&Token>,
+        children: &[Node],
+        span: Span,
+        initial: Option<usize>,
+        context: &mut ParseContext,
+    ) -> Result<Option<SentencePart>, ParseError> {
+        let mut tokens = Vec::new();
+        let mut nested = Vec::new();
+        for child in children {
+            match child {
+                Node::Word(token) => tokens.push(self.markup_word(token, initial, context)?),
+                Node::Group {
+                    separator,
+                    children,
+                    span,
+                } => {
+                    if !tokens.is_empty() {
+                        nested.push(plain_part(std::mem::take(&mut tokens)));
+                    }
+                    nested.extend(self.markup_group(
+                        separator.as_ref(),
+                        children,
+                        *span,
+                        initial,
+                        context,
+                    )?);
+                }
+                Node::Mark(_) => {}
+            }
+        }
+
+        let parts = if nested.is_empty() {
+            if tokens.is_empty() {
+                self.report(context, DiagnosticKind::InvalidMarkup, "empty group", span)?;
+                return Ok(None);
+            }
+            None
+        } else {
+            if !tokens.is_empty() {
+                nested.push(plain_part(tokens));
+                tokens = Vec::new();
+            }
+            Some(nested)
+        };
+
+        let Some(separator) = separator else {
+            return Ok(Some(SentencePart::Subject {
+                tokens,
+                separator: None,
+                parts,
+                span,
+            }));
+        };
+        let word = separator.text.to_lowercase();
+
+        let part = if word == "o" {
+            // An address holds words only
+            if parts.is_some() {
+                self.report(
+                    context,
+                    DiagnosticKind::InvalidMarkup,
+                    "groups cannot be nested in an address",
+                    span,
+                )?;
+            }
+            let tokens = match parts {
+                Some(parts) => parts
+                    .iter()
+                    .flat_map(|part| part.tokens().to_vec())
+                    .collect(),
+                None => tokens,
+            };
+            SentencePart::Address {
+                separator: word,
+                tokens,
+                parts: None,
+                span,
+            }
+        } else if self.lexicon.is_object_marker(&word) {
+            SentencePart::ObjectMarker {
+                separator: word,
+                tokens,
+                parts,
+                span,
+            }
+        } else if self.lexicon.is_preposition(&word) {
+            SentencePart::PrepPhrase {
+                separator: word,
+                tokens,
+                parts,
+                span,
+            }
+        } else if self.lexicon.role(&word) == Some(WordRole::ContainerMarker) {
+            SentencePart::Subject {
+                tokens,
+                separator: Some(word),
+                parts,
+                span,
+            }
+        } else if self.lexicon.is_preverb(&word) {
+            let mut parts = parts.unwrap_or_else(|| vec![plain_part(tokens)]);
+            self.split_names(&mut parts, context)?;
+            SentencePart::Preverb {
+                tokens: vec![Token::new(word, separator.span)],
+                parts,
+                span,
+            }
+        } else {
+            self.report(
+                context,
+                DiagnosticKind::InvalidMarkup,
+                &format!("'{}' is not a separator", separator.text),
+                separator.span,
+            )?;
+            SentencePart::Subject {
+                tokens,
+                separator: None,
+                parts,
+                span,
+            }
+        };
+
+        let mut part = [part];
+        self.split_names(&mut part, context)?;
+        let [part] = part;
+        Ok(Some(part))
+    }
+
+    /// Validate a word written in markup, recognising capitalised names as in text mode
+    ///
+    /// `initial` is the start of the first word of the sentence.
+    fn markup_word(
+        &self,
+        token: &Token,
+        initial: Option<usize>,
+        context: &mut ParseContext,
+    ) -> Result<Token, ParseError> {
+        let lower = token.text.to_lowercase();
+        let is_name = token.kind == TokenKind::Name
+            || (self.name_detection == NameDetection::Capitalized
+                && is_proper_name(&token.text)
+                && !(initial == Some(token.span.start) && self.lexicon.contains(&lower)));
+        if is_name {
+            return Ok(Token::with_kind(&token.text, token.span, TokenKind::Name));
+        }
+
+        let text = if self.lexicon.contains(&lower) {
+            lower
+        } else {
+            self.report(context, DiagnosticKind::IllegalToken, &lower, token.span)?;
+            PLACEHOLDER.to_string()
+        };
+        Ok(Token::new(text, token.span))
+    }
+
+    /// Whether a word opens a container: `pi`, or a preposition not used as a content word
+    fn opens_container(&self, token: &Token, context: &ParseContext) -> bool {
+        token.kind == TokenKind::Word
+            && self.lexicon.opens_container(token.as_str())
+            && !context.used_as_content(token)
+    }
+
+    /// Role of a token in the noun phrase grammar
+    fn classify(&self, token: &Token, context: &ParseContext) -> noun_phrase::Class {
+        let word = token.text.to_lowercase();
+        if token.kind != TokenKind::Word {
+            noun_phrase::Class::Word
+        } else if matches!(word.as_str(), "en" | "anu" | "li" | "e") {
+            noun_phrase::Class::Boundary
+        } else if !self.opens_container(token, context) {
+            noun_phrase::Class::Word
+        } else if self.lexicon.is_preposition(&word) {
+            noun_phrase::Class::Preposition
+        } else {
+            noun_phrase::Class::Regroup
+        }
+    }
+
+    /// Runs of number words that form number phrases, as `(start, end, value)`
+    ///
+    /// A single number word only forms a number phrase after `nanpa`, so `jan tu`
+    /// keeps its usual reading.
+    fn number_phrases(&self, tokens: &[Token]) -> Vec<(usize, usize, u64)> {
+        let is_number = |token: &Token| {
+            token.kind == TokenKind::Word && self.number_system.is_number_word(&token.text)
+        };
+
+        let mut phrases = Vec::new();
+        let mut start = 0;
+        while start < tokens.len() {
+            if !is_number(&tokens[start]) {
+                start += 1;
+                continue;
+            }
+            let end = (start..tokens.len())
+                .find(|&i| !is_number(&tokens[i]))
+                .unwrap_or(tokens.len());
+            let words: Vec<&str> = tokens[start..end].iter().map(Token::as_str).collect();
+            let ordinal = start > 0 && tokens[start - 1].is_word("nanpa");
+            if let Some(value) = self.number_system.value(&words) {
+                if end - start > 1 || ordinal {
+                    phrases.push((start, end, value));
+                }
+            }
+            start = end;
+        }
+
+        phrases
     }
 }
 
@@ -837,36 +1511,506 @@ impl Default for Parser {
     }
 }
 
+/// Parse mode and diagnostics collected so far
+struct ParseContext {
+    /// `None` in strict mode, where the first problem aborts parsing
+    diagnostics: Option<Vec<Diagnostic>>,
+    /// Index of the sentence being parsed
+    sentence: usize,
+    /// Spelling of names not written out in Latin letters, by name span start
+    spellings: HashMap<usize, Spelling>,
+    /// Use of the preposition words seen so far, by word span start
+    prepositions: HashMap<usize, PrepositionDecision>,
+}
+
+/// How a marked up name, a UCSUR cartouche or a name spelled by explicit syllables is spelled
+#[derive(Debug, Clone)]
+struct Spelling {
+    /// Source span of every letter of the name
+    letters: Vec<Span>,
+    /// Syllables given by the author, bypassing the syllabifier
+    syllables: Option<Vec<String>>,
+}
+
+impl ParseContext {
+    fn strict() -> Self {
+        Self {
+            diagnostics: None,
+            sentence: 0,
+            spellings: HashMap::new(),
+            prepositions: HashMap::new(),
+        }
+    }
+
+    fn collecting() -> Self {
+        Self {
+            diagnostics: Some(Vec::new()),
+            sentence: 0,
+            spellings: HashMap::new(),
+            prepositions: HashMap::new(),
+        }
+    }
+
+    /// Whether a preposition word was decided to be a content word
+    fn used_as_content(&self, token: &Token) -> bool {
+        self.prepositions
+            .get(&token.span.start)
+            .is_some_and(|decision| decision.usage == PrepositionUse::Content)
+    }
+}
+
+/// Levenshtein distance between two words
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Whether a word is written as a proper name (starts with an upper case letter)
+fn is_proper_name(word: &str) -> bool {
+    word.chars()
+        .next()
+        .map(|c| c.is_uppercase())
+        .unwrap_or(false)
+}
+
+/// Start of the first word in markup, looking inside groups
+fn first_word(nodes: &[Node]) -> Option<usize> {
+    nodes.iter().find_map(|node| match node {
+        Node::Word(token) => Some(token.span.start),
+        Node::Group { children, .. } => first_word(children),
+        Node::Mark(_) => None,
+    })
+}
+
+/// Span covering all tokens
+fn tokens_span(tokens: &[Token]) -> Span {
+    tokens
+        .iter()
+        .fold(Span::default(), |span, token| span.join(token.span))
+}
+
+/// Plain subject part holding the given tokens
+fn plain_part(tokens: Vec<Token>) -> SentencePart {
+    SentencePart::Subject {
+        span: tokens_span(&tokens),
+        tokens,
+        separator: None,
+        parts: None,
+    }
+}
+
+/// Sentence part for a node of the noun phrase grammar
+///
+/// Marked phrases become containers with the marker as separator; a marked phrase
+/// holding a single run keeps its words flat.
+fn phrase_part(phrase: Phrase) -> SentencePart {
+    match phrase {
+        Phrase::Words(tokens) => plain_part(tokens),
+        Phrase::Marked { marker, phrases } => {
+            let (tokens, parts) = match &phrases[..] {
+                [Phrase::Words(tokens)] => (tokens.clone(), None),
+                _ => (
+                    Vec::new(),
+                    Some(phrases.into_iter().map(phrase_part).collect::<Vec<_>>()),
+                ),
+            };
+            let end = match &parts {
+                Some(parts) => parts.last().map_or(marker.span, SentencePart::span),
+                None => tokens.last().map_or(marker.span, |token| token.span),
+            };
+            SentencePart::Subject {
+                span: marker.span.join(end),
+                separator: Some(marker.text),
+                tokens,
+                parts,
+            }
+        }
+    }
+}
+
+/// Split tokens at every `en` and `anu` between words into a coordination of plain parts
+///
+/// A closing `anu seme` is the question tag "or what?" and is left alone.
+fn coordinate(tokens: &[Token]) -> Option<SentencePart> {
+    let is_conjunction =
+        |token: &Token| token.kind == TokenKind::Word && matches!(token.as_str(), "en" | "anu");
+
+    let mut conjunctions = Vec::new();
+    let mut conjuncts = Vec::new();
+    let mut start = 0;
+    for (index, token) in tokens.iter().enumerate() {
+        let tag = token.as_str() == "anu"
+            && index + 2 == tokens.len()
+            && tokens[index + 1].as_str() == "seme";
+        // A conjunction needs words on both sides
+        if is_conjunction(token) && !tag && index > start && index + 1 < tokens.len() {
+            conjuncts.push(plain_part(tokens[start..index].to_vec()));
+            conjunctions.push(token.clone());
+            start = index + 1;
+        }
+    }
+    if conjunctions.is_empty() {
+        return None;
+    }
+    conjuncts.push(plain_part(tokens[start..].to_vec()));
+
+    Some(SentencePart::Coordination {
+        tokens: conjunctions,
+        parts: conjuncts,
+        span: tokens_span(tokens),
+    })
+}
+
+/// Append a token to a part, growing the part's span to cover it
+fn push_token(part: &mut SentencePart, token: Token) {
+    match part {
+        SentencePart::Subject { tokens, span, .. }
+        | SentencePart::ObjectMarker { tokens, span, .. }
+        | SentencePart::PrepPhrase { tokens, span, .. }
+        | SentencePart::Address { tokens, span, .. } => {
+            *span = span.join(token.span);
+            tokens.push(token);
+        }
+        _ => {}
+    }
+}
+
+/// Interjection of a clause that is only an exclamation word, emphasised by `a` or `kin`
+///
+/// A word on its own is only an exclamation in a sentence ending in `!`; `pona.` is a
+/// plain statement.
+fn exclamation(tokens: &[Token], exclaimed: bool) -> Option<SentencePart> {
+    let (first, rest) = tokens.split_first()?;
+    let is_word = |token: &Token, words: &[&str]| {
+        token.kind == TokenKind::Word && words.contains(&token.text.to_lowercase().as_str())
+    };
+    if !is_word(first, INTERJECTIONS)
+        || !rest.iter().all(|token| is_word(token, &["a", "kin"]))
+        || (rest.is_empty() && !exclaimed)
+    {
+        return None;
+    }
+    let tokens: Vec<Token> = tokens
+        .iter()
+        .map(|token| Token::new(token.text.to_lowercase(), token.span))
+        .collect();
+    Some(SentencePart::Interjection {
+        span: tokens_span(&tokens),
+        tokens,
+    })
+}
+
+/// Latin punctuation mark for a character, mapping UCSUR punctuation
+fn latin_mark(c: char) -> char {
+    ucsur::punctuation(c).unwrap_or(c)
+}
+
+/// Closing quote mark for an opening one
+fn closing_quote(c: char) -> Option<char> {
+    match c {
+        '"' => Some('"'),
+        '“' => Some('”'),
+        '«' => Some('»'),
+        '「' => Some('」'),
+        _ => None,
+    }
+}
+
+/// Whether a character opens or closes a quotation
+pub(crate) fn is_quote_mark(c: char) -> bool {
+    closing_quote(c).is_some() || matches!(c, '”' | '»' | '」')
+}
+
+/// Whether the text after `text[start..end]` starts a new sentence
+///
+/// True when the range, ignoring trailing whitespace, ends with a sentence terminator
+/// outside quotations, or holds only whitespace.
+pub(crate) fn ends_sentence(text: &str, start: usize, end: usize) -> bool {
+    let end = start + text[start..end].trim_end().len();
+    end == start
+        || sentence_ranges(text, start, end)
+            .last()
+            .is_some_and(|&(_, range_end)| range_end == end)
+}
+
+/// Ranges of `text[start..end]` ending in sentence terminators (`.`, `!`, `?` or `#`)
+///
+/// Terminators inside quotations belong to the quoted sentence and do not end the outer one.
+/// Terminators not preceded by any text are skipped.
+fn sentence_ranges(text: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+    let is_terminator = |c: char| matches!(latin_mark(c), '.' | '!' | '?' | '#');
+
+    let mut ranges = Vec::new();
+    let mut sentence_start = None;
+    let mut open_quote = None;
+    let mut chars = text[start..end].char_indices().peekable();
+
+    while let Some((offset, c)) = chars.next() {
+        let pos = start + offset;
+        if let Some(close) = open_quote {
+            if c == close {
+                open_quote = None;
+            }
+        } else if let Some(close) = closing_quote(c) {
+            open_quote = Some(close);
+        } else if is_terminator(c) {
+            let Some(range_start) = sentence_start.take() else {
+                continue;
+            };
+            let mut range_end = pos + c.len_utf8();
+            while let Some(&(offset, d)) = chars.peek().filter(|(_, d)| is_terminator(*d)) {
+                range_end = start + offset + d.len_utf8();
+                chars.next();
+            }
+            ranges.push((range_start, range_end));
+            continue;
+        }
+        sentence_start.get_or_insert(pos);
+    }
+
+    ranges
+}
+
+/// Split `text[start..end]` into words, quotations and in-sentence punctuation marks
+fn lex(text: &str, start: usize, end: usize) -> Vec<Lexeme> {
+    let mut lexemes = Vec::new();
+    let mut word_start = None;
+
+    let mut chars = text[start..end].char_indices();
+    while let Some((offset, c)) = chars.next() {
+        let pos = start + offset;
+        if let Some(close) = closing_quote(c) {
+            if let Some(word_start) = word_start.take() {
+                lexemes.push(Lexeme::Word(Token::new(
+                    &text[word_start..pos],
+                    Span::new(word_start, pos),
+                )));
+            }
+            // An unterminated quotation runs to the end of the sentence
+            let inner_start = pos + c.len_utf8();
+            let (inner_end, quote_end) = match chars.find(|&(_, d)| d == close) {
+                Some((offset, d)) => (start + offset, start + offset + d.len_utf8()),
+                None => (end, end),
+            };
+            lexemes.push(Lexeme::Quote {
+                inner: Span::new(inner_start, inner_end),
+                span: Span::new(pos, quote_end),
+            });
+        } else if c.is_whitespace() || ucsur::is_ucsur(c) || matches!(c, ',' | ':' | '[' | '{') {
+            if let Some(word_start) = word_start.take() {
+                lexemes.push(Lexeme::Word(Token::new(
+                    &text[word_start..pos],
+                    Span::new(word_start, pos),
+                )));
+            }
+            let span = Span::new(pos, pos + c.len_utf8());
+            if c == ucsur::CARTOUCHE_START {
+                // Cartouche glyphs spell a name by their initial letters
+                let mut name = String::new();
+                let mut letters = Vec::new();
+                let mut name_end = end;
+                for (offset, d) in chars.by_ref() {
+                    let pos = start + offset;
+                    if d == ucsur::CARTOUCHE_END {
+                        name_end = pos + d.len_utf8();
+                        break;
+                    }
+                    if let Some(initial) = ucsur::word(d).and_then(|word| word.chars().next()) {
+                        name.push(initial);
+                        letters.push(Span::new(pos, pos + d.len_utf8()));
+                    }
+                }
+                if !name.is_empty() {
+                    let span = Span::new(span.start, name_end);
+                    let spelling = Spelling {
+                        letters,
+                        syllables: None,
+                    };
+                    let token = Token::with_kind(name, span, TokenKind::Name);
+                    lexemes.push(Lexeme::Name(token, spelling));
+                }
+            } else if c == '[' {
+                // Explicit cartouche spelling: [o-la-pu]
+                let mut syllables = vec![String::new()];
+                let mut letters = Vec::new();
+                let mut name_end = end;
+                for (offset, d) in chars.by_ref() {
+                    let pos = start + offset;
+                    match d {
+                        ']' => {
+                            name_end = pos + 1;
+                            break;
+                        }
+                        '-' => syllables.push(String::new()),
+                        d if d.is_alphabetic() => {
+                            syllables.last_mut().unwrap().extend(d.to_lowercase());
+                            letters.push(Span::new(pos, pos + d.len_utf8()));
+                        }
+                        _ => {}
+                    }
+                }
+                syllables.retain(|syllable| !syllable.is_empty());
+                let name = syllables.concat();
+                if !name.is_empty() {
+                    let span = Span::new(span.start, name_end);
+                    let spelling = Spelling {
+                        letters,
+                        syllables: Some(syllables),
+                    };
+                    let token = Token::with_kind(name, span, TokenKind::Name);
+                    lexemes.push(Lexeme::Name(token, spelling));
+                }
+            } else if c == '{' {
+                // Marked up name: {Pona}
+                let mut name = String::new();
+                let mut letters = Vec::new();
+                let mut name_end = end;
+                for (offset, d) in chars.by_ref() {
+                    let pos = start + offset;
+                    if d == '}' {
+                        name_end = pos + 1;
+                        break;
+                    }
+                    if d.is_alphabetic() {
+                        name.push(d);
+                        letters.push(Span::new(pos, pos + d.len_utf8()));
+                    }
+                }
+                if !name.is_empty() {
+                    let span = Span::new(span.start, name_end);
+                    let spelling = Spelling {
+                        letters,
+                        syllables: None,
+                    };
+                    let token = Token::with_kind(name, span, TokenKind::Name);
+                    lexemes.push(Lexeme::Name(token, spelling));
+                }
+            } else if let Some(word) = ucsur::word(c) {
+                lexemes.push(Lexeme::Word(Token::new(word, span)));
+            } else if matches!(latin_mark(c), ',' | ':') {
+                lexemes.push(Lexeme::Mark(latin_mark(c), span));
+            }
+            // Joiners, extensions and long glyph markers only affect glyph shapes
+        } else if word_start.is_none() {
+            word_start = Some(pos);
+        }
+    }
+    if let Some(word_start) = word_start {
+        lexemes.push(Lexeme::Word(Token::new(
+            &text[word_start..end],
+            Span::new(word_start, end),
+        )));
+    }
+
+    lexemes
+}
+
+/// Remove commas directly before and after `la` and before `li`
+fn drop_redundant_commas(lexemes: Vec<Lexeme>) -> Vec<Lexeme> {
+    let is_word = |lexeme: Option<&Lexeme>, words: &[&str]| matches!(lexeme, Some(Lexeme::Word(token)) if words.contains(&token.as_str()));
+
+    lexemes
+        .iter()
+        .enumerate()
+        .filter(|(i, lexeme)| {
+            !matches!(lexeme, Lexeme::Mark(',', _))
+                || !(is_word(lexemes.get(i + 1), &["la", "li"])
+                    || (*i > 0 && is_word(lexemes.get(i - 1), &["la"])))
+        })
+        .map(|(_, lexeme)| lexeme.clone())
+        .collect()
+}
+
+/// Split lexemes on `la` context separators
+///
+/// A `la` only separates when it is surrounded by other lexemes. Returns the
+/// parts together with the `la` tokens that separated them.
+fn split_on_la(lexemes: Vec<Lexeme>) -> (Vec<Vec<Lexeme>>, Vec<Token>) {
+    let len = lexemes.len();
+    let mut parts = vec![Vec::new()];
+    let mut la_tokens = Vec::new();
+
+    for (i, lexeme) in lexemes.into_iter().enumerate() {
+        match lexeme {
+            Lexeme::Word(token) if token.as_str() == "la" && i > 0 && i < len - 1 => {
+                la_tokens.push(token);
+                parts.push(Vec::new());
+            }
+            lexeme => parts.last_mut().unwrap().push(lexeme),
+        }
+    }
+
+    (parts, la_tokens)
+}
+
+/// A word or in-sentence punctuation mark in the source text
+#[derive(Debug, Clone)]
+enum Lexeme {
+    Word(Token),
+    Mark(char, Span),
+    /// Quotation with the span of its contents and the span including the quote marks
+    Quote {
+        inner: Span,
+        span: Span,
+    },
+    /// Name marked up as `{Pona}`, spelled by a UCSUR cartouche or by explicit syllables
+    Name(Token, Spelling),
+}
+
 /// Internal structure for parsing
 #[derive(Debug)]
 enum ParsablePart {
-    Content(String),
-    Punctuation(String),
+    Content(Vec<Token>),
+    Punctuation(Token),
 }
 
 #[derive(Debug)]
 struct ParsableSentence {
     parts: Vec<ParsablePart>,
+    /// Preformatted quotations, in the order of their quote tokens in the content
+    quotes: Vec<Vec<ParsableSentence>>,
+    span: Span,
 }
 
 impl ParsableSentence {
-    fn new() -> Self {
-        Self { parts: Vec::new() }
+    fn new(span: Span) -> Self {
+        Self {
+            parts: Vec::new(),
+            quotes: Vec::new(),
+            span,
+        }
     }
 
-    fn push_content(&mut self, content: &str) {
-        self.parts.push(ParsablePart::Content(content.to_string()));
+    fn push_content(&mut self, content: Vec<Token>) {
+        if !content.is_empty() {
+            self.parts.push(ParsablePart::Content(content));
+        }
     }
 
-    fn push_punctuation(&mut self, punct: &str) {
-        self.parts
-            .push(ParsablePart::Punctuation(punct.to_string()));
+    fn push_punctuation(&mut self, punct: &str, span: Span) {
+        self.parts.push(ParsablePart::Punctuation(Token::with_kind(
+            punct,
+            span,
+            TokenKind::Punctuation,
+        )));
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::syllables::SyllableMode;
 
     #[test]
     fn test_parse_simple() {
@@ -878,13 +2022,1045 @@ mod tests {
     #[test]
     fn test_pi_container_boundaries() {
         let parser = Parser::new();
-        let result = parser.parse("jan pi pona mute en jan pi sona lili.").unwrap();
+        let result = parser
+            .parse("jan pi pona mute en jan pi sona lili.")
+            .unwrap();
+        assert_eq!(result.len(), 1);
+
+        // Each conjunct holds its own pi container
+        assert_eq!(
+            markup_shape(&result[0].parts),
+            "(((jan) pi(pona mute)) en ((jan) pi(sona lili))) period"
+        );
+    }
+
+    #[test]
+    fn test_pi_phrase_grammar() {
+        let parser = Parser::new();
+        let cases = [
+            // Every pi phrase modifies the head
+            (
+                "jan pi ma tomo pi telo suli li kama.",
+                "((jan) pi(ma tomo) pi(telo suli)) li(kama) period",
+            ),
+            (
+                "tomo pi telo nasa li pona.",
+                "((tomo) pi(telo nasa)) li(pona) period",
+            ),
+            // The object of a preposition is a noun phrase of its own
+            (
+                "mi lon tomo pi telo nasa.",
+                "(mi) lon((tomo) pi(telo nasa)) period",
+            ),
+            (
+                "mi tawa ma pi jan mute lon tenpo pi pimeja ale.",
+                "(mi) tawa((ma) pi(jan mute)) lon((tenpo) pi(pimeja ale)) period",
+            ),
+            (
+                "tomo pi kasi lon ma pi telo ala li suli.",
+                "((tomo) pi(kasi)) lon((ma) pi(telo ala)) li(suli) period",
+            ),
+            // pi phrases inside objects and predicates
+            (
+                "mi lukin e jan pi ma tomo pi telo suli.",
+                "(mi lukin) e((jan) pi(ma tomo) pi(telo suli)) period",
+            ),
+            (
+                "ona li jan pi sona mute lon ma pi telo ala.",
+                "(ona) li((jan) pi(sona mute)) lon((ma) pi(telo ala)) period",
+            ),
+            (
+                "ona li lon tomo pi telo nasa.",
+                "(ona) li(lon((tomo) pi(telo nasa))) period",
+            ),
+            // A marker without a word after it is a plain word
+            ("ona li jan pi.", "(ona) li(jan pi) period"),
+            ("jan pi pi ma li kama.", "((jan pi) pi(ma)) li(kama) period"),
+        ];
+        for (text, expected) in cases {
+            let result = parser.parse(text).unwrap();
+            assert_eq!(markup_shape(&result[0].parts), expected, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn test_pi_phrase_spans() {
+        let text = "mi lon tomo pi telo nasa pi kasi suli.";
+        let result = Parser::new().parse(text).unwrap();
+        let SentencePart::PrepPhrase {
+            parts: Some(parts), ..
+        } = &result[0].parts[1]
+        else {
+            panic!("expected a structured prepositional phrase");
+        };
+        let spans: Vec<&str> = parts
+            .iter()
+            .map(|part| &text[part.span().start..part.span().end])
+            .collect();
+        assert_eq!(spans, vec!["tomo", "pi telo nasa", "pi kasi suli"]);
+    }
+
+    #[test]
+    fn test_diagnostics_collect_every_illegal_token() {
+        let parser = Parser::new();
+        let text = "mi mokuu e kili. sina pona. ona li wawaa e xyz.";
+        let (sentences, diagnostics) = parser.parse_with_diagnostics(text);
+
+        assert_eq!(sentences.len(), 3);
+        let found: Vec<(&str, usize, Option<&str>)> = diagnostics
+            .iter()
+            .map(|d| (d.text.as_str(), d.sentence, d.suggestion.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("mokuu", 0, Some("moku")),
+                ("wawaa", 2, Some("wawa")),
+                ("xyz", 2, None),
+            ]
+        );
+        assert_eq!(
+            &text[diagnostics[0].span.start..diagnostics[0].span.end],
+            "mokuu"
+        );
+
+        // The illegal word is kept as a placeholder so the sentence still renders
+        assert!(all_tokens(&sentences[0].parts)
+            .iter()
+            .any(|token| token.as_str() == PLACEHOLDER));
+    }
+
+    #[test]
+    fn test_diagnostics_for_illegal_syllables() {
+        let parser = Parser::new();
+        let text = "jan Tiku li pona.";
+        let (_, diagnostics) = parser.parse_with_diagnostics(text);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::IllegalSyllable);
+        assert_eq!(diagnostics[0].text, "ti");
+        assert_eq!(
+            &text[diagnostics[0].span.start..diagnostics[0].span.end],
+            "Ti"
+        );
+    }
+
+    #[test]
+    fn test_strict_parse_still_fails_on_first_error() {
+        let parser = Parser::new();
+        assert!(matches!(
+            parser.parse("mi mokuu e xyz."),
+            Err(ParseError::IllegalToken(token)) if token == "mokuu"
+        ));
+    }
+
+    /// Collect every token in a part tree, in order
+    fn all_tokens(parts: &[SentencePart]) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        for part in parts {
+            // Conjunctions stand between the conjuncts
+            if let SentencePart::Coordination {
+                tokens: conjunctions,
+                parts,
+                ..
+            } = part
+            {
+                for (index, conjunct) in parts.iter().enumerate() {
+                    if index > 0 {
+                        tokens.push(conjunctions[index - 1].clone());
+                    }
+                    tokens.extend(all_tokens(std::slice::from_ref(conjunct)));
+                }
+                continue;
+            }
+            tokens.extend(part.tokens().iter().cloned());
+            if let SentencePart::Subject {
+                parts: Some(nested),
+                ..
+            }
+            | SentencePart::ObjectMarker {
+                parts: Some(nested),
+                ..
+            }
+            | SentencePart::PrepPhrase {
+                parts: Some(nested),
+                ..
+            } = part
+            {
+                tokens.extend(all_tokens(nested));
+            }
+            if let SentencePart::Preverb { parts, .. } = part {
+                tokens.extend(all_tokens(parts));
+            }
+        }
+        tokens
+    }
+
+    /// Number phrases anywhere in the parts, as their words and value
+    fn numbers(parts: &[SentencePart]) -> Vec<(Vec<&str>, u64)> {
+        let mut found = Vec::new();
+        for part in parts {
+            match part {
+                SentencePart::Number { tokens, value, .. } => {
+                    found.push((texts(tokens), *value));
+                }
+                SentencePart::Subject {
+                    parts: Some(nested),
+                    ..
+                }
+                | SentencePart::ObjectMarker {
+                    parts: Some(nested),
+                    ..
+                }
+                | SentencePart::PrepPhrase {
+                    parts: Some(nested),
+                    ..
+                } => found.extend(numbers(nested)),
+                _ => {}
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn test_token_spans_point_into_source() {
+        let parser = Parser::new();
+        let text = "tenpo ni, la  jan Pona li moku e kili pi  pona mute.";
+        let result = parser.parse(text).unwrap();
+
+        for token in all_tokens(&result[0].parts) {
+            let source = &text[token.span.start..token.span.end];
+            match token.as_str() {
+                "period" => assert_eq!(source, "."),
+                "la" => assert_eq!(source, "la"),
+                word => assert_eq!(source.to_lowercase(), word),
+            }
+        }
+        assert_eq!(result[0].span, Span::new(0, text.len()));
+    }
+
+    #[test]
+    fn test_part_spans_include_separator() {
+        let parser = Parser::new();
+        let text = "mi moku e kili lon tomo.";
+        let result = parser.parse(text).unwrap();
+
+        let spans: Vec<&str> = result[0]
+            .parts
+            .iter()
+            .map(|part| &text[part.span().start..part.span().end])
+            .collect();
+        assert_eq!(spans, vec!["mi moku", "e kili", "lon tomo", "."]);
+    }
+
+    #[test]
+    fn test_cartouche_syllable_spans() {
+        let parser = Parser::new();
+        let text = "jan Kana li pona.";
+        let result = parser.parse(text).unwrap();
+
+        let SentencePart::Subject {
+            parts: Some(nested),
+            ..
+        } = &result[0].parts[0]
+        else {
+            panic!("expected nested subject");
+        };
+        let cartouche = &nested[1];
+        assert_eq!(&text[cartouche.span().start..cartouche.span().end], "Kana");
+
+        let syllables: Vec<(&str, &str)> = cartouche
+            .tokens()
+            .iter()
+            .map(|token| (token.as_str(), &text[token.span.start..token.span.end]))
+            .collect();
+        assert_eq!(syllables, vec![("ka", "Ka"), ("na", "na")]);
+    }
+
+    #[test]
+    fn test_nested_pi_spans() {
+        let parser = Parser::new();
+        let text = "jan pi ma tomo li pona.";
+        let result = parser.parse(text).unwrap();
+
+        let SentencePart::Subject {
+            parts: Some(nested),
+            ..
+        } = &result[0].parts[0]
+        else {
+            panic!("expected nested subject");
+        };
+        let pi = nested
+            .iter()
+            .find(|part| matches!(part, SentencePart::Subject { separator: Some(s), .. } if s == "pi"))
+            .unwrap();
+        assert_eq!(&text[pi.span().start..pi.span().end], "pi ma tomo");
+    }
+
+    #[test]
+    fn test_chained_la_clauses() {
+        let parser = Parser::new();
+        let text = "tenpo pini la mi lon ma ni la mi pilin pona.";
+        let result = parser.parse(text).unwrap();
+
+        let markers: Vec<&str> = result[0]
+            .parts
+            .iter()
+            .filter_map(|part| match part {
+                SentencePart::Punctuation { tokens, span } => {
+                    Some((tokens[0].as_str(), &text[span.start..span.end]))
+                }
+                _ => None,
+            })
+            .map(|(name, source)| {
+                assert!(name != "la" || source == "la");
+                name
+            })
+            .collect();
+        assert_eq!(markers, vec!["la", "la", "period"]);
+
+        let contents: Vec<&str> = result[0]
+            .parts
+            .iter()
+            .filter(|part| !matches!(part, SentencePart::Punctuation { .. }))
+            .map(|part| &text[part.span().start..part.span().end])
+            .collect();
+        assert_eq!(
+            contents,
+            vec!["tenpo pini", "mi", "lon ma ni", "mi pilin pona"]
+        );
+    }
+
+    #[test]
+    fn test_quotation_after_colon() {
+        let parser = Parser::new();
+        let text = "jan li toki e ni: \"mi pona. sina moku.\"";
+        let result = parser.parse(text).unwrap();
+        assert_eq!(result.len(), 1);
+
+        let Some(SentencePart::ObjectMarker {
+            separator,
+            parts: Some(nested),
+            span,
+            ..
+        }) = result[0].parts.last()
+        else {
+            panic!("expected a quote container, got {:?}", result[0].parts);
+        };
+        assert_eq!(separator, QUOTE_SEPARATOR);
+        assert_eq!(&text[span.start..span.end], "\"mi pona. sina moku.\"");
+
+        let [SentencePart::Quotation { sentences, .. }] = &nested[..] else {
+            panic!("expected a quotation, got {nested:?}");
+        };
+        assert_eq!(sentences.len(), 2);
+        let quoted: Vec<&str> = sentences
+            .iter()
+            .map(|sentence| &text[sentence.span.start..sentence.span.end])
+            .collect();
+        assert_eq!(quoted, vec!["mi pona.", "sina moku."]);
+    }
+
+    #[test]
+    fn test_quotation_as_object() {
+        let parser = Parser::new();
+        let text = "ona li toki e “sina pona!” tawa mi.";
+        let result = parser.parse(text).unwrap();
         assert_eq!(result.len(), 1);
-        
-        // Check that the sentence has the expected structure
-        let sentence = &result[0];
-        // The sentence should have parts that correctly contain "pona mute" and "sona lili" in pi-containers
-        // We can't easily test the exact structure without exposing internals, but we can verify it parses
-        assert!(!sentence.parts.is_empty());
+
+        let object = result[0]
+            .parts
+            .iter()
+            .find(|part| matches!(part, SentencePart::ObjectMarker { separator, .. } if separator == "e"))
+            .unwrap();
+        let SentencePart::ObjectMarker {
+            parts: Some(nested),
+            ..
+        } = object
+        else {
+            panic!("expected nested object parts");
+        };
+        let [SentencePart::Quotation { sentences, .. }] = &nested[..] else {
+            panic!("expected a quotation, got {nested:?}");
+        };
+        let tokens = all_tokens(&sentences[0].parts);
+        assert_eq!(texts(&tokens), vec!["sina", "pona", "exclamation"]);
+        assert!(result[0].parts.iter().any(
+            |part| matches!(part, SentencePart::PrepPhrase { separator, .. } if separator == "tawa")
+        ));
+    }
+
+    #[test]
+    fn test_unterminated_quotation_runs_to_end() {
+        let parser = Parser::new();
+        let result = parser.parse("mi toki e \"sina pona").unwrap();
+        assert_eq!(result.len(), 1);
+
+        let (_, diagnostics) = parser.parse_with_diagnostics("mi toki e \"sina mokuu.\"");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].text, "mokuu");
+    }
+
+    /// Part texts of a sentence tree, ignoring spans
+    fn part_texts(sentences: &[Sentence]) -> Vec<Vec<String>> {
+        sentences
+            .iter()
+            .map(|sentence| {
+                all_tokens(&sentence.parts)
+                    .into_iter()
+                    .map(|token| token.text)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ucsur_words_and_punctuation() {
+        let parser = Parser::new();
+        // mi li pona · sina moku
+        let text = "\u{F1934}\u{F1927}\u{F1954}\u{F199C} \u{F195E} \u{F1936}";
+        let result = parser.parse(text).unwrap();
+        let latin = parser.parse("mi li pona. sina moku").unwrap();
+        assert_eq!(part_texts(&result), part_texts(&latin));
+
+        for token in all_tokens(&result[0].parts) {
+            assert_eq!(token.span.len(), 4, "{token:?}");
+        }
+    }
+
+    #[test]
+    fn test_ucsur_cartouche_spells_name() {
+        let parser = Parser::new();
+        // jan [kala alasa nasin alasa] li pona
+        let text =
+            "\u{F1911}\u{F1990}\u{F1914}\u{F1903}\u{F193F}\u{F1903}\u{F1991}\u{F1927}\u{F1954}";
+        let result = parser.parse(text).unwrap();
+        let latin = parser.parse("jan Kana li pona").unwrap();
+        assert_eq!(part_texts(&result), part_texts(&latin));
+
+        let syllables: Vec<(String, &str)> = all_tokens(&result[0].parts)
+            .into_iter()
+            .filter(|token| token.text.len() == 2)
+            .map(|token| (token.text, &text[token.span.start..token.span.end]))
+            .collect();
+        assert_eq!(
+            syllables,
+            vec![
+                ("ka".to_string(), "\u{F1914}\u{F1903}"),
+                ("na".to_string(), "\u{F193F}\u{F1903}")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mixed_latin_and_ucsur() {
+        let parser = Parser::new();
+        let result = parser.parse("mi lukin e \u{F1960}\u{F1954}.").unwrap();
+        let latin = parser.parse("mi lukin e sitelen pona.").unwrap();
+        assert_eq!(part_texts(&result), part_texts(&latin));
+    }
+
+    fn cartouche_syllables(parser: &Parser, text: &str) -> Vec<(String, String)> {
+        let result = parser.parse(text).unwrap();
+        all_tokens(&result[0].parts)
+            .into_iter()
+            .skip(1)
+            .map(|token| {
+                (
+                    token.text,
+                    text[token.span.start..token.span.end].to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_strict_syllables_report_leftovers() {
+        let parser = Parser::new();
+        assert!(matches!(
+            parser.parse("jan Olaf"),
+            Err(ParseError::IllegalSyllable(syllable)) if syllable == "f"
+        ));
+    }
+
+    #[test]
+    fn test_lenient_syllables() {
+        let mut parser = Parser::new();
+        parser.set_syllabifier(SyllableMode::Lenient);
+        let syllables = cartouche_syllables(&parser, "jan Olaf");
+        let texts: Vec<&str> = syllables.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(texts, vec!["o", "la", "pu"]);
+        // Respelled names cannot point at single letters
+        assert!(syllables.iter().all(|(_, source)| source == "Olaf"));
+
+        let syllables = cartouche_syllables(&parser, "jan Kanata");
+        assert_eq!(syllables[2], ("ta".to_string(), "ta".to_string()));
+    }
+
+    #[test]
+    fn test_explicit_syllables_override() {
+        let mut parser = Parser::new();
+        let syllables = cartouche_syllables(&parser, "jan [o-la-pu] li pona");
+        assert_eq!(
+            syllables[..3],
+            [
+                ("o".to_string(), "o".to_string()),
+                ("la".to_string(), "la".to_string()),
+                ("pu".to_string(), "pu".to_string())
+            ]
+        );
+
+        // The override bypasses the syllabifier, so illegal syllables are still reported
+        parser.set_syllabifier(SyllableMode::Lenient);
+        let (_, diagnostics) = parser.parse_with_diagnostics("jan [ti-mo]");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].text, "ti");
+        assert_eq!(diagnostics[0].span, Span::new(5, 7));
+    }
+
+    /// Syllables drawn in cartouches, with the source text each one covers
+    fn name_syllables<'a>(sentences: &[Sentence], text: &'a str) -> Vec<(String, &'a str)> {
+        all_tokens(&sentences[0].parts)
+            .into_iter()
+            .filter(|token| token.kind == TokenKind::Syllable)
+            .map(|token| (token.text, &text[token.span.start..token.span.end]))
+            .collect()
+    }
+
+    #[test]
+    fn test_sentence_initial_capital_is_a_word() {
+        let parser = Parser::new();
+        let result = parser.parse("Mi pona. Sina moku e kili.").unwrap();
+        let lower = parser.parse("mi pona. sina moku e kili.").unwrap();
+        assert_eq!(part_texts(&result), part_texts(&lower));
+
+        // A capitalised first word outside the lexicon is still a name
+        let text = "Ana li pona.";
+        let result = parser.parse(text).unwrap();
+        assert_eq!(
+            name_syllables(&result, text),
+            vec![("a".to_string(), "A"), ("na".to_string(), "na")]
+        );
+    }
+
+    #[test]
+    fn test_repeated_and_overlapping_names() {
+        let text = "jan Ana en jan Anana li lukin e jan Ana.";
+        let result = Parser::new().parse(text).unwrap();
+        let syllables = name_syllables(&result, text);
+        let texts: Vec<&str> = syllables.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(texts, vec!["a", "na", "a", "na", "na", "a", "na"]);
+        let sources: Vec<&str> = syllables.iter().map(|(_, source)| *source).collect();
+        assert_eq!(sources, vec!["A", "na", "A", "na", "na", "A", "na"]);
+        assert_eq!(
+            all_tokens(&result[0].parts)
+                .iter()
+                .filter(|token| token.kind == TokenKind::Syllable)
+                .map(|token| token.span.start)
+                .collect::<Vec<_>>(),
+            vec![4, 5, 15, 16, 18, 36, 37]
+        );
+    }
+
+    #[test]
+    fn test_marked_up_names() {
+        let text = "{pona} li pona.";
+        let result = Parser::new().parse(text).unwrap();
+        assert_eq!(
+            name_syllables(&result, text),
+            vec![("po".to_string(), "po"), ("na".to_string(), "na")]
+        );
+        assert!(all_tokens(&result[0].parts)
+            .iter()
+            .any(|token| token.is_word("pona")));
+
+        // Name words such as li are not read as markers
+        let result = Parser::new().parse("jan {Li} li pona.").unwrap();
+        assert_eq!(name_syllables(&result, "jan {Li} li pona.").len(), 1);
+    }
+
+    #[test]
+    fn test_explicit_name_detection() {
+        let mut parser = Parser::new();
+        parser.set_name_detection(NameDetection::Explicit);
+
+        let result = parser.parse("jan Pona li pona.").unwrap();
+        let lower = parser.parse("jan pona li pona.").unwrap();
+        assert_eq!(part_texts(&result), part_texts(&lower));
+        assert!(matches!(
+            parser.parse("jan Kana li pona."),
+            Err(ParseError::IllegalToken(token)) if token == "kana"
+        ));
+
+        let text = "jan {Kana} li pona.";
+        let result = parser.parse(text).unwrap();
+        assert_eq!(
+            name_syllables(&result, text),
+            vec![("ka".to_string(), "Ka"), ("na".to_string(), "na")]
+        );
+    }
+
+    #[test]
+    fn test_placeholder_lookalikes_are_words() {
+        let parser = Parser::new();
+        assert!(matches!(
+            parser.parse("mi toki e 'Quote'."),
+            Err(ParseError::IllegalToken(token)) if token == "'quote'"
+        ));
+        assert!(matches!(
+            parser.parse("mi toki e 'Name'."),
+            Err(ParseError::IllegalToken(token)) if token == "'name'"
+        ));
+    }
+
+    #[test]
+    fn test_number_phrases_are_off_by_default() {
+        let result = Parser::new().parse("jan luka tu li kama.").unwrap();
+        assert!(numbers(&result[0].parts).is_empty());
+    }
+
+    #[test]
+    fn test_pona_number_phrases() {
+        let mut parser = Parser::new();
+        parser.set_number_system(NumberSystem::Pona);
+
+        let text = "jan mute luka tu li kama. mi jo e kili tu ale wan.";
+        let result = parser.parse(text).unwrap();
+        assert_eq!(
+            numbers(&result[0].parts),
+            vec![(vec!["mute", "luka", "tu"], 27)]
+        );
+        assert_eq!(
+            numbers(&result[1].parts),
+            vec![(vec!["tu", "ale", "wan"], 201)]
+        );
+
+        // Single number words keep their usual reading, except after nanpa
+        let result = parser.parse("jan mute li lukin e lipu nanpa tu.").unwrap();
+        assert_eq!(numbers(&result[0].parts), vec![(vec!["tu"], 2)]);
+
+        // Words in the wrong order are not a number
+        let result = parser.parse("ona li tu luka.").unwrap();
+        assert!(numbers(&result[0].parts).is_empty());
+    }
+
+    #[test]
+    fn test_pu_number_phrases() {
+        let mut parser = Parser::new();
+        parser.set_number_system(NumberSystem::Pu);
+        let text = "soweli tu wan li moku e kili mute luka.";
+        let result = parser.parse(text).unwrap();
+        let found = numbers(&result[0].parts);
+        assert_eq!(found, vec![(vec!["tu", "wan"], 3)]);
+
+        // The words around a number phrase keep their spans and names
+        let result = parser.parse("jan Ana tu tu li kama.").unwrap();
+        assert_eq!(numbers(&result[0].parts)[0].1, 4);
+        assert!(all_tokens(&result[0].parts)
+            .iter()
+            .any(|token| token.kind == TokenKind::Syllable));
+    }
+
+    /// Structured predicate of the first sentence (the parts after the subject)
+    fn predicate(text: &str) -> Vec<SentencePart> {
+        let result = Parser::new().parse(text).unwrap();
+        match &result[0].parts[..] {
+            [SentencePart::Subject {
+                parts: Some(parts), ..
+            }, ..] => parts[1..].to_vec(),
+            [_, SentencePart::ObjectMarker {
+                parts: Some(parts), ..
+            }, ..] => parts.clone(),
+            _ => panic!("expected a structured predicate in {text:?}"),
+        }
+    }
+
+    fn texts(tokens: &[Token]) -> Vec<&str> {
+        tokens.iter().map(Token::as_str).collect()
+    }
+
+    #[test]
+    fn test_preverb_groups_with_verb() {
+        let parts = predicate("mi wile moku.");
+        let [SentencePart::Preverb { tokens, parts, .. }] = &parts[..] else {
+            panic!("expected a preverb, got {parts:?}");
+        };
+        assert_eq!(texts(tokens), vec!["wile"]);
+        assert_eq!(texts(parts[0].tokens()), vec!["moku"]);
+    }
+
+    #[test]
+    fn test_chained_and_negated_preverbs() {
+        let parts = predicate("ona li wile ala kama sona.");
+        let [SentencePart::Preverb { tokens, parts, .. }] = &parts[..] else {
+            panic!("expected a preverb, got {parts:?}");
+        };
+        assert_eq!(texts(tokens), vec!["wile", "ala"]);
+        let [SentencePart::Preverb { tokens, parts, .. }] = &parts[..] else {
+            panic!("expected a nested preverb, got {parts:?}");
+        };
+        assert_eq!(texts(tokens), vec!["kama"]);
+        assert_eq!(texts(parts[0].tokens()), vec!["sona"]);
+    }
+
+    #[test]
+    fn test_negation_and_ala_question() {
+        let parts = predicate("mi moku ala e kili.");
+        assert!(
+            matches!(&parts[..], [SentencePart::Negation { tokens, .. }] if texts(tokens) == ["moku", "ala"])
+        );
+
+        let parts = predicate("sina pona ala pona?");
+        assert!(
+            matches!(&parts[..], [SentencePart::AlaQuestion { tokens, .. }] if texts(tokens) == ["pona", "ala", "pona"])
+        );
+
+        let parts = predicate("ona li ken ala ken tawa?");
+        let [SentencePart::Preverb { tokens, .. }] = &parts[..] else {
+            panic!("expected a preverb, got {parts:?}");
+        };
+        assert_eq!(texts(tokens), vec!["ken", "ala", "ken"]);
+    }
+
+    #[test]
+    fn test_plain_predicates_stay_flat() {
+        let parser = Parser::new();
+        for text in [
+            "mi wile e kili.",
+            "mi mute li moku.",
+            "ona li kama.",
+            "jan ala li lon.",
+        ] {
+            let result = parser.parse(text).unwrap();
+            assert!(
+                result[0].parts.iter().all(|part| !matches!(
+                    part,
+                    SentencePart::Subject { parts: Some(_), .. }
+                        | SentencePart::ObjectMarker { parts: Some(_), .. }
+                )),
+                "{text:?} should not be structured"
+            );
+        }
+    }
+
+    #[test]
+    fn test_preverb_spans_and_nested_containers() {
+        let text = "ona li kama jan pi sona mute.";
+        let result = Parser::new().parse(text).unwrap();
+        let tokens = all_tokens(&result[0].parts[..2]);
+        assert_eq!(texts(&tokens), vec!["ona", "kama", "jan", "sona", "mute"]);
+        for token in &tokens {
+            assert_eq!(&text[token.span.start..token.span.end], token.as_str());
+        }
+
+        let parts = predicate(text);
+        let [SentencePart::Preverb { parts, span, .. }] = &parts[..] else {
+            panic!("expected a preverb, got {parts:?}");
+        };
+        assert_eq!(&text[span.start..span.end], "kama jan pi sona mute");
+        assert!(matches!(
+            &parts[0],
+            SentencePart::Subject { parts: Some(_), .. }
+        ));
+    }
+
+    /// Parts written back as markup, with the punctuation by name
+    fn markup_shape(parts: &[SentencePart]) -> String {
+        let shape =
+            |separator: Option<&str>, tokens: &[Token], parts: Option<&Vec<SentencePart>>| {
+                let inner = match parts {
+                    Some(parts) => markup_shape(parts),
+                    None => texts(tokens).join(" "),
+                };
+                format!("{}({})", separator.unwrap_or(""), inner)
+            };
+        let shapes: Vec<String> = parts
+            .iter()
+            .map(|part| match part {
+                SentencePart::Subject {
+                    separator,
+                    tokens,
+                    parts,
+                    ..
+                } => shape(separator.as_deref(), tokens, parts.as_ref()),
+                SentencePart::ObjectMarker {
+                    separator,
+                    tokens,
+                    parts,
+                    ..
+                }
+                | SentencePart::PrepPhrase {
+                    separator,
+                    tokens,
+                    parts,
+                    ..
+                } => shape(Some(separator), tokens, parts.as_ref()),
+                SentencePart::Address {
+                    separator,
+                    tokens,
+                    parts,
+                    ..
+                } => shape(Some(separator), tokens, parts.as_ref()),
+                SentencePart::Preverb { tokens, parts, .. } => {
+                    shape(Some(tokens[0].as_str()), &[], Some(parts))
+                }
+                SentencePart::Coordination { tokens, parts, .. } => {
+                    let mut conjuncts = vec![markup_shape(&parts[..1])];
+                    for (conjunction, part) in tokens.iter().zip(&parts[1..]) {
+                        conjuncts.push(conjunction.text.clone());
+                        conjuncts.push(markup_shape(std::slice::from_ref(part)));
+                    }
+                    conjuncts.join(" ")
+                }
+                other => texts(other.tokens()).join(" "),
+            })
+            .collect();
+        shapes.join(" ")
+    }
+
+    #[test]
+    fn test_coordinated_conjuncts() {
+        let parser = Parser::new();
+        let shapes: Vec<String> = [
+            "mi en sina li moku.",
+            "mi wile e kili anu telo.",
+            "jan pi pona mute en jan pi sona lili li kama.",
+            "ona li lon tomo anu seme?",
+            "en mi li pona.",
+        ]
+        .iter()
+        .map(|text| markup_shape(&parser.parse(text).unwrap()[0].parts))
+        .collect();
+        assert_eq!(
+            shapes,
+            vec![
+                "((mi) en (sina)) li(moku) period",
+                "(mi wile) e((kili) anu (telo)) period",
+                "(((jan) pi(pona mute)) en ((jan) pi(sona lili))) li(kama) period",
+                "(ona) li(lon(tomo) (anu seme)) question",
+                "(en mi) li(pona) period",
+            ]
+        );
+
+        // The coordination spans all conjuncts and keeps the conjunctions
+        let text = "mi en sina en ona li pona.";
+        let result = parser.parse(text).unwrap();
+        let SentencePart::Subject {
+            parts: Some(parts), ..
+        } = &result[0].parts[0]
+        else {
+            panic!("expected a structured subject");
+        };
+        let [SentencePart::Coordination {
+            tokens,
+            parts,
+            span,
+        }] = &parts[..]
+        else {
+            panic!("expected a coordination, got {parts:?}");
+        };
+        assert_eq!(texts(tokens), vec!["en", "en"]);
+        assert_eq!(parts.len(), 3);
+        assert_eq!(&text[span.start..span.end], "mi en sina en ona");
+    }
+
+    #[test]
+    fn test_vocatives_commands_and_interjections() {
+        let parser = Parser::new();
+        let cases = [
+            (
+                "jan Ali o, moku.",
+                "o((jan) cartouche(a li)) comma (moku) period",
+            ),
+            ("sina o moku.", "o(sina) o(moku) period"),
+            ("jan Ali o kama.", "o((jan) cartouche(a li)) o(kama) period"),
+            (
+                "toki, jan Ali o kama.",
+                "(toki) comma o((jan) cartouche(a li)) o(kama) period",
+            ),
+            ("o moku e kili.", "o(moku) e(kili) period"),
+            ("o wile ala lape!", "o(wile((lape))) exclamation"),
+            ("o tawa tomo a!", "o(tawa(tomo)) a exclamation"),
+            ("tenpo ni la o lape.", "(tenpo ni) la o(lape) period"),
+            (
+                "jan Ali o, o kama.",
+                "o((jan) cartouche(a li)) comma o(kama) period",
+            ),
+            ("o, sina pona.", "o comma (sina pona) period"),
+            ("mi moku a a a.", "(mi moku) a a a period"),
+            ("mi a li moku.", "(mi) a li(moku) period"),
+            ("a a a!", "a a a exclamation"),
+            (
+                "toki, jan Ali o!",
+                "toki comma o((jan) cartouche(a li)) exclamation",
+            ),
+            ("pona kin!", "pona kin exclamation"),
+            ("pona!", "pona exclamation"),
+            ("pona.", "(pona) period"),
+            ("toki.", "(toki) period"),
+            ("pona kin.", "pona kin period"),
+            ("pona la mi kama.", "(pona) la (mi kama) period"),
+            ("ona li pona kin.", "(ona) li(pona kin) period"),
+        ];
+        for (text, expected) in cases {
+            let sentences = parser.parse(text).unwrap();
+            assert_eq!(markup_shape(&sentences[0].parts), expected, "{text:?}");
+        }
+
+        // A vocative stands before the words said to it, a command opens its clause
+        let parts = &parser.parse("jan Ali o, o kama.").unwrap()[0].parts;
+        assert!(matches!(parts[0], SentencePart::Address { .. }));
+        assert!(matches!(parts[2], SentencePart::ObjectMarker { .. }));
+        let parts = &parser.parse("toki a!").unwrap()[0].parts;
+        let SentencePart::Interjection { tokens, span } = &parts[0] else {
+            panic!("expected an interjection, got {parts:?}");
+        };
+        assert_eq!(texts(tokens), vec!["toki", "a"]);
+        assert_eq!((span.start, span.end), (0, 6));
+
+        // Names addressed by o are cartouches, whose syllables are checked
+        let (_, diagnostics) = parser.parse_with_diagnostics("jan Tiku o, moku.");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::IllegalSyllable);
+        assert_eq!(diagnostics[0].text, "ti");
+
+        // A word on its own is only an exclamation when the sentence ends in `!`
+        let parts = &parser.parse("pona.").unwrap()[0].parts;
+        assert!(
+            matches!(parts[0], SentencePart::Subject { .. }),
+            "{parts:?}"
+        );
+    }
+
+    #[test]
+    fn test_parse_document() {
+        let parser = Parser::new();
+        let text = "# toki pona\n\nmi pona. sina pona.\n\nkon li tawa\ntelo li tawa.\n";
+        let document = parser.parse_document(text).unwrap();
+
+        let kinds: Vec<(&str, Vec<usize>)> = document
+            .blocks
+            .iter()
+            .map(|block| {
+                let kind = match block {
+                    Block::Heading { level: 1, .. } => "heading",
+                    Block::Paragraph { .. } => "paragraph",
+                    Block::Stanza { .. } => "stanza",
+                    Block::Heading { .. } => "subheading",
+                };
+                (kind, block.lines().iter().map(|line| line.len()).collect())
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("heading", vec![1]),
+                ("paragraph", vec![2]),
+                ("stanza", vec![1, 1])
+            ]
+        );
+
+        // Spans refer to the whole text
+        let spans: Vec<&str> = document
+            .sentences()
+            .map(|sentence| &text[sentence.span.start..sentence.span.end])
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                "toki pona",
+                "mi pona.",
+                "sina pona.",
+                "kon li tawa",
+                "telo li tawa."
+            ]
+        );
+        assert_eq!(
+            &text[document.blocks[2].span().start..],
+            "kon li tawa\ntelo li tawa.\n"
+        );
+    }
+
+    #[test]
+    fn test_document_diagnostics_count_sentences_across_blocks() {
+        let parser = Parser::new();
+        let (document, diagnostics) =
+            parser.parse_document_with_diagnostics("mi pona. sina pona.\n\nona li wawaa.");
+        assert_eq!(document.sentences().count(), 3);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].sentence, 2);
+        assert!(matches!(
+            parser.parse_document("# mi mokuu"),
+            Err(ParseError::IllegalToken(_))
+        ));
+    }
+
+    fn markup_parser() -> Parser {
+        let mut parser = Parser::new();
+        parser.set_input_mode(InputMode::Markup);
+        parser
+    }
+
+    #[test]
+    fn test_markup_forces_structure() {
+        let parser = markup_parser();
+        // Parsed as text, poka would open a prepositional phrase
+        let result = parser
+            .parse("(mi) li[lukin] e(poka) lon[tomo pi(telo nasa)]. sina o[kama]!")
+            .unwrap();
+        let shapes: Vec<String> = result.iter().map(|s| markup_shape(&s.parts)).collect();
+        assert_eq!(
+            shapes,
+            vec![
+                "(mi) li(lukin) e(poka) lon((tomo) pi(telo nasa)) period",
+                "(sina) o(kama) exclamation",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markup_preverbs_names_and_la() {
+        let text = "(tenpo ni) la (jan Ana) li[wile(moku e(kili))], {Kala} li[pona]";
+        let result = markup_parser().parse(text).unwrap();
+        assert_eq!(
+            markup_shape(&result[0].parts),
+            "(tenpo ni) la ((jan) cartouche(a na)) li(wile((moku) e(kili))) comma \
+             (cartouche(ka la)) li(pona)"
+        );
+
+        // Spans cover the separator and the brackets
+        let SentencePart::ObjectMarker { span, .. } = &result[0].parts[3] else {
+            panic!("expected an object marker");
+        };
+        assert_eq!(&text[span.start..span.end], "li[wile(moku e(kili))]");
+    }
+
+    #[test]
+    fn test_markup_errors() {
+        let parser = markup_parser();
+        assert!(matches!(
+            parser.parse("(mi) li[moku"),
+            Err(ParseError::InvalidMarkup(_))
+        ));
+        assert!(matches!(
+            parser.parse("(mi) li[mokuu]"),
+            Err(ParseError::IllegalToken(_))
+        ));
+
+        let text = "(mi)) li[moku] ala(pona) ()";
+        let (result, diagnostics) = parser.parse_with_diagnostics(text);
+        let problems: Vec<(&str, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.text.as_str(), &text[d.span.start..d.span.end]))
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                ("unexpected ')'", ")"),
+                ("'ala' is not a separator", "ala"),
+                ("empty group", "()"),
+            ]
+        );
+        assert_eq!(markup_shape(&result[0].parts), "(mi) li(moku) (pona)");
     }
 }
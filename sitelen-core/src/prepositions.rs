@@ -0,0 +1,330 @@
+//! Deciding whether a preposition word opens a prepositional phrase
+//!
+//! Words such as `tawa`, `lon` and `poka` are also verbs, nouns and modifiers:
+//! `mi tawa` (I go), `ona li lon` (it exists), `jan poka mi` (my neighbour). The
+//! parser asks [`disambiguate`] for every clause, and the rules below are tried in
+//! order; the first that applies decides. A decision can be overridden per token
+//! with `Parser::set_preposition_overrides`.
+
+use crate::lexicon::Lexicon;
+use crate::types::{Span, Token, TokenKind};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// How a preposition word is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrepositionUse {
+    /// Opens a prepositional phrase drawn in its own container
+    Preposition,
+    /// Content word: verb, noun or modifier
+    Content,
+}
+
+/// The rule behind a decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrepositionRule {
+    /// Set by the caller for this token
+    Override,
+    /// Nothing but an interjection follows in the clause or conjunct: `mi tawa`,
+    /// `soweli li lon anu moli`, `sina o tawa a`
+    EndOfClause,
+    /// Directly before `li` or `e`: `jan poka li kama`
+    BeforeObjectMarker,
+    /// Negated by a following `ala`, or repeated in a question: `mi tawa ala tawa`
+    Negated,
+    /// Head of a noun phrase regrouped by `pi`: `li tan pi mani ma`
+    BeforePi,
+    /// A verb followed by a preposition other than `poka`: `o tawa tan nasin`
+    BeforePreposition,
+    /// Directly after `li`, `e` or the `o` of a command; the phrase is drawn inside that
+    /// container: `ona li lon tomo`, `o tawa tomo`
+    AfterObjectMarker,
+    /// Head of the phrase of the preposition before it: `tawa poka sina`
+    ObjectOfPreposition,
+    /// Modifies a noun before a possessive pronoun: `jan poka mi li pona`
+    Modifier,
+    /// None of the above
+    Default,
+}
+
+/// Decision for one preposition word
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrepositionDecision {
+    pub word: String,
+    pub span: Span,
+    #[serde(rename = "use")]
+    pub usage: PrepositionUse,
+    pub rule: PrepositionRule,
+}
+
+/// Decide the use of every preposition word in a clause
+///
+/// Returns one entry per token, `None` for tokens that are not preposition words.
+/// `overrides` maps the start offset of a token to the use it is forced to.
+pub fn disambiguate(
+    lexicon: &Lexicon,
+    tokens: &[Token],
+    overrides: &HashMap<usize, PrepositionUse>,
+) -> Vec<Option<PrepositionDecision>> {
+    let is_object_marker =
+        |i: usize| word_at(tokens, i).is_some_and(|word| lexicon.is_object_marker(&word));
+    // o opening a clause marks a command as li marks a predicate
+    let opens_predicate =
+        |i: usize| is_object_marker(i) || (i == 0 && word_at(tokens, 0).as_deref() == Some("o"));
+
+    let mut decisions: Vec<Option<PrepositionDecision>> = Vec::with_capacity(tokens.len());
+    for (i, token) in tokens.iter().enumerate() {
+        let word = token.text.to_lowercase();
+        if token.kind != TokenKind::Word || !lexicon.is_preposition(&word) {
+            decisions.push(None);
+            continue;
+        }
+
+        let next = word_at(tokens, i + 1);
+        let after_preposition = i > 0
+            && decisions[i - 1]
+                .as_ref()
+                .is_some_and(|decision| decision.usage == PrepositionUse::Preposition);
+        let question = i > 1
+            && word_at(tokens, i - 1).as_deref() == Some("ala")
+            && word_at(tokens, i - 2).as_deref() == Some(word.as_str());
+
+        // A phrase needs words to hold, so the end of a clause cannot be overridden
+        let (usage, rule) =
+            if i + 1 == tokens.len() || matches!(next.as_deref(), Some("en" | "anu" | "a")) {
+                (PrepositionUse::Content, PrepositionRule::EndOfClause)
+            } else if let Some(usage) = overrides.get(&token.span.start) {
+                (*usage, PrepositionRule::Override)
+            } else if is_object_marker(i + 1) {
+                (PrepositionUse::Content, PrepositionRule::BeforeObjectMarker)
+            } else if next.as_deref() == Some("ala") || question {
+                (PrepositionUse::Content, PrepositionRule::Negated)
+            } else if next.as_deref() == Some("pi") {
+                (PrepositionUse::Content, PrepositionRule::BeforePi)
+            } else if next.is_some_and(|next| next != "poka" && lexicon.is_preposition(&next)) {
+                (PrepositionUse::Content, PrepositionRule::BeforePreposition)
+            } else if i > 0 && opens_predicate(i - 1) {
+                (
+                    PrepositionUse::Preposition,
+                    PrepositionRule::AfterObjectMarker,
+                )
+            } else if after_preposition {
+                (
+                    PrepositionUse::Content,
+                    PrepositionRule::ObjectOfPreposition,
+                )
+            } else if modifies_noun(tokens, i) {
+                (PrepositionUse::Content, PrepositionRule::Modifier)
+            } else {
+                (PrepositionUse::Preposition, PrepositionRule::Default)
+            };
+
+        decisions.push(Some(PrepositionDecision {
+            word,
+            span: token.span,
+            usage,
+            rule,
+        }));
+    }
+
+    decisions
+}
+
+/// Whether the preposition word at `i` modifies the noun before it, as in `meli sama mi`
+///
+/// The noun must start a subject or follow `pi`, `en` or `anu`, and the word must be
+/// followed by a pronoun that ends the noun phrase. Objects and predicates are left
+/// alone, since `e pona tawa mi` reads as "goodness to me".
+fn modifies_noun(tokens: &[Token], i: usize) -> bool {
+    let starts_phrase = match i {
+        1 => !matches!(word_at(tokens, 0).as_deref(), Some("mi" | "sina")),
+        2.. => matches!(word_at(tokens, i - 2).as_deref(), Some("pi" | "en" | "anu")),
+        _ => false,
+    };
+    if !starts_phrase
+        || !matches!(
+            word_at(tokens, i + 1).as_deref(),
+            Some("mi" | "sina" | "ona")
+        )
+    {
+        return false;
+    }
+
+    let mut end = i + 2;
+    if word_at(tokens, end).as_deref() == Some("mute") {
+        end += 1;
+    }
+    end == tokens.len()
+        || matches!(
+            word_at(tokens, end).as_deref(),
+            Some("li" | "o" | "en" | "anu")
+        )
+}
+
+/// Lower case text of the token at `i`, if it is a word
+fn word_at(tokens: &[Token], i: usize) -> Option<String> {
+    tokens
+        .get(i)
+        .filter(|token| token.kind == TokenKind::Word)
+        .map(|token| token.text.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::types::SentencePart;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Example {
+        toki: String,
+    }
+
+    /// Decisions for the preposition words of a text, as `word:use`
+    fn decisions(parser: &Parser, text: &str) -> Vec<String> {
+        parser
+            .preposition_decisions(text)
+            .into_iter()
+            .map(|decision| {
+                let usage = match decision.usage {
+                    PrepositionUse::Preposition => "prep",
+                    PrepositionUse::Content => "content",
+                };
+                format!("{}:{}", decision.word, usage)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tatoeba_examples() {
+        let parser = Parser::new();
+        let cases: &[(&str, &[&str])] = &[
+            ("mi wile tawa poka sina.", &["tawa:prep", "poka:content"]),
+            ("ni li pona tawa mi.", &["tawa:prep"]),
+            ("pakala ni li tan ala mi.", &["tan:content"]),
+            ("o tawa ala weka.", &["tawa:content"]),
+            ("o tawa tan nasin.", &["tawa:content", "tan:prep"]),
+            (
+                "sina lon ala lon tomo lon tenpo pimeja kama?",
+                &["lon:content", "lon:content", "lon:prep"],
+            ),
+            ("meli sama mi li lili. sina suli.", &["sama:content"]),
+            (
+                "tomo tawa sina li suli. tomo tawa mi li lili.",
+                &["tawa:content", "tawa:content"],
+            ),
+            (
+                "o lukin e pali pi meli sama sina, o pali e sama.",
+                &["sama:content", "sama:content"],
+            ),
+            ("mi wile e ni: sina pana e pona tawa mi.", &["tawa:prep"]),
+            (
+                "mije sama mi li awen lon ma tomo Tokijo.",
+                &["sama:content", "lon:prep"],
+            ),
+            (
+                "ona li lawa e tomo tawa, la o toki ala tawa ona.",
+                &["tawa:content", "tawa:prep"],
+            ),
+            (
+                "jan mute li tawa lon ali poka ala tan.",
+                &["tawa:content", "lon:prep", "poka:content", "tan:content"],
+            ),
+            (
+                "mi kama sona lon kon seli ni, la ona li ike tawa mi.",
+                &["lon:prep", "tawa:prep"],
+            ),
+        ];
+
+        let corpus: Vec<Example> =
+            serde_json::from_str(include_str!("../../examples/tatoeba/tokitatoeba.json")).unwrap();
+        for (text, expected) in cases {
+            assert!(
+                corpus.iter().any(|example| example.toki == *text),
+                "{text:?} is not in the corpus"
+            );
+            assert_eq!(decisions(&parser, text), *expected, "{text:?}");
+        }
+    }
+
+    /// Separator and start offset of every prepositional phrase and container in the parts
+    fn containers<'a>(parts: &'a [SentencePart], found: &mut Vec<(&'a str, usize)>) {
+        for part in parts {
+            match part {
+                SentencePart::PrepPhrase {
+                    separator,
+                    span,
+                    parts,
+                    ..
+                }
+                | SentencePart::Subject {
+                    separator: Some(separator),
+                    span,
+                    parts,
+                    ..
+                } => {
+                    found.push((separator, span.start));
+                    containers(parts.as_deref().unwrap_or_default(), found);
+                }
+                SentencePart::Subject { parts, .. } | SentencePart::ObjectMarker { parts, .. } => {
+                    containers(parts.as_deref().unwrap_or_default(), found)
+                }
+                SentencePart::Preverb { parts, .. } | SentencePart::Coordination { parts, .. } => {
+                    containers(parts, found)
+                }
+                SentencePart::Quotation { sentences, .. } => {
+                    for sentence in sentences {
+                        containers(&sentence.parts, found);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_follows_decisions_across_corpus() {
+        // Prepositions open a container and content words never do
+        let parser = Parser::new();
+        let corpus: Vec<Example> =
+            serde_json::from_str(include_str!("../../examples/tatoeba/tokitatoeba.json")).unwrap();
+        for example in corpus {
+            let Ok(sentences) = parser.parse(&example.toki) else {
+                continue;
+            };
+            let mut found = Vec::new();
+            for sentence in &sentences {
+                containers(&sentence.parts, &mut found);
+            }
+            for decision in parser.preposition_decisions(&example.toki) {
+                assert_eq!(
+                    found.contains(&(decision.word.as_str(), decision.span.start)),
+                    decision.usage == PrepositionUse::Preposition,
+                    "{} in {:?}",
+                    decision.word,
+                    example.toki
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_overrides() {
+        let mut parser = Parser::new();
+        let text = "jan poka mi li pona.";
+        assert_eq!(decisions(&parser, text), vec!["poka:content"]);
+
+        parser.set_preposition_overrides(HashMap::from([(4, PrepositionUse::Preposition)]));
+        let decision = &parser.preposition_decisions(text)[0];
+        assert_eq!(decision.rule, PrepositionRule::Override);
+        assert_eq!(decision.usage, PrepositionUse::Preposition);
+        let parts = &parser.parse(text).unwrap()[0].parts;
+        assert!(matches!(
+            &parts[1],
+            SentencePart::PrepPhrase { separator, .. } if separator == "poka"
+        ));
+    }
+}
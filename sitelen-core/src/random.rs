@@ -0,0 +1,69 @@
+//! Seeded random numbers for choosing layouts
+//!
+//! Layouts picked at random must come out the same for the same text and seed, on
+//! every platform, so a small SplitMix64 generator is used rather than an entropy
+//! source.
+
+/// SplitMix64 pseudo-random number generator
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Generator for one piece of text, so its numbers do not depend on the text around it
+    pub(crate) fn for_text(seed: u64, text: &str) -> Self {
+        // FNV-1a
+        let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        Self::new(seed ^ hash)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Number in `0.0..1.0`
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Index in `0..count`; `count` must not be zero
+    pub(crate) fn below(&mut self, count: usize) -> usize {
+        (self.next_f64() * count as f64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng() {
+        // Reference values of SplitMix64 seeded with 0
+        let mut rng = Rng::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+
+        let mut a = Rng::for_text(7, "mi pona.");
+        let mut b = Rng::for_text(7, "mi pona.");
+        for _ in 0..100 {
+            let index = a.below(5);
+            assert!(index < 5);
+            assert_eq!(index, b.below(5));
+        }
+        assert_ne!(
+            Rng::for_text(7, "mi pona.").next_u64(),
+            Rng::for_text(8, "mi pona.").next_u64()
+        );
+    }
+}
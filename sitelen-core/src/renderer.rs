@@ -1,9 +1,30 @@
 /// SVG renderer for Sitelen Sitelen
 use crate::config::{OutputFormat, RenderConfig};
 use crate::glyphs::{get_glyph_registry, GlyphError, GlyphRegistry};
+use crate::layout::{COORDINATION_SEPARATOR, IMPERATIVE_SEPARATOR, QUOTATION_SEPARATOR};
+use crate::lexicon::Lexicon;
 use crate::types::*;
 use std::collections::HashSet;
 use std::fmt::Write;
+use std::sync::Arc;
+
+/// Container glyph of a separator: a command is drawn in the `o` container, as an address is
+fn container_glyph(separator: &str) -> &str {
+    if separator == IMPERATIVE_SEPARATOR {
+        "o"
+    } else {
+        separator
+    }
+}
+
+/// Fill of a separator drawn as a rounded rect: `li` is solid, the coordination frame is open
+fn rect_fill(separator: &str) -> &'static str {
+    if separator == "li" {
+        "#fff"
+    } else {
+        "none"
+    }
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum RenderError {
@@ -18,13 +39,20 @@ pub enum RenderError {
 /// Renderer for converting layouts to SVG
 pub struct Renderer {
     pub config: RenderConfig,
+    lexicon: Arc<Lexicon>,
     used_glyphs: HashSet<String>,
 }
 
 impl Renderer {
     pub fn new(config: RenderConfig) -> Self {
+        Self::with_lexicon(config, Arc::new(Lexicon::default()))
+    }
+
+    /// Create a renderer that takes word glyph IDs from the given lexicon
+    pub fn with_lexicon(config: RenderConfig, lexicon: Arc<Lexicon>) -> Self {
         Self {
             config,
+            lexicon,
             used_glyphs: HashSet::new(),
         }
     }
@@ -37,12 +65,36 @@ impl Renderer {
     ) -> Result<Vec<u8>, RenderError> {
         match format {
             OutputFormat::Svg => self.render_svg(layout),
-            OutputFormat::Png => self.render_png(layout),
-            OutputFormat::Html => self.render_html(layout),
+            OutputFormat::Png => {
+                let svg = self.render_svg(layout)?;
+                self.svg_to_png(svg)
+            }
+            OutputFormat::Html => {
+                let svg = self.render_svg(layout)?;
+                self.svg_to_html(svg)
+            }
         }
     }
 
-    /// Render to SVG
+    /// Render a page of compounds, such as a laid out document
+    pub fn render_page(
+        &mut self,
+        page: &Page,
+        format: OutputFormat,
+    ) -> Result<Vec<u8>, RenderError> {
+        let svg = self.render_page_svg(page)?;
+        match format {
+            OutputFormat::Svg => Ok(svg),
+            OutputFormat::Png => self.svg_to_png(svg),
+            OutputFormat::Html => self.svg_to_html(svg),
+        }
+    }
+
+    /// Render to SVG, stacking the compounds from top to bottom
+    ///
+    /// Every compound is stretched to the width of the widest one, unless
+    /// `ignore_height` is set: compounds then keep their own size in glyphs, so the
+    /// height of the drawing is the sum of their heights.
     fn render_svg(&mut self, layout: &Layout) -> Result<Vec<u8>, RenderError> {
         // Clear used glyphs for this render
         self.used_glyphs.clear();
@@ -57,13 +109,57 @@ impl Renderer {
             x_size = x_size.max(option.size.width);
         }
 
+        let ignore_height = self.config.ignore_height;
+        let box_size = |option: &LayoutOption| {
+            if ignore_height {
+                (option.size.width, option.size.height)
+            } else {
+                (x_size, option.size.height * x_size / option.size.width)
+            }
+        };
+
         for option in &layout.compounds {
-            y_size += option.size.height * x_size / option.size.width;
+            y_size += box_size(option).1;
         }
 
-        let box_width = x_size * 100.0;
-        let box_height = y_size * 100.0;
+        self.write_header(&mut svg, x_size * 100.0, y_size * 100.0);
+
+        // Render each compound
+        let mut y_pos = 0.0;
+        for option in &layout.compounds {
+            let (width, height) = box_size(option);
+            let inner_width = width * 100.0;
+            let inner_height = height * 100.0;
+            self.write_compound(&mut svg, option, 0.0, y_pos, inner_width, inner_height)?;
+            y_pos += inner_height;
+        }
+
+        self.write_footer(&mut svg);
+        Ok(svg.into_bytes())
+    }
+
+    /// Render a page to SVG, placing every compound at its position
+    fn render_page_svg(&mut self, page: &Page) -> Result<Vec<u8>, RenderError> {
+        self.used_glyphs.clear();
+
+        let mut svg = String::new();
+        self.write_header(&mut svg, page.size.width * 100.0, page.size.height * 100.0);
+        for placed in &page.compounds {
+            self.write_compound(
+                &mut svg,
+                &placed.option,
+                placed.position.x * 100.0,
+                placed.position.y * 100.0,
+                placed.size.width * 100.0,
+                placed.size.height * 100.0,
+            )?;
+        }
+        self.write_footer(&mut svg);
+        Ok(svg.into_bytes())
+    }
 
+    /// Open the SVG document with room for glyphs sticking out of the box, and add styles
+    fn write_header(&self, svg: &mut String, box_width: f64, box_height: f64) {
         let viewbox_x = -(box_width * self.config.scale_skew - box_width) / 2.0;
         let viewbox_y = -(box_height * self.config.scale - box_height) / 2.0;
         let viewbox_width = box_width * self.config.scale_skew;
@@ -89,29 +185,42 @@ impl Renderer {
                 r#"<filter id="shadow" width="150%" height="150%"><feOffset result="offOut" in="SourceGraphic" dx="0" dy="2"></feOffset><feColorMatrix result="matrixOut" in="offOut" type="matrix" values="0.2 0 0 0 0 0 0.2 0 0 0 0 0 0.2 0 0 0 0 0 1 0"></feColorMatrix><feGaussianBlur result="blurOut" in="matrixOut" stdDeviation="2"></feGaussianBlur><feBlend in="SourceGraphic" in2="blurOut" mode="normal"></feBlend></filter>"#
             ).unwrap();
         }
+    }
 
-        // Render each compound
-        let mut y_pos = 0.0;
-        for option in &layout.compounds {
-            let inner_width = x_size * 100.0;
-            let inner_height = option.size.height * x_size / option.size.width * 100.0;
-
-            writeln!(
-                svg,
-                r#"<svg width="{}" height="{}" viewBox="0 0 100 100" y="{}" preserveAspectRatio="none"{} style="overflow: visible;">"#,
-                inner_width,
-                inner_height,
-                y_pos,
-                if self.config.shadow { r#" filter="url(#shadow)""# } else { "" }
-            ).unwrap();
-
-            self.render_part_option(option, &mut svg, None, None, None)?;
+    /// Render a compound into a box of the document
+    fn write_compound(
+        &mut self,
+        svg: &mut String,
+        option: &LayoutOption,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    ) -> Result<(), RenderError> {
+        // Compounds of a vertical strip all start at the left edge
+        let x = if x == 0.0 {
+            String::new()
+        } else {
+            format!(r#" x="{}""#, x)
+        };
+        writeln!(
+            svg,
+            r#"<svg width="{}" height="{}" viewBox="0 0 100 100"{} y="{}" preserveAspectRatio="none"{} style="overflow: visible;">"#,
+            width,
+            height,
+            x,
+            y,
+            if self.config.shadow { r#" filter="url(#shadow)""# } else { "" }
+        ).unwrap();
 
-            writeln!(svg, r#"</svg>"#).unwrap();
+        self.render_part_option(option, svg, None, None, None)?;
 
-            y_pos += inner_height;
-        }
+        writeln!(svg, r#"</svg>"#).unwrap();
+        Ok(())
+    }
 
+    /// Embed the used glyphs, if exportable, and close the SVG document
+    fn write_footer(&self, svg: &mut String) {
         // If exportable, embed the used glyph symbols
         if self.config.exportable {
             if let Some(registry) = get_glyph_registry() {
@@ -131,8 +240,6 @@ impl Renderer {
         }
 
         writeln!(svg, r#"</svg>"#).unwrap();
-
-        Ok(svg.into_bytes())
     }
 
     /// Render part option recursively
@@ -144,6 +251,21 @@ impl Renderer {
         size_parent: Option<Size>,
         size_parent_normed: Option<Size>,
     ) -> Result<(), RenderError> {
+        // A quotation, and a coordination without a frame, are drawn as plain containers
+        let plain;
+        let option = if option.separator.as_deref() == Some(QUOTATION_SEPARATOR)
+            || (!self.config.coordination_frame
+                && option.separator.as_deref() == Some(COORDINATION_SEPARATOR))
+        {
+            plain = LayoutOption {
+                separator: None,
+                ..option.clone()
+            };
+            &plain
+        } else {
+            option
+        };
+
         let glyph_scale = self.config.scale;
         let separator_scale = self.get_separator_scale(option, glyph_scale);
         let is_nested = position.is_some();
@@ -171,15 +293,15 @@ impl Renderer {
                     center_y - separator_scale[1] * center_y
                 );
 
-                if separator == "li" {
-                    // Special case for li - render as rect
+                if separator == "li" || separator == COORDINATION_SEPARATOR {
+                    // Special case for li and the coordination frame - render as rect
                     let max_size = option.size.width.max(option.size.height);
                     let rx = 15.0 / option.size.width * max_size / separator_scale[0];
                     let ry = 15.0 / option.size.height * max_size / separator_scale[1];
                     writeln!(
                         svg,
-                        "<rect transform=\"{}\" height=\"{}\" width=\"{}\" x=\"{}\" y=\"{}\" rx=\"{}\" ry=\"{}\" fill=\"#fff\"></rect>",
-                        matrix, box_height, box_width, box_x, box_y, rx, ry
+                        "<rect transform=\"{}\" height=\"{}\" width=\"{}\" x=\"{}\" y=\"{}\" rx=\"{}\" ry=\"{}\" fill=\"{}\"></rect>",
+                        matrix, box_height, box_width, box_x, box_y, rx, ry, rect_fill(separator)
                     ).unwrap();
                 } else if separator == "cartouche" {
                     let container_id = GlyphRegistry::container_glyph_id(separator, option.ratio);
@@ -190,7 +312,8 @@ impl Renderer {
                         container_id, matrix, box_height, box_width, box_x, box_y
                     ).unwrap();
                 } else {
-                    let container_id = GlyphRegistry::container_glyph_id(separator, option.ratio);
+                    let container_id =
+                        GlyphRegistry::container_glyph_id(container_glyph(separator), option.ratio);
                     self.used_glyphs.insert(container_id.clone());
                     writeln!(
                         svg,
@@ -225,15 +348,15 @@ impl Renderer {
                     center_y - separator_scale[1] * center_y
                 );
 
-                if separator == "li" {
-                    // Special case for li - render as rect
+                if separator == "li" || separator == COORDINATION_SEPARATOR {
+                    // Special case for li and the coordination frame - render as rect
                     let max_size = size_p.width.max(size_p.height);
                     let rx = 15.0 / size_pn.width * max_size / separator_scale[0];
                     let ry = 15.0 / size_pn.height * max_size / separator_scale[1];
                     writeln!(
                         svg,
-                        "<rect transform=\"{}\" height=\"{}\" width=\"{}\" x=\"{}\" y=\"{}\" rx=\"{}\" ry=\"{}\" fill=\"#fff\"></rect>",
-                        matrix, box_height, box_width, box_x, box_y, rx, ry
+                        "<rect transform=\"{}\" height=\"{}\" width=\"{}\" x=\"{}\" y=\"{}\" rx=\"{}\" ry=\"{}\" fill=\"{}\"></rect>",
+                        matrix, box_height, box_width, box_x, box_y, rx, ry, rect_fill(separator)
                     ).unwrap();
                 } else if separator == "cartouche" {
                     let container_id = GlyphRegistry::container_glyph_id(separator, option.ratio);
@@ -244,7 +367,8 @@ impl Renderer {
                         container_id, matrix, box_height, box_width, box_x, box_y
                     ).unwrap();
                 } else {
-                    let container_id = GlyphRegistry::container_glyph_id(separator, option.ratio);
+                    let container_id =
+                        GlyphRegistry::container_glyph_id(container_glyph(separator), option.ratio);
                     self.used_glyphs.insert(container_id.clone());
                     writeln!(
                         svg,
@@ -287,6 +411,7 @@ impl Renderer {
                     layout_type,
                 } => {
                     let nested_ratio = size.ratio();
+                    #[allow(clippy::clone_on_copy)]
                     let nested_option = LayoutOption {
                         layout_type: layout_type.clone(),
                         separator: separator.clone(),
@@ -363,7 +488,7 @@ impl Renderer {
 
         match &placed.unit {
             LayoutUnit::WordGlyph { token, .. } => {
-                let glyph_id = GlyphRegistry::word_glyph_id(token);
+                let glyph_id = GlyphRegistry::lexicon_glyph_id(&self.lexicon, token);
                 self.used_glyphs.insert(glyph_id.clone());
                 writeln!(
                     svg,
@@ -386,6 +511,22 @@ impl Renderer {
                     "Container units should be handled in render_part_option".to_string(),
                 ));
             }
+            LayoutUnit::Number { units, size } => {
+                // Scale the block's own arrangement into the box of the placed unit
+                let scale_x = placed.size.width / size.width;
+                let scale_y = placed.size.height / size.height;
+                for glyph in units {
+                    let placed_glyph = PlacedUnit {
+                        unit: glyph.unit.clone(),
+                        size: Size::new(glyph.size.width * scale_x, glyph.size.height * scale_y),
+                        position: Position::new(
+                            placed.position.x + glyph.position.x * scale_x,
+                            placed.position.y + glyph.position.y * scale_y,
+                        ),
+                    };
+                    self.render_glyph(&placed_glyph, svg, container_size, glyph_scale)?;
+                }
+            }
             LayoutUnit::Punctuation { tokens, .. } => {
                 // Render punctuation
                 for token in tokens {
@@ -404,6 +545,7 @@ impl Renderer {
     }
 
     /// Get separator scale
+    #[allow(clippy::if_same_then_else)]
     fn get_separator_scale(&self, option: &LayoutOption, base_scale: f64) -> [f64; 4] {
         let mut scale = [
             base_scale * if option.ratio < 0.667 { 1.2 } else { 0.92 },
@@ -413,7 +555,7 @@ impl Renderer {
         ];
 
         if let Some(separator) = &option.separator {
-            match separator.as_str() {
+            match container_glyph(separator) {
                 "li" => {
                     scale[0] = base_scale * if option.ratio < 0.667 { 1.2 } else { 0.88 };
                     scale[1] = base_scale * if option.ratio < 0.667 { 0.9 } else { 0.88 };
@@ -484,6 +626,7 @@ impl Renderer {
     }
 
     /// Get container scale
+    #[allow(clippy::collapsible_match)]
     fn get_container_scale(&self, option: &LayoutOption, base_scale: f64, _is_nested: bool) -> f64 {
         // Use baseScale * 1.1 if there's a separator, otherwise 1.02
         let mut scale = if option.separator.is_some() {
@@ -525,9 +668,8 @@ impl Renderer {
         scale
     }
 
-    /// Render to PNG (using resvg)
-    fn render_png(&mut self, layout: &Layout) -> Result<Vec<u8>, RenderError> {
-        let svg_bytes = self.render_svg(layout)?;
+    /// Convert rendered SVG to PNG (using resvg)
+    fn svg_to_png(&self, svg_bytes: Vec<u8>) -> Result<Vec<u8>, RenderError> {
         let svg_str = String::from_utf8(svg_bytes)
             .map_err(|e| RenderError::Rendering(format!("Invalid SVG: {}", e)))?;
 
@@ -552,9 +694,8 @@ impl Renderer {
             .map_err(|e| RenderError::Rendering(format!("Failed to encode PNG: {}", e)))
     }
 
-    /// Render to HTML with embedded SVG
-    fn render_html(&mut self, layout: &Layout) -> Result<Vec<u8>, RenderError> {
-        let svg_bytes = self.render_svg(layout)?;
+    /// Wrap rendered SVG in an HTML page
+    fn svg_to_html(&self, svg_bytes: Vec<u8>) -> Result<Vec<u8>, RenderError> {
         let svg_str = String::from_utf8(svg_bytes)
             .map_err(|e| RenderError::Rendering(format!("Invalid SVG: {}", e)))?;
 
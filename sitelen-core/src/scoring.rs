@@ -0,0 +1,562 @@
+//! Choosing between the layouts of a compound
+//!
+//! The layout engine returns every arrangement it found for a compound; the pipeline
+//! draws the one with the lowest weighted sum of the criteria in [`CriterionScores`].
+//! The weights live in `RenderConfig::layout_weights`. By default only the distance
+//! from the optimal ratio counts, which draws the layout with the ratio nearest to
+//! `RenderConfig::optimal_ratio`.
+//!
+//! [`AestheticScorer::rank`] lists the best options with the score of every criterion,
+//! to compare layouts or tune the weights.
+//!
+//! Only options whose ratio lies within `RenderConfig::min_ratio` and `max_ratio` are
+//! considered, see [`within_ratio_bounds`].
+//!
+//! For many different renderings of the same text, `Pipeline::sample_layouts` draws
+//! layouts at random, the better scored ones more often, see [`LayoutSampling`].
+
+use crate::layout::IMPERATIVE_SEPARATOR;
+use crate::random::Rng;
+use crate::search::{area_spread, LayoutScorer};
+use crate::types::{LayoutOption, LayoutType, LayoutUnit, PlacedUnit, Position, Size};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::{Entry, HashMap};
+
+/// Weight of every criterion in the score of a layout
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayoutWeights {
+    /// Distance of the ratio from the optimal ratio
+    pub ratio: f64,
+    /// Surface per glyph, which grows as glyphs are drawn smaller
+    pub surface: f64,
+    /// Tiny glyphs drawn next to huge ones
+    pub contrast: f64,
+    /// Containers whose units differ in size
+    pub balance: f64,
+    /// Predicate drawn beside its subject instead of below it
+    pub subject_order: f64,
+}
+
+impl LayoutWeights {
+    /// Weights that also take the look of the glyphs into account
+    pub fn aesthetic() -> Self {
+        Self {
+            ratio: 1.0,
+            surface: 0.25,
+            contrast: 0.5,
+            balance: 0.5,
+            subject_order: 0.5,
+        }
+    }
+}
+
+impl Default for LayoutWeights {
+    fn default() -> Self {
+        Self {
+            ratio: 1.0,
+            surface: 0.0,
+            contrast: 0.0,
+            balance: 0.0,
+            subject_order: 0.0,
+        }
+    }
+}
+
+/// Penalties of a layout for every criterion; lower is better
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct CriterionScores {
+    /// Absolute difference between the ratio and the optimal ratio
+    pub ratio: f64,
+    /// Log of the surface per glyph, at least zero
+    pub surface: f64,
+    /// Largest log ratio of the areas of two touching glyphs
+    pub contrast: f64,
+    /// Mean spread of the log areas of the units of each container
+    pub balance: f64,
+    /// 1 when the first predicate starts beside its subject rather than below it
+    pub subject_order: f64,
+}
+
+impl CriterionScores {
+    /// Weighted sum of the scores; criteria without weight are left out
+    pub fn total(&self, weights: &LayoutWeights) -> f64 {
+        [
+            (weights.ratio, self.ratio),
+            (weights.surface, self.surface),
+            (weights.contrast, self.contrast),
+            (weights.balance, self.balance),
+            (weights.subject_order, self.subject_order),
+        ]
+        .iter()
+        .filter(|(weight, _)| *weight != 0.0)
+        .map(|(weight, score)| weight * score)
+        .sum()
+    }
+}
+
+/// Layout option with its score, as ranked by [`AestheticScorer::rank`]
+#[derive(Debug, Clone)]
+pub struct RankedLayout<'a> {
+    pub option: &'a LayoutOption,
+    /// Weighted sum of the criteria
+    pub score: f64,
+    pub criteria: CriterionScores,
+}
+
+/// Scores complete layouts by a weighted sum of aesthetic criteria
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AestheticScorer {
+    /// Ratio (width / height) the layout should have
+    pub optimal_ratio: f64,
+    pub weights: LayoutWeights,
+}
+
+impl AestheticScorer {
+    pub fn new(optimal_ratio: f64, weights: LayoutWeights) -> Self {
+        Self {
+            optimal_ratio,
+            weights,
+        }
+    }
+
+    /// Score of every criterion for a layout
+    pub fn criteria(&self, option: &LayoutOption) -> CriterionScores {
+        let mut glyphs = Vec::new();
+        collect_glyphs(
+            &option.state.units,
+            Position::new(0.0, 0.0),
+            (1.0, 1.0),
+            &mut glyphs,
+        );
+        let mut spreads = Vec::new();
+        collect_spreads(&option.state.units, &mut spreads);
+
+        CriterionScores {
+            ratio: (option.ratio - self.optimal_ratio).abs(),
+            surface: (option.surface / glyphs.len().max(1) as f64).max(1.0).ln(),
+            contrast: contrast(&glyphs),
+            balance: if spreads.is_empty() {
+                0.0
+            } else {
+                spreads.iter().sum::<f64>() / spreads.len() as f64
+            },
+            subject_order: subject_order(&option.state.units),
+        }
+    }
+
+    /// The `count` best options, best first; equal scores keep the order of `options`
+    pub fn rank<'a>(
+        &self,
+        options: impl IntoIterator<Item = &'a LayoutOption>,
+        count: usize,
+    ) -> Vec<RankedLayout<'a>> {
+        let mut ranked: Vec<RankedLayout> = options
+            .into_iter()
+            .map(|option| {
+                let criteria = self.criteria(option);
+                RankedLayout {
+                    option,
+                    score: criteria.total(&self.weights),
+                    criteria,
+                }
+            })
+            .collect();
+        ranked.sort_by(|a, b| a.score.total_cmp(&b.score));
+        ranked.truncate(count);
+        ranked
+    }
+
+    /// Option with the lowest score, the first of equals
+    pub fn best<'a>(
+        &self,
+        options: impl IntoIterator<Item = &'a LayoutOption>,
+    ) -> Option<&'a LayoutOption> {
+        options
+            .into_iter()
+            .map(|option| (self.score(option), option))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, option)| option)
+    }
+}
+
+impl LayoutScorer for AestheticScorer {
+    fn score(&self, option: &LayoutOption) -> f64 {
+        self.criteria(option).total(&self.weights)
+    }
+}
+
+/// Settings for drawing many different layouts of the same text
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayoutSampling {
+    /// Layouts to draw; fewer are drawn when the text has fewer distinct arrangements
+    pub count: usize,
+    /// The same text and seed draw the same layouts
+    pub seed: u64,
+    /// How much worse scored layouts are drawn: a layout whose score is higher by the
+    /// temperature is drawn e (2.7) times less often; zero draws the best ones in order
+    pub temperature: f64,
+}
+
+impl Default for LayoutSampling {
+    fn default() -> Self {
+        Self {
+            count: 8,
+            seed: 0,
+            temperature: 0.5,
+        }
+    }
+}
+
+/// Options drawn at random without drawing the same arrangement twice
+///
+/// Options are weighted by `exp(-score / temperature)`; of options with the same `key`,
+/// only the best scored one can be drawn.
+pub(crate) fn sample<'a>(
+    scorer: &AestheticScorer,
+    options: impl IntoIterator<Item = &'a LayoutOption>,
+    key: impl Fn(&LayoutOption) -> String,
+    sampling: &LayoutSampling,
+    rng: &mut Rng,
+) -> Vec<&'a LayoutOption> {
+    let mut candidates: Vec<(f64, &LayoutOption)> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for option in options {
+        let score = scorer.score(option);
+        match seen.entry(key(option)) {
+            Entry::Occupied(entry) => {
+                let candidate = &mut candidates[*entry.get()];
+                if score < candidate.0 {
+                    *candidate = (score, option);
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(candidates.len());
+                candidates.push((score, option));
+            }
+        }
+    }
+
+    let best = candidates
+        .iter()
+        .map(|(score, _)| *score)
+        .fold(f64::INFINITY, f64::min);
+    let mut weighted: Vec<(f64, f64, &LayoutOption)> = candidates
+        .into_iter()
+        .map(|(score, option)| {
+            let weight = if sampling.temperature > 0.0 {
+                (-(score - best) / sampling.temperature).exp()
+            } else {
+                0.0
+            };
+            (weight, score, option)
+        })
+        .collect();
+
+    let mut samples = Vec::new();
+    while samples.len() < sampling.count && !weighted.is_empty() {
+        let total: f64 = weighted.iter().map(|(weight, _, _)| weight).sum();
+        let index = if total > 0.0 && total.is_finite() {
+            let mut target = rng.next_f64() * total;
+            weighted
+                .iter()
+                .position(|(weight, _, _)| {
+                    target -= weight;
+                    target < 0.0
+                })
+                .unwrap_or(weighted.len() - 1)
+        } else {
+            // Without temperature, or once only hopeless options are left: the best one
+            weighted
+                .iter()
+                .enumerate()
+                .min_by(|a, b| a.1 .1.total_cmp(&b.1 .1))
+                .map_or(0, |(index, _)| index)
+        };
+        samples.push(weighted.remove(index).2);
+    }
+    samples
+}
+
+/// Options whose ratio lies within `min_ratio..=max_ratio`, in their order
+///
+/// When no option fits, the options nearest the bounds are kept instead, so a compound
+/// is always drawn: the widest when all are too narrow, the tallest when all are too
+/// wide, and all equally near ones when the bounds are empty.
+pub fn within_ratio_bounds(
+    options: &[LayoutOption],
+    min_ratio: f64,
+    max_ratio: f64,
+) -> Vec<&LayoutOption> {
+    let distance = |option: &LayoutOption| {
+        (min_ratio - option.ratio)
+            .max(option.ratio - max_ratio)
+            .max(0.0)
+    };
+    let nearest = options.iter().map(distance).fold(f64::INFINITY, f64::min);
+    options
+        .iter()
+        .filter(|option| distance(option) <= nearest)
+        .collect()
+}
+
+/// Glyph drawn at a place in the compound
+struct GlyphRect {
+    position: Position,
+    size: Size,
+}
+
+impl GlyphRect {
+    /// Whether the glyphs share an edge
+    fn touches(&self, other: &GlyphRect) -> bool {
+        const EPSILON: f64 = 1e-6;
+        let overlap = |start: f64, length: f64, other_start: f64, other_length: f64| {
+            (start + length).min(other_start + other_length) - start.max(other_start) > EPSILON
+        };
+        let meets = |start: f64, length: f64, other_start: f64, other_length: f64| {
+            (start + length - other_start).abs() < EPSILON
+                || (other_start + other_length - start).abs() < EPSILON
+        };
+        let (a, b) = (self, other);
+        (meets(a.position.x, a.size.width, b.position.x, b.size.width)
+            && overlap(a.position.y, a.size.height, b.position.y, b.size.height))
+            || (meets(a.position.y, a.size.height, b.position.y, b.size.height)
+                && overlap(a.position.x, a.size.width, b.position.x, b.size.width))
+    }
+}
+
+/// Glyphs of the units in compound coordinates, leaving out punctuation
+///
+/// Units of a container are placed within its `size` and drawn scaled to its placed size.
+fn collect_glyphs(
+    units: &[PlacedUnit],
+    origin: Position,
+    scale: (f64, f64),
+    glyphs: &mut Vec<GlyphRect>,
+) {
+    for placed in units {
+        let position = Position::new(
+            origin.x + placed.position.x * scale.0,
+            origin.y + placed.position.y * scale.1,
+        );
+        let size = Size::new(placed.size.width * scale.0, placed.size.height * scale.1);
+        match &placed.unit {
+            LayoutUnit::Punctuation { .. }
+            | LayoutUnit::Container {
+                layout_type: LayoutType::Punctuation,
+                ..
+            } => {}
+            LayoutUnit::Container {
+                units, size: inner, ..
+            } if inner.width > 0.0 && inner.height > 0.0 => {
+                let scale = (size.width / inner.width, size.height / inner.height);
+                collect_glyphs(units, position, scale, glyphs);
+            }
+            _ => glyphs.push(GlyphRect { position, size }),
+        }
+    }
+}
+
+/// Spread of unit areas of every container holding more than one unit
+fn collect_spreads(units: &[PlacedUnit], spreads: &mut Vec<f64>) {
+    if units.len() > 1 {
+        spreads.push(area_spread(units));
+    }
+    for placed in units {
+        if let LayoutUnit::Container { units, .. } = &placed.unit {
+            collect_spreads(units, spreads);
+        }
+    }
+}
+
+/// Largest log ratio of the areas of two touching glyphs
+fn contrast(glyphs: &[GlyphRect]) -> f64 {
+    let mut contrast: f64 = 0.0;
+    for (i, a) in glyphs.iter().enumerate() {
+        for b in &glyphs[i + 1..] {
+            if a.touches(b) {
+                let areas = a.size.surface() / b.size.surface();
+                if areas.is_finite() && areas > 0.0 {
+                    contrast = contrast.max(areas.ln().abs());
+                }
+            }
+        }
+    }
+    contrast
+}
+
+/// 1 when the first predicate of the compound starts above the bottom of the unit before it
+fn subject_order(units: &[PlacedUnit]) -> f64 {
+    let predicate = units.iter().position(|placed| {
+        matches!(
+            &placed.unit,
+            LayoutUnit::Container { separator: Some(separator), .. }
+                if separator == "li" || separator == IMPERATIVE_SEPARATOR
+        )
+    });
+    match predicate {
+        Some(index) if index > 0 => {
+            let subject = &units[index - 1];
+            let subject_bottom = subject.position.y + subject.size.height;
+            if units[index].position.y + 1e-6 < subject_bottom {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::LayoutEngine;
+    use crate::parser::Parser;
+    use std::collections::HashSet;
+
+    fn options(text: &str) -> Vec<LayoutOption> {
+        let sentences = Parser::new().parse(text).unwrap();
+        LayoutEngine::new().layout_compound(&sentences[0])
+    }
+
+    #[test]
+    fn test_default_weights_pick_the_nearest_ratio() {
+        let options = options("jan pona mute li moku e kili suli.");
+        let scorer = AestheticScorer::new(0.75, LayoutWeights::default());
+        let nearest = options
+            .iter()
+            .min_by(|a, b| (a.ratio - 0.75).abs().total_cmp(&(b.ratio - 0.75).abs()))
+            .unwrap();
+        let best = scorer.best(&options).unwrap();
+        assert!(std::ptr::eq(best, nearest));
+
+        let ranked = scorer.rank(&options, 3);
+        assert_eq!(ranked.len(), 3.min(options.len()));
+        assert!(std::ptr::eq(ranked[0].option, best));
+        assert!(ranked.windows(2).all(|pair| pair[0].score <= pair[1].score));
+        assert_eq!(ranked[0].score, ranked[0].criteria.ratio);
+    }
+
+    #[test]
+    fn test_criteria() {
+        let options = options("jan pona li moku.");
+        let scorer = AestheticScorer::new(0.75, LayoutWeights::aesthetic());
+        for option in &options {
+            let criteria = scorer.criteria(option);
+            assert!(criteria.surface >= 0.0);
+            assert!(criteria.contrast >= 0.0);
+            assert!(criteria.balance >= 0.0);
+            assert!(criteria.subject_order == 0.0 || criteria.subject_order == 1.0);
+        }
+
+        // Some layouts draw the predicate below the subject and some beside it
+        let orders: Vec<f64> = options
+            .iter()
+            .map(|option| scorer.criteria(option).subject_order)
+            .collect();
+        assert!(orders.contains(&0.0) && orders.contains(&1.0));
+
+        let subject_first = LayoutWeights {
+            subject_order: 100.0,
+            ..LayoutWeights::default()
+        };
+        let best = AestheticScorer::new(0.75, subject_first)
+            .best(&options)
+            .unwrap();
+        assert_eq!(scorer.criteria(best).subject_order, 0.0);
+    }
+
+    #[test]
+    fn test_within_ratio_bounds() {
+        let options = options("jan pona li moku e kili.");
+        let ratios = |kept: Vec<&LayoutOption>| -> Vec<f64> {
+            kept.iter().map(|option| option.ratio).collect()
+        };
+
+        assert_eq!(
+            within_ratio_bounds(&options, 0.0, 100.0).len(),
+            options.len()
+        );
+        let kept = ratios(within_ratio_bounds(&options, 0.5, 1.0));
+        assert!(!kept.is_empty() && kept.len() < options.len());
+        assert!(kept.iter().all(|ratio| (0.5..=1.0).contains(ratio)));
+
+        // Nothing fits: the widest options are kept
+        let widest = options
+            .iter()
+            .map(|option| option.ratio)
+            .fold(0.0, f64::max);
+        let kept = ratios(within_ratio_bounds(&options, 50.0, 100.0));
+        assert!(!kept.is_empty() && kept.iter().all(|ratio| *ratio == widest));
+    }
+
+    #[test]
+    fn test_sample() {
+        let options = options("jan pona mute li moku e kili suli.");
+        let engine = LayoutEngine::new();
+        let scorer = AestheticScorer::new(0.75, LayoutWeights::default());
+        let draw = |sampling: &LayoutSampling| {
+            let mut rng = Rng::new(sampling.seed);
+            sample(
+                &scorer,
+                &options,
+                |o| engine.option_key(o),
+                sampling,
+                &mut rng,
+            )
+        };
+
+        let sampling = LayoutSampling::default();
+        let samples = draw(&sampling);
+        assert_eq!(samples.len(), sampling.count);
+        let keys: HashSet<String> = samples.iter().map(|o| engine.option_key(o)).collect();
+        assert_eq!(keys.len(), samples.len());
+
+        let again = draw(&sampling);
+        assert!(samples
+            .iter()
+            .zip(&again)
+            .all(|(a, b)| std::ptr::eq(*a, *b)));
+        let other = draw(&LayoutSampling {
+            seed: 1,
+            ..sampling
+        });
+        assert!(samples
+            .iter()
+            .zip(&other)
+            .any(|(a, b)| !std::ptr::eq(*a, *b)));
+
+        // Without temperature the best layouts come first, as ranked
+        let greedy = draw(&LayoutSampling {
+            temperature: 0.0,
+            ..sampling
+        });
+        assert!(std::ptr::eq(greedy[0], scorer.best(&options).unwrap()));
+        let scores: Vec<f64> = greedy.iter().map(|o| scorer.score(o)).collect();
+        assert!(scores.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        // No more samples than distinct arrangements
+        let all = draw(&LayoutSampling {
+            count: usize::MAX,
+            ..sampling
+        });
+        let distinct: HashSet<String> = options.iter().map(|o| engine.option_key(o)).collect();
+        assert_eq!(all.len(), distinct.len());
+    }
+
+    #[test]
+    fn test_contrast() {
+        let glyph = |x: f64, y: f64, side: f64| GlyphRect {
+            position: Position::new(x, y),
+            size: Size::new(side, side),
+        };
+        assert_eq!(contrast(&[glyph(0.0, 0.0, 1.0), glyph(1.0, 0.0, 1.0)]), 0.0);
+        let uneven = contrast(&[glyph(0.0, 0.0, 2.0), glyph(2.0, 0.0, 0.5)]);
+        assert!((uneven - 16f64.ln()).abs() < 1e-9);
+        // Apart, or meeting only at a corner
+        assert_eq!(contrast(&[glyph(0.0, 0.0, 2.0), glyph(3.0, 0.0, 0.5)]), 0.0);
+        assert_eq!(contrast(&[glyph(0.0, 0.0, 2.0), glyph(2.0, 2.0, 0.5)]), 0.0);
+    }
+}
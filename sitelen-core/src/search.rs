@@ -0,0 +1,254 @@
+//! Limits and scoring for the layout search
+//!
+//! `LayoutEngine::layout_compound` tries every arrangement of the glyphs in a container
+//! and every combination of the options of a sentence's parts, which grows exponentially
+//! with the length of the sentence. [`SearchLimits`] bounds that work:
+//!
+//! - `max_nodes` is a hard budget of placements for one compound. When it runs out, the
+//!   rest of the search follows only the best scored placement at every step, so the
+//!   search still finishes with complete layouts. Counting placements rather than time
+//!   makes the result the same on every run and every machine.
+//! - `beam_width` switches from exploring every arrangement to a beam search that keeps
+//!   the best scored partial layouts of each length, and the best combinations of part
+//!   options.
+//!
+//! Partial and complete layouts are ranked by a [`LayoutScorer`]; lower scores are better.
+
+use crate::types::{LayoutOption, PlacedUnit};
+use serde::{Deserialize, Serialize};
+
+/// Placements tried for one compound when no limit is set
+///
+/// The sentences of the example corpora are laid out exactly as by the full search;
+/// compounds of a dozen words or more spend it within a few hundred milliseconds.
+pub const DEFAULT_MAX_NODES: usize = 50_000;
+
+/// Bounds on the work done to lay out one compound
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchLimits {
+    /// Partial layouts kept at every step, at least one; `None` explores every arrangement
+    pub beam_width: Option<usize>,
+    /// Placements tried before the rest of the search is finished greedily
+    pub max_nodes: usize,
+}
+
+impl SearchLimits {
+    /// Beam search keeping `beam_width` partial layouts, with the default budget
+    pub fn beam(beam_width: usize) -> Self {
+        Self {
+            beam_width: Some(beam_width),
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        Self {
+            beam_width: None,
+            max_nodes: DEFAULT_MAX_NODES,
+        }
+    }
+}
+
+/// Ranks layouts during the search; lower scores are better
+///
+/// The search scores partial layouts as well as complete ones, so a score should not
+/// depend on how many units are placed.
+pub trait LayoutScorer: Send + Sync {
+    fn score(&self, option: &LayoutOption) -> f64;
+}
+
+/// Weighted sum of penalties for the shape of a layout and the glyphs in it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WeightedScorer {
+    /// Aspect ratio (width / height) the layout should have
+    pub optimal_ratio: f64,
+    /// Weight of the distance from the optimal ratio, on a log scale
+    pub ratio: f64,
+    /// Weight of the surface per glyph, which grows as glyphs are drawn smaller
+    pub surface: f64,
+    /// Weight of the spread of glyph areas, so glyphs of a layout keep similar sizes
+    pub uniformity: f64,
+    /// Weight of each glyph that starts above the one read before it
+    pub reading_order: f64,
+}
+
+impl WeightedScorer {
+    /// Scorer aiming at the given aspect ratio with the default weights
+    pub fn new(optimal_ratio: f64) -> Self {
+        Self {
+            optimal_ratio,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for WeightedScorer {
+    fn default() -> Self {
+        Self {
+            optimal_ratio: 0.75,
+            ratio: 1.0,
+            surface: 0.5,
+            uniformity: 0.5,
+            reading_order: 0.25,
+        }
+    }
+}
+
+impl LayoutScorer for WeightedScorer {
+    fn score(&self, option: &LayoutOption) -> f64 {
+        let units = &option.state.units;
+        if units.is_empty() || option.ratio <= 0.0 {
+            return f64::INFINITY;
+        }
+        let ratio = (option.ratio / self.optimal_ratio).ln().abs();
+        let surface = (option.surface / units.len() as f64).max(1.0).ln();
+        self.ratio * ratio
+            + self.surface * surface
+            + self.uniformity * area_spread(units)
+            + self.reading_order * reading_order_breaks(units) as f64
+    }
+}
+
+/// Standard deviation of the log areas of placed units
+pub(crate) fn area_spread(units: &[PlacedUnit]) -> f64 {
+    let logs: Vec<f64> = units
+        .iter()
+        .map(|placed| placed.size.surface().max(f64::MIN_POSITIVE).ln())
+        .collect();
+    let mean = logs.iter().sum::<f64>() / logs.len() as f64;
+    let variance = logs.iter().map(|log| (log - mean).powi(2)).sum::<f64>() / logs.len() as f64;
+    variance.sqrt()
+}
+
+/// Units that start above the top of the unit before them, breaking the reading order
+pub(crate) fn reading_order_breaks(units: &[PlacedUnit]) -> usize {
+    units
+        .windows(2)
+        .filter(|pair| pair[1].position.y + 1e-6 < pair[0].position.y)
+        .count()
+}
+
+/// Placements a search may still try
+///
+/// The budget of a compound is shared out between its parts and the combination of
+/// their options, so that a long part cannot leave the rest only a greedy search.
+#[derive(Debug)]
+pub(crate) struct Budget {
+    remaining: usize,
+}
+
+impl Budget {
+    pub(crate) fn new(max_nodes: usize) -> Self {
+        Self {
+            remaining: max_nodes,
+        }
+    }
+
+    pub(crate) fn spend(&mut self) {
+        self.remaining = self.remaining.saturating_sub(1);
+    }
+
+    pub(crate) fn exhausted(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Equal share of the remaining placements for one of `searches` searches left to run
+    pub(crate) fn share(&mut self, searches: usize) -> Budget {
+        let share = self.remaining / searches.max(1);
+        self.remaining -= share;
+        Budget::new(share)
+    }
+
+    /// Take back the placements a share did not use
+    pub(crate) fn refund(&mut self, share: Budget) {
+        self.remaining += share.remaining;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{LayoutState, LayoutUnit, Position, Size};
+
+    /// Option of square word glyphs with the given positions and sizes
+    fn option(glyphs: &[(f64, f64, f64)]) -> LayoutOption {
+        let units: Vec<PlacedUnit> = glyphs
+            .iter()
+            .map(|&(x, y, side)| PlacedUnit {
+                unit: LayoutUnit::WordGlyph {
+                    token: "pona".to_string(),
+                    size: Size::new(1.0, 1.0),
+                },
+                size: Size::new(side, side),
+                position: Position::new(x, y),
+            })
+            .collect();
+        let width = glyphs.iter().map(|g| g.0 + g.2).fold(0.0, f64::max);
+        let height = glyphs.iter().map(|g| g.1 + g.2).fold(0.0, f64::max);
+        let size = Size::new(width, height);
+        LayoutOption {
+            layout_type: crate::types::LayoutType::Container,
+            separator: None,
+            state: LayoutState {
+                units,
+                size,
+                forbidden: Vec::new(),
+            },
+            size,
+            ratio: size.ratio(),
+            normed_ratio: size.ratio().min(1.0 / size.ratio()),
+            surface: size.surface(),
+        }
+    }
+
+    #[test]
+    fn test_weighted_scorer() {
+        let scorer = WeightedScorer::new(1.0);
+        let square = option(&[
+            (0.0, 0.0, 1.0),
+            (1.0, 0.0, 1.0),
+            (0.0, 1.0, 1.0),
+            (1.0, 1.0, 1.0),
+        ]);
+        let row = option(&[
+            (0.0, 0.0, 1.0),
+            (1.0, 0.0, 1.0),
+            (2.0, 0.0, 1.0),
+            (3.0, 0.0, 1.0),
+        ]);
+        assert!(scorer.score(&square) < scorer.score(&row));
+
+        // A glyph squeezed next to a large one
+        let uneven = option(&[(0.0, 0.0, 2.0), (2.0, 0.0, 0.5)]);
+        assert!(area_spread(&uneven.state.units) > 0.0);
+        assert_eq!(area_spread(&square.state.units), 0.0);
+
+        // Reading back up to the top of the next column
+        let columns = option(&[(0.0, 0.0, 1.0), (0.0, 1.0, 1.0), (1.0, 0.0, 1.0)]);
+        assert_eq!(reading_order_breaks(&columns.state.units), 1);
+        assert_eq!(reading_order_breaks(&square.state.units), 0);
+        assert_eq!(reading_order_breaks(&row.state.units), 0);
+    }
+
+    #[test]
+    fn test_budget() {
+        let mut budget = Budget::new(2);
+        budget.spend();
+        assert!(!budget.exhausted());
+        budget.spend();
+        budget.spend();
+        assert!(budget.exhausted());
+
+        // Shares split what is left, and unused placements go back to the next share
+        let mut budget = Budget::new(90);
+        let mut first = budget.share(3);
+        assert_eq!((first.remaining, budget.remaining), (30, 60));
+        first.spend();
+        budget.refund(first);
+        let second = budget.share(2);
+        assert_eq!((second.remaining, budget.remaining), (44, 45));
+        assert!(Budget::new(1).share(2).exhausted());
+    }
+}
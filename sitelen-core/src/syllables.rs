@@ -0,0 +1,214 @@
+//! Splitting proper names into the syllables drawn inside a cartouche
+//!
+//! The parser hands every proper name to a [`Syllabifier`] and checks the
+//! returned syllables against the Toki Pona syllable inventory. The built-in
+//! [`SyllableMode`]s either split names as written (strict) or adapt foreign
+//! names to Toki Pona phonology first (lenient). Authors can bypass both by
+//! spelling a cartouche explicitly as `[o-la-pu]`.
+
+/// Splits a proper name into cartouche syllables
+pub trait Syllabifier: Send + Sync {
+    /// Split a name (any case) into lower case syllables
+    ///
+    /// Syllables that are not valid Toki Pona are reported by the parser, so an
+    /// implementation should return leftovers rather than silently dropping them.
+    fn syllabify(&self, name: &str) -> Vec<String>;
+}
+
+/// Built-in syllabification modes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyllableMode {
+    /// Split the name as written; letters that do not form Toki Pona syllables are reported
+    #[default]
+    Strict,
+    /// Map foreign letters and clusters to the closest Toki Pona syllables
+    Lenient,
+}
+
+impl Syllabifier for SyllableMode {
+    fn syllabify(&self, name: &str) -> Vec<String> {
+        match self {
+            SyllableMode::Strict => split_strict(&name.to_lowercase()),
+            SyllableMode::Lenient => split_lenient(&name.to_lowercase()),
+        }
+    }
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+/// Split a name into (C)V(n) syllables, keeping unsplittable leftovers as a final syllable
+fn split_strict(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut syllables = Vec::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        let first = chars[i];
+        let second = chars.get(i + 1).copied().unwrap_or(' ');
+        let third = chars.get(i + 2).copied().unwrap_or(' ');
+        let fourth = chars.get(i + 3).copied().unwrap_or(' ');
+
+        let len = if !is_vowel(first) {
+            // Consonant start: MONsi or POki
+            if third == 'n' && !is_vowel(fourth) {
+                3
+            } else {
+                2
+            }
+        } else if chars.len() - i == 2 || (second == 'n' && !is_vowel(third)) {
+            // Vowel start: UNpa, or a two letter ending
+            2
+        } else {
+            1
+        };
+
+        let end = (i + len).min(chars.len());
+        syllables.push(chars[i..end].iter().collect());
+        i = end;
+    }
+
+    syllables
+}
+
+/// Toki Pona spelling of a foreign letter, or `None` when the letter is dropped
+fn map_letter(c: char) -> Option<&'static str> {
+    Some(match c {
+        'a' | 'á' | 'à' | 'â' | 'ä' | 'å' => "a",
+        'e' | 'é' | 'è' | 'ê' | 'ë' => "e",
+        'i' | 'í' | 'ì' | 'î' | 'ï' | 'y' => "i",
+        'o' | 'ó' | 'ò' | 'ô' | 'ö' | 'ø' => "o",
+        'u' | 'ú' | 'ù' | 'û' | 'ü' => "u",
+        'b' | 'f' | 'p' => "p",
+        'v' | 'w' => "w",
+        'd' | 't' => "t",
+        'c' | 'g' | 'k' | 'q' => "k",
+        'x' => "ks",
+        'j' => "j",
+        'l' | 'r' => "l",
+        'm' => "m",
+        'n' | 'ñ' => "n",
+        's' | 'z' | 'ß' => "s",
+        _ => return None,
+    })
+}
+
+/// Adapt a foreign name to Toki Pona phonology before splitting it
+///
+/// Letters are mapped to the closest Toki Pona consonant or vowel, doubled
+/// consonants are merged, consonant clusters and final consonants get an extra
+/// vowel (`n` and `m` before a consonant become a syllable-final `n`) and the forbidden
+/// syllables ji, ti, wo and wu are replaced by their closest legal neighbours.
+fn split_lenient(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut letters: Vec<char> = Vec::new();
+    for (i, &c) in chars.iter().enumerate() {
+        // A y before a vowel is a consonant
+        let before_vowel = chars
+            .get(i + 1)
+            .and_then(|next| map_letter(*next))
+            .is_some_and(|next| next.starts_with(is_vowel));
+        let mapped = if c == 'y' && before_vowel {
+            Some("j")
+        } else {
+            map_letter(c)
+        };
+        for letter in mapped.into_iter().flat_map(str::chars) {
+            if letters.last() == Some(&letter) && !is_vowel(letter) {
+                continue;
+            }
+            letters.push(letter);
+        }
+    }
+
+    let mut syllables: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < letters.len() {
+        let c = letters[i];
+        let next = letters.get(i + 1).copied();
+
+        if is_vowel(c) {
+            syllables.push(c.to_string());
+            i += 1;
+        } else if matches!(c, 'n' | 'm')
+            && !next.is_some_and(|next| is_vowel(next) || matches!(next, 'n' | 'm'))
+            && can_close(&syllables)
+        {
+            // Nasal without a vowel closes the previous syllable: Ben → pen
+            syllables.last_mut().unwrap().push('n');
+            i += 1;
+        } else if let Some(vowel) = next.filter(|v| is_vowel(*v)) {
+            syllables.push(legal_syllable(c, vowel));
+            i += 2;
+        } else {
+            // Consonant in a cluster or at the end: add a vowel
+            let vowel = if c == 'w' { 'e' } else { 'u' };
+            syllables.push(legal_syllable(c, vowel));
+            i += 1;
+        }
+    }
+
+    syllables
+}
+
+/// Whether the last syllable can take a final n
+fn can_close(syllables: &[String]) -> bool {
+    syllables
+        .last()
+        .is_some_and(|last| last.ends_with(is_vowel))
+}
+
+/// Consonant-vowel pair, replacing the syllables Toki Pona does not allow
+fn legal_syllable(consonant: char, vowel: char) -> String {
+    match (consonant, vowel) {
+        ('t', 'i') => "si".to_string(),
+        ('j', 'i') => "i".to_string(),
+        ('w', 'o') => "o".to_string(),
+        ('w', 'u') => "u".to_string(),
+        _ => format!("{consonant}{vowel}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn syllables(mode: SyllableMode, name: &str) -> Vec<String> {
+        mode.syllabify(name)
+    }
+
+    #[test]
+    fn test_strict_splits_as_written() {
+        assert_eq!(
+            syllables(SyllableMode::Strict, "Kanata"),
+            ["ka", "na", "ta"]
+        );
+        assert_eq!(syllables(SyllableMode::Strict, "Monsi"), ["mon", "si"]);
+        assert_eq!(syllables(SyllableMode::Strict, "Unpa"), ["un", "pa"]);
+        assert_eq!(syllables(SyllableMode::Strict, "Ale"), ["a", "le"]);
+    }
+
+    #[test]
+    fn test_strict_keeps_leftovers() {
+        assert_eq!(syllables(SyllableMode::Strict, "Olaf"), ["o", "la", "f"]);
+    }
+
+    #[test]
+    fn test_lenient_adapts_foreign_names() {
+        assert_eq!(syllables(SyllableMode::Lenient, "Olaf"), ["o", "la", "pu"]);
+        assert_eq!(syllables(SyllableMode::Lenient, "Ben"), ["pen"]);
+        assert_eq!(
+            syllables(SyllableMode::Lenient, "Robert"),
+            ["lo", "pe", "lu", "tu"]
+        );
+        assert_eq!(syllables(SyllableMode::Lenient, "Tim"), ["sin"]);
+        assert_eq!(syllables(SyllableMode::Lenient, "Anna"), ["a", "na"]);
+        assert_eq!(
+            syllables(SyllableMode::Lenient, "Kanata"),
+            ["ka", "na", "ta"]
+        );
+        assert_eq!(syllables(SyllableMode::Lenient, "Yoshi"), ["jo", "si"]);
+        assert_eq!(syllables(SyllableMode::Lenient, "Amna"), ["a", "mu", "na"]);
+    }
+}
@@ -1,39 +1,254 @@
 /// Core data structures for the Sitelen Sitelen renderer
 
+#[allow(clippy::useless_attribute, clippy::empty_line_after_doc_comments)]
+use serde::{Deserialize, Serialize};
+
+/// Byte range into the original input text
+///
+/// Spans always refer to the text passed to `Parser::parse`, so they survive
+/// the comma/`la` rewriting and proper name handling done while parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    /// Smallest span covering both spans (empty spans are ignored)
+    pub fn join(&self, other: Span) -> Span {
+        if self.is_empty() {
+            other
+        } else if other.is_empty() {
+            *self
+        } else {
+            Span::new(self.start.min(other.start), self.end.max(other.end))
+        }
+    }
+
+    /// Whether the given byte offset falls inside this span
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+}
+
+/// What a token stands for, decided once when the source text is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    #[default]
+    Word,
+    /// Proper name, split into syllables and drawn in a cartouche
+    Name,
+    /// Syllable of a proper name
+    Syllable,
+    /// Quotation while the sentence around it is structured
+    Quote,
+    Punctuation,
+}
+
+/// A word, syllable or punctuation mark together with its source location
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Token {
+    pub text: String,
+    #[serde(default)]
+    pub span: Span,
+    #[serde(default)]
+    pub kind: TokenKind,
+}
+
+impl Token {
+    /// Create a word token
+    pub fn new(text: impl Into<String>, span: Span) -> Self {
+        Self::with_kind(text, span, TokenKind::Word)
+    }
+
+    pub fn with_kind(text: impl Into<String>, span: Span, kind: TokenKind) -> Self {
+        Self {
+            text: text.into(),
+            span,
+            kind,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether this token is the given word (names that happen to be spelled alike are not)
+    pub fn is_word(&self, word: &str) -> bool {
+        self.kind == TokenKind::Word && self.text == word
+    }
+}
+
 /// Part of a sentence (subject, object, prepositional phrase, etc.)
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Serialised with a `type` field holding the snake case variant name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum SentencePart {
     Subject {
-        tokens: Vec<String>,
+        #[serde(default)]
+        tokens: Vec<Token>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         separator: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         parts: Option<Vec<SentencePart>>,
+        #[serde(default)]
+        span: Span,
     },
     ObjectMarker {
         separator: String,
-        tokens: Vec<String>,
+        #[serde(default)]
+        tokens: Vec<Token>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         parts: Option<Vec<SentencePart>>,
+        #[serde(default)]
+        span: Span,
     },
     PrepPhrase {
         separator: String,
-        tokens: Vec<String>,
+        #[serde(default)]
+        tokens: Vec<Token>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         parts: Option<Vec<SentencePart>>,
+        #[serde(default)]
+        span: Span,
     },
+    /// Words addressed by a following `o`: `jan Ali o`
+    ///
+    /// `parts` holds the words split around the cartouches of proper names.
     Address {
         separator: String,
-        tokens: Vec<String>,
+        #[serde(default)]
+        tokens: Vec<Token>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        parts: Option<Vec<SentencePart>>,
+        #[serde(default)]
+        span: Span,
+    },
+    /// Preverb (with optional `ala`) and the verb phrase it modifies: `wile ala moku`
+    Preverb {
+        tokens: Vec<Token>,
+        parts: Vec<SentencePart>,
+        #[serde(default)]
+        span: Span,
     },
+    /// Conjuncts joined by `en` or `anu`: `mi en sina`, `kili anu telo`
+    ///
+    /// `tokens` holds the conjunctions, one between every two of the `parts`.
+    Coordination {
+        tokens: Vec<Token>,
+        parts: Vec<SentencePart>,
+        #[serde(default)]
+        span: Span,
+    },
+    /// Verb negated by a following `ala`: `moku ala`
+    Negation {
+        tokens: Vec<Token>,
+        #[serde(default)]
+        span: Span,
+    },
+    /// Yes/no question of the form `X ala X`: `moku ala moku`
+    AlaQuestion {
+        tokens: Vec<Token>,
+        #[serde(default)]
+        span: Span,
+    },
+    /// `a`, a run of it, a lone `o`, or an exclamation opening a sentence: `toki a!`
     Interjection {
-        tokens: Vec<String>,
+        tokens: Vec<Token>,
+        #[serde(default)]
+        span: Span,
+    },
+    /// Run of number words forming a number in the parser's counting system: `luka tu`
+    Number {
+        tokens: Vec<Token>,
+        value: u64,
+        #[serde(default)]
+        span: Span,
+    },
+    /// Quoted speech, parsed into its own sentences; the span includes the quote marks
+    Quotation {
+        sentences: Vec<Sentence>,
+        #[serde(default)]
+        span: Span,
     },
     Punctuation {
-        tokens: Vec<String>,
+        tokens: Vec<Token>,
+        #[serde(default)]
+        span: Span,
     },
 }
 
+impl SentencePart {
+    /// Source range covered by this part, including its separator word
+    pub fn span(&self) -> Span {
+        match self {
+            SentencePart::Subject { span, .. }
+            | SentencePart::ObjectMarker { span, .. }
+            | SentencePart::PrepPhrase { span, .. }
+            | SentencePart::Address { span, .. }
+            | SentencePart::Preverb { span, .. }
+            | SentencePart::Coordination { span, .. }
+            | SentencePart::Negation { span, .. }
+            | SentencePart::AlaQuestion { span, .. }
+            | SentencePart::Interjection { span, .. }
+            | SentencePart::Number { span, .. }
+            | SentencePart::Quotation { span, .. }
+            | SentencePart::Punctuation { span, .. } => *span,
+        }
+    }
+
+    /// Tokens held directly by this part (nested parts are not included)
+    pub fn tokens(&self) -> &[Token] {
+        match self {
+            SentencePart::Subject { tokens, .. }
+            | SentencePart::ObjectMarker { tokens, .. }
+            | SentencePart::PrepPhrase { tokens, .. }
+            | SentencePart::Address { tokens, .. }
+            | SentencePart::Preverb { tokens, .. }
+            | SentencePart::Coordination { tokens, .. }
+            | SentencePart::Negation { tokens, .. }
+            | SentencePart::AlaQuestion { tokens, .. }
+            | SentencePart::Interjection { tokens, .. }
+            | SentencePart::Number { tokens, .. }
+            | SentencePart::Punctuation { tokens, .. } => tokens,
+            SentencePart::Quotation { .. } => &[],
+        }
+    }
+}
+
 /// A structured sentence ready for layout
-#[derive(Debug, Clone)]
+///
+/// Sentences, their parts and tokens serialise to the JSON AST described in
+/// [`crate::ast`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Sentence {
     pub parts: Vec<SentencePart>,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl Sentence {
+    /// Create a sentence spanning all of its parts
+    pub fn new(parts: Vec<SentencePart>) -> Self {
+        let span = parts
+            .iter()
+            .fold(Span::default(), |span, part| span.join(part.span()));
+        Self { parts, span }
+    }
 }
 
 /// Size in 2D space
@@ -74,7 +289,7 @@ impl Position {
     }
 }
 
-/// A unit that can be laid out (glyph, syllable, or container)
+/// A unit that can be laid out (glyph, syllable, number block or container)
 #[derive(Debug, Clone)]
 pub enum LayoutUnit {
     WordGlyph {
@@ -95,6 +310,11 @@ pub enum LayoutUnit {
         tokens: Vec<String>,
         size: Size,
     },
+    /// Number glyphs in a fixed compact arrangement, positioned within `size`
+    Number {
+        units: Vec<PlacedUnit>,
+        size: Size,
+    },
 }
 
 /// A unit that has been placed in a layout
@@ -131,8 +351,43 @@ pub enum LayoutType {
     Punctuation,
 }
 
+impl LayoutOption {
+    /// Toki Pona text drawn by this option, in reading order
+    ///
+    /// See [`Layout::to_text`].
+    pub fn to_text(&self) -> String {
+        crate::linearize::to_text(std::slice::from_ref(self))
+    }
+}
+
 /// A complete layout for rendering
 #[derive(Debug, Clone)]
 pub struct Layout {
     pub compounds: Vec<LayoutOption>,
 }
+
+impl Layout {
+    /// Toki Pona text drawn by the layout, for alt text and copying
+    ///
+    /// Containers are read in order with their separators, names and punctuation put
+    /// back. The text parses to the same structure as the source, but is not a copy
+    /// of it: UCSUR input comes back in Latin letters and dropped commas stay dropped.
+    pub fn to_text(&self) -> String {
+        crate::linearize::to_text(&self.compounds)
+    }
+}
+
+/// A compound placed on a page, in glyph units
+#[derive(Debug, Clone)]
+pub struct PlacedCompound {
+    pub option: LayoutOption,
+    pub position: Position,
+    pub size: Size,
+}
+
+/// Compounds arranged in rows on a page, in glyph units
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub size: Size,
+    pub compounds: Vec<PlacedCompound>,
+}
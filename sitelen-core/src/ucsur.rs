@@ -0,0 +1,208 @@
+//! sitelen pona codepoints from the UCSUR private use range
+//!
+//! Text copied from sitelen pona fonts uses the Under-ConScript Unicode Registry
+//! block U+F1900–U+F19FF: one codepoint per word, cartouche start/end markers and
+//! a handful of combining and joining controls. This module maps those codepoints
+//! back to Toki Pona words so the parser can treat them like Latin input.
+
+/// Words at U+F1900 onwards, in codepoint order
+const WORDS: &[&str] = &[
+    "a",
+    "akesi",
+    "ala",
+    "alasa",
+    "ale",
+    "anpa",
+    "ante",
+    "anu",
+    "awen",
+    "e",
+    "en",
+    "esun",
+    "ijo",
+    "ike",
+    "ilo",
+    "insa",
+    "jaki",
+    "jan",
+    "jelo",
+    "jo",
+    "kala",
+    "kalama",
+    "kama",
+    "kasi",
+    "ken",
+    "kepeken",
+    "kili",
+    "kiwen",
+    "ko",
+    "kon",
+    "kule",
+    "kulupu",
+    "kute",
+    "la",
+    "lape",
+    "laso",
+    "lawa",
+    "len",
+    "lete",
+    "li",
+    "lili",
+    "linja",
+    "lipu",
+    "loje",
+    "lon",
+    "luka",
+    "lukin",
+    "lupa",
+    "ma",
+    "mama",
+    "mani",
+    "meli",
+    "mi",
+    "mije",
+    "moku",
+    "moli",
+    "monsi",
+    "mu",
+    "mun",
+    "musi",
+    "mute",
+    "nanpa",
+    "nasa",
+    "nasin",
+    "nena",
+    "ni",
+    "nimi",
+    "noka",
+    "o",
+    "olin",
+    "ona",
+    "open",
+    "pakala",
+    "pali",
+    "palisa",
+    "pan",
+    "pana",
+    "pi",
+    "pilin",
+    "pimeja",
+    "pini",
+    "pipi",
+    "poka",
+    "poki",
+    "pona",
+    "pu",
+    "sama",
+    "seli",
+    "selo",
+    "seme",
+    "sewi",
+    "sijelo",
+    "sike",
+    "sin",
+    "sina",
+    "sinpin",
+    "sitelen",
+    "sona",
+    "soweli",
+    "suli",
+    "suno",
+    "supa",
+    "suwi",
+    "tan",
+    "taso",
+    "tawa",
+    "telo",
+    "tenpo",
+    "toki",
+    "tomo",
+    "tu",
+    "unpa",
+    "uta",
+    "utala",
+    "walo",
+    "wan",
+    "waso",
+    "wawa",
+    "weka",
+    "wile",
+    "namako",
+    "kin",
+    "oko",
+    "kipisi",
+    "leko",
+    "monsuta",
+    "tonsi",
+    "jasima",
+    "kijetesantakalu",
+    "soko",
+    "meso",
+    "epiku",
+    "kokosila",
+    "lanpan",
+    "n",
+    "misikeke",
+    "ku",
+];
+
+/// Words at U+F19A0 onwards, in codepoint order
+const EXTRA_WORDS: &[&str] = &["pake", "apeja", "majuna", "powe"];
+
+/// First codepoint of the sitelen pona block
+const BLOCK_START: u32 = 0xF1900;
+/// First codepoint of the additional word range
+const EXTRA_WORDS_START: u32 = 0xF19A0;
+
+pub const CARTOUCHE_START: char = '\u{F1990}';
+pub const CARTOUCHE_END: char = '\u{F1991}';
+/// Start of a long `pi`, written as the word `pi`
+pub const LONG_PI_START: char = '\u{F1993}';
+/// Sentence ending middle dot, read as `.`
+pub const MIDDLE_DOT: char = '\u{F199C}';
+/// Colon, read as `:`
+pub const COLON: char = '\u{F199D}';
+
+/// Whether a character lies in the sitelen pona UCSUR block
+pub fn is_ucsur(c: char) -> bool {
+    (BLOCK_START..=0xF19FF).contains(&(c as u32))
+}
+
+/// Word written by a codepoint, if it is a word glyph
+pub fn word(c: char) -> Option<&'static str> {
+    if c == LONG_PI_START {
+        return Some("pi");
+    }
+    let code = c as u32;
+    let lookup = |start: u32, words: &[&'static str]| {
+        code.checked_sub(start)
+            .and_then(|index| words.get(index as usize).copied())
+    };
+    lookup(BLOCK_START, WORDS).or_else(|| lookup(EXTRA_WORDS_START, EXTRA_WORDS))
+}
+
+/// Latin punctuation mark written by a codepoint
+pub fn punctuation(c: char) -> Option<char> {
+    match c {
+        MIDDLE_DOT => Some('.'),
+        COLON => Some(':'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_codepoints() {
+        assert_eq!(word('\u{F1900}'), Some("a"));
+        assert_eq!(word('\u{F1934}'), Some("mi"));
+        assert_eq!(word('\u{F1977}'), Some("wile"));
+        assert_eq!(word('\u{F1988}'), Some("ku"));
+        assert_eq!(word('\u{F19A3}'), Some("powe"));
+        assert_eq!(word(LONG_PI_START), Some("pi"));
+        assert_eq!(word(CARTOUCHE_START), None);
+        assert_eq!(word('a'), None);
+    }
+}
@@ -1,6 +1,11 @@
 /// Integration tests for Sitelen Sitelen renderer
 
-use sitelen_core::{OutputFormat, Pipeline, RenderConfig};
+#[allow(clippy::useless_attribute, clippy::empty_line_after_doc_comments)]
+use sitelen_core::{
+    InputMode, LayoutEngine, LayoutSampling, LayoutWeights, NumberSystem, OutputFormat, Pipeline,
+    RenderConfig, SearchLimits, Sentence, SentencePart, Span, TextEdit,
+};
+use std::collections::BTreeSet;
 
 #[test]
 fn test_simple_parse() {
@@ -29,6 +34,227 @@ fn test_layout_simple() {
     }
 }
 
+#[test]
+fn test_layout_weights_choose_the_drawn_layout() {
+    let text = "jan pona li moku e kili.";
+    let pipeline = Pipeline::new().unwrap();
+    let compounds = pipeline.compound_options(text).unwrap();
+    assert_eq!(compounds.len(), 1);
+
+    // The default weights rank by ratio alone and draw the best ranked layout
+    let ranking = pipeline.rank_layouts(&compounds[0], 5);
+    assert_eq!(ranking.len(), 5);
+    assert!(ranking
+        .windows(2)
+        .all(|pair| pair[0].score <= pair[1].score));
+    let layout = pipeline.layout_text(text).unwrap();
+    assert_eq!(
+        format!("{:?}", layout.compounds[0]),
+        format!("{:?}", ranking[0].option)
+    );
+
+    let config = RenderConfig {
+        layout_weights: LayoutWeights {
+            subject_order: 10.0,
+            ..LayoutWeights::aesthetic()
+        },
+        ..RenderConfig::default()
+    };
+    let pipeline = Pipeline::with_config(config).unwrap();
+    let ranking = pipeline.rank_layouts(&compounds[0], 5);
+    assert_eq!(ranking[0].criteria.subject_order, 0.0);
+    assert!(ranking[0].criteria.contrast >= 0.0 && ranking[0].criteria.balance >= 0.0);
+    let layout = pipeline.layout_text(text).unwrap();
+    assert_eq!(
+        format!("{:?}", layout.compounds[0]),
+        format!("{:?}", ranking[0].option)
+    );
+
+    // Weights left out of a configuration file keep their defaults
+    let weights: LayoutWeights = serde_json::from_str(r#"{"contrast": 0.5}"#).unwrap();
+    assert_eq!(
+        weights,
+        LayoutWeights {
+            contrast: 0.5,
+            ..LayoutWeights::default()
+        }
+    );
+}
+
+#[test]
+fn test_ratio_bounds_and_random_layouts() {
+    let text = "jan pona li moku e kili. toki, jan Ali o kama.";
+    let render = |config: RenderConfig| {
+        let pipeline = Pipeline::with_config(RenderConfig {
+            exportable: false,
+            ..config
+        })
+        .unwrap();
+        let layout = pipeline.layout_text(text).unwrap();
+        let svg = pipeline.render(&layout, OutputFormat::Svg).unwrap();
+        (layout, String::from_utf8(svg).unwrap())
+    };
+
+    // Only layouts within the bounds are drawn, or the nearest when none fits
+    let (layout, _) = render(RenderConfig {
+        min_ratio: 1.5,
+        ..RenderConfig::default()
+    });
+    let pipeline = Pipeline::new().unwrap();
+    for (compound, options) in layout
+        .compounds
+        .iter()
+        .zip(pipeline.compound_options(text).unwrap())
+    {
+        let widest = options
+            .iter()
+            .map(|option| option.ratio)
+            .fold(0.0, f64::max);
+        assert!(compound.ratio >= 1.5 || compound.ratio == widest);
+    }
+
+    // Random picks are the same for the same seed, and differ between seeds
+    let random = |seed| {
+        render(RenderConfig {
+            random: true,
+            seed,
+            ..RenderConfig::default()
+        })
+        .1
+    };
+    assert_eq!(random(1), random(1));
+    assert!((2..10).any(|seed| random(seed) != random(1)));
+
+    // Compounds keep their own width instead of the width of the widest
+    let (layout, stretched) = render(RenderConfig::default());
+    let (_, own_size) = render(RenderConfig {
+        ignore_height: true,
+        ..RenderConfig::default()
+    });
+    assert_ne!(stretched, own_size);
+    let widths: Vec<f64> = layout.compounds.iter().map(|c| c.size.width).collect();
+    let narrowest = widths.iter().copied().fold(f64::INFINITY, f64::min);
+    assert!(own_size.contains(&format!(r#"width="{}""#, narrowest * 100.0)));
+}
+
+#[test]
+fn test_sampled_layouts_are_distinct_and_reproducible() {
+    let pipeline = Pipeline::new().unwrap();
+    let text = "jan pona li moku e kili. mi pona.";
+    let sampling = LayoutSampling {
+        count: 6,
+        seed: 42,
+        ..LayoutSampling::default()
+    };
+    let keys = |layouts: &[sitelen_core::Layout]| -> Vec<String> {
+        layouts
+            .iter()
+            .map(|layout| format!("{:?}", layout.compounds))
+            .collect()
+    };
+
+    let samples = pipeline.sample_layouts(text, &sampling).unwrap();
+    assert_eq!(samples.len(), 6);
+    let distinct: BTreeSet<String> = keys(&samples).into_iter().collect();
+    assert_eq!(distinct.len(), 6);
+    for layout in &samples {
+        assert_eq!(layout.compounds.len(), 2);
+        assert_eq!(layout.to_text(), "jan pona li moku e kili. mi pona.");
+    }
+
+    assert_eq!(
+        keys(&pipeline.sample_layouts(text, &sampling).unwrap()),
+        keys(&samples)
+    );
+    let reseeded = LayoutSampling {
+        seed: 43,
+        ..sampling
+    };
+    assert_ne!(
+        keys(&pipeline.sample_layouts(text, &reseeded).unwrap()),
+        keys(&samples)
+    );
+
+    // "mi pona" has only two arrangements
+    let short = pipeline.sample_layouts("mi pona", &sampling).unwrap();
+    assert_eq!(short.len(), 2);
+}
+
+#[test]
+fn test_search_limits_bound_long_sentences() {
+    let parser = sitelen_core::Parser::new();
+    let short = &parser.parse("jan pona li moku e kili lon tomo.").unwrap()[0];
+    let long = &parser
+        .parse("soweli suli ike wawa mute lili pona jaki laso loje jelo walo pimeja sin ante ale suwi nasa li moku.")
+        .unwrap()[0];
+
+    // Within the budget, the search tries every arrangement
+    let full = LayoutEngine::new().layout_compound(short);
+    let mut engine = LayoutEngine::new();
+    engine.set_search_limits(SearchLimits {
+        beam_width: None,
+        max_nodes: 20_000,
+    });
+    assert_eq!(
+        format!("{:?}", engine.layout_compound(short)),
+        format!("{full:?}")
+    );
+
+    // Far too long for the full search, but a spent budget or a beam still finishes,
+    // with the same layouts every time
+    for limits in [
+        SearchLimits {
+            beam_width: None,
+            max_nodes: 100,
+        },
+        SearchLimits::beam(1),
+        SearchLimits::beam(8),
+    ] {
+        engine.set_search_limits(limits);
+        let options = engine.layout_compound(long);
+        assert!(!options.is_empty(), "{limits:?}");
+        assert_eq!(
+            format!("{:?}", engine.layout_compound(long)),
+            format!("{options:?}")
+        );
+    }
+
+    // A beam keeps fewer layouts than the full search finds
+    engine.set_search_limits(SearchLimits::beam(2));
+    let beam = engine.layout_compound(short);
+    assert!(!beam.is_empty() && beam.len() < full.len());
+}
+
+#[test]
+fn test_search_budget_is_shared_between_parts() {
+    let parser = sitelen_core::Parser::new();
+    let long = &parser
+        .parse("jan pona mute suli lili ike wawa sin laso jelo loje walo pimeja kule li moku.")
+        .unwrap()[0];
+
+    // A long subject does not spend the budget of the other parts, so the compound
+    // still has layouts of several shapes to rank and sample from
+    let options = LayoutEngine::new().layout_compound(long);
+    let ratios: BTreeSet<String> = options
+        .iter()
+        .map(|option| format!("{:.3}", option.ratio))
+        .collect();
+    assert!(ratios.len() > 1, "{} options", options.len());
+
+    // The budget bounds the time spent on a long compound of many parts
+    let many = &parser
+        .parse("jan pona mute suli li moku e kili suli mute lon tomo mi suli pi jan ale tan ma ante kepeken ilo pona mute.")
+        .unwrap()[0];
+    let mut engine = LayoutEngine::new();
+    engine.set_search_limits(SearchLimits {
+        beam_width: None,
+        max_nodes: 2_000,
+    });
+    let start = std::time::Instant::now();
+    assert!(engine.layout_compound(many).len() > 1);
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+}
+
 #[test]
 fn test_render_svg() {
     let config = RenderConfig::default();
@@ -53,3 +279,422 @@ fn test_render_html() {
     assert!(html_str.contains("<!DOCTYPE html>"));
 }
 
+#[test]
+fn test_render_with_diagnostics() {
+    let config = RenderConfig::default();
+    let pipeline = Pipeline::with_config(config).unwrap();
+
+    assert!(pipeline
+        .render_text("mi mokuu.", OutputFormat::Svg)
+        .is_err());
+
+    let (svg_bytes, diagnostics) = pipeline
+        .render_text_with_diagnostics("mi mokuu. sina pona.", OutputFormat::Svg)
+        .unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    let svg_str = String::from_utf8(svg_bytes).unwrap();
+    assert!(svg_str.contains("tp-wg-unknown"));
+    assert!(svg_str.contains("tp-wg-sina"));
+}
+
+#[test]
+fn test_custom_lexicon() {
+    let mut lexicon = sitelen_core::Lexicon::default();
+    lexicon.extend(
+        sitelen_core::Lexicon::from_toml("[words.kiki]\nglyph = \"tp-wg-kijetesantakalu\"\n")
+            .unwrap(),
+    );
+    let pipeline = Pipeline::with_lexicon(RenderConfig::default(), lexicon).unwrap();
+
+    assert!(Pipeline::new().unwrap().parse("kiki li pona.").is_err());
+    let svg_bytes = pipeline
+        .render_text("kiki li pona.", OutputFormat::Svg)
+        .unwrap();
+    let svg_str = String::from_utf8(svg_bytes).unwrap();
+    assert!(svg_str.contains("tp-wg-kijetesantakalu"));
+}
+
+#[test]
+fn test_builtin_lexicon_glyphs_exist() {
+    let _pipeline = Pipeline::new().unwrap();
+    let registry = sitelen_core::glyphs::get_glyph_registry().unwrap();
+    let missing = registry.missing_glyphs(&sitelen_core::Lexicon::default());
+    // The sprite has no glyph for pu yet
+    assert_eq!(missing, vec!["pu".to_string()]);
+}
+
+#[test]
+fn test_preverbs_and_negation_render() {
+    let pipeline = Pipeline::new().unwrap();
+    for text in [
+        "mi wile ala moku.",
+        "sina pona ala pona?",
+        "ona li ken ala ken kama sona.",
+    ] {
+        let output = pipeline
+            .render_text(text, OutputFormat::Svg)
+            .unwrap_or_else(|e| panic!("failed to render {text:?}: {e}"));
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains("tp-wg-ala"), "{text:?} should draw ala");
+    }
+}
+
+#[test]
+fn test_la_clause_corpus() {
+    let corpus = include_str!("corpus/la_clauses.txt");
+    let pipeline = Pipeline::new().unwrap();
+
+    for line in corpus
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    {
+        let (expected, text) = line.split_once('\t').unwrap();
+        let expected: usize = expected.parse().unwrap();
+
+        let sentences = pipeline.parse(text).unwrap();
+        let markers = sentences
+            .iter()
+            .flat_map(|sentence| &sentence.parts)
+            .filter(|part| {
+                matches!(part, SentencePart::Punctuation { tokens, .. } if tokens[0].as_str() == "la")
+            })
+            .count();
+        assert_eq!(markers, expected, "la markers in {text:?}");
+
+        let svg =
+            String::from_utf8(pipeline.render_text(text, OutputFormat::Svg).unwrap()).unwrap();
+        assert_eq!(
+            svg.matches("#tp-wg-la\"").count(),
+            expected,
+            "la glyphs in {text:?}"
+        );
+    }
+}
+
+#[test]
+fn test_quoted_speech_renders_as_embedded_block() {
+    let pipeline = Pipeline::new().unwrap();
+    let output = pipeline
+        .render_text("jan li toki e ni: \"mi pona.\"", OutputFormat::Svg)
+        .unwrap();
+    let svg = String::from_utf8(output).unwrap();
+    assert!(svg.contains("#tp-c-quote"));
+    assert_eq!(svg.matches("#tp-wg-period\"").count(), 1);
+}
+
+#[test]
+fn test_number_phrase_is_a_single_block() {
+    let mut pipeline = Pipeline::new().unwrap();
+    pipeline.set_number_system(NumberSystem::Pona);
+
+    let sentences = pipeline.parse("mute mute luka tu wan").unwrap();
+    let [SentencePart::Subject {
+        parts: Some(parts), ..
+    }] = &sentences[0].parts[..]
+    else {
+        panic!(
+            "expected a structured subject, got {:?}",
+            sentences[0].parts
+        );
+    };
+    let [number @ SentencePart::Number { value, .. }] = &parts[..] else {
+        panic!("expected a number phrase, got {parts:?}");
+    };
+    assert_eq!(*value, 48);
+
+    // The glyphs are arranged once, not searched combinatorially
+    let options = pipeline.layout(&Sentence::new(vec![number.clone()]));
+    assert_eq!(options.len(), 1);
+    assert_eq!(options[0].size.width, 3.0);
+
+    let output = pipeline
+        .render_text("jan luka tu li kama.", OutputFormat::Svg)
+        .unwrap();
+    let svg = String::from_utf8(output).unwrap();
+    assert!(svg.contains("#tp-wg-luka\"") && svg.contains("#tp-wg-tu\""));
+}
+
+#[test]
+fn test_render_from_json_ast() {
+    let config = RenderConfig {
+        exportable: false,
+        ..RenderConfig::default()
+    };
+    let pipeline = Pipeline::with_config(config).unwrap();
+    let text = "jan Ana li moku e kili lon tomo.";
+
+    let json = pipeline.parse_to_json(text).unwrap();
+    let from_json = pipeline.render_json(&json, OutputFormat::Svg).unwrap();
+    let from_text = pipeline.render_text(text, OutputFormat::Svg).unwrap();
+    assert_eq!(from_json, from_text);
+
+    // A hand-corrected tree renders without going through the parser
+    let edited = json.replace("\"kili\"", "\"telo\"");
+    let svg = String::from_utf8(pipeline.render_json(&edited, OutputFormat::Svg).unwrap()).unwrap();
+    assert!(svg.contains("#tp-wg-telo\"") && !svg.contains("#tp-wg-kili\""));
+}
+
+#[test]
+fn test_render_markup() {
+    let config = RenderConfig {
+        exportable: false,
+        ..RenderConfig::default()
+    };
+    let text_pipeline = Pipeline::with_config(config.clone()).unwrap();
+    let mut markup_pipeline = Pipeline::with_config(config).unwrap();
+    markup_pipeline.set_input_mode(InputMode::Markup);
+
+    // Markup matching the automatic parse renders the same
+    let from_text = text_pipeline
+        .render_text("jan Ana li moku e kili lon tomo.", OutputFormat::Svg)
+        .unwrap();
+    let from_markup = markup_pipeline
+        .render_text("(jan Ana) li[moku] e(kili) lon[tomo].", OutputFormat::Svg)
+        .unwrap();
+    assert_eq!(from_markup, from_text);
+
+    // Plain words outside groups are kept as one part, with no automatic structure
+    let sentences = markup_pipeline.parse("mi lukin e poka.").unwrap();
+    assert!(matches!(
+        &sentences[0].parts[0],
+        SentencePart::Subject { tokens, parts: None, .. } if tokens.len() == 4
+    ));
+}
+
+#[test]
+fn test_coordination_frame() {
+    let text = "mi en sina li moku e kili anu telo.";
+    let render = |coordination_frame| {
+        let config = RenderConfig {
+            coordination_frame,
+            exportable: false,
+            ..RenderConfig::default()
+        };
+        let pipeline = Pipeline::with_config(config).unwrap();
+        String::from_utf8(pipeline.render_text(text, OutputFormat::Svg).unwrap()).unwrap()
+    };
+
+    // Without the frame, only li is drawn as a rect
+    let plain = render(false);
+    assert_eq!(plain.matches("<rect").count(), 1);
+    assert!(!plain.contains("fill=\"none\""));
+
+    let framed = render(true);
+    assert_eq!(framed.matches("fill=\"none\"></rect>").count(), 2);
+    assert!(framed.contains("#tp-wg-en\"") && framed.contains("#tp-wg-anu\""));
+}
+
+#[test]
+fn test_commands_and_vocatives_use_the_o_container() {
+    let pipeline = Pipeline::new().unwrap();
+    let render = |text: &str| {
+        let output = pipeline.render_text(text, OutputFormat::Svg).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+
+    for text in ["o moku e kili!", "jan Ali o, moku.", "tenpo ni la o lape."] {
+        let svg = render(text);
+        assert!(
+            svg.contains("#tp-c-o"),
+            "{text:?} should draw the o container"
+        );
+        assert!(
+            !svg.contains("#tp-wg-o\""),
+            "{text:?} should not draw o as a word"
+        );
+    }
+
+    // Names addressed by o are drawn in cartouches
+    let svg = render("jan Ali o, moku.");
+    assert!(svg.contains("#tp-c-cartouche") && svg.contains("#tp-syl-li\""));
+    assert!(!svg.contains("#tp-wg-Ali\""));
+
+    // Interjections are drawn as words of their own
+    let svg = render("toki a! mi moku a a a.");
+    assert_eq!(svg.matches("#tp-wg-a\"").count(), 4);
+    assert!(!svg.contains("#tp-c-"));
+}
+
+#[test]
+fn test_filled_lines_and_columns() {
+    let text = "mi jan pona. sina jan pona. jan pona mute li moku e kili suli. ona li tawa \
+                tomo tawa. soweli lili li lape lon tomo. telo li pona tawa mi. kon li tawa.";
+    let config = RenderConfig {
+        page_width: 12.0,
+        page_height: Some(6.0),
+        columns: 2,
+        fill_lines: true,
+        exportable: false,
+        ..RenderConfig::default()
+    };
+    let pipeline = Pipeline::with_config(config).unwrap();
+    let document = pipeline.parse_document(text).unwrap();
+    let page = pipeline.layout_document(&document);
+    assert_eq!(page.compounds.len(), 7);
+    assert_eq!(
+        sitelen_core::Layout {
+            compounds: page.compounds.iter().map(|c| c.option.clone()).collect()
+        }
+        .to_text(),
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    );
+
+    // Columns of 5.625 glyphs; rows continue in the second column and on a new page
+    let column_width = (12.0 - 0.75) / 2.0;
+    let second_column = 12.0 - column_width;
+    assert!(page.compounds.iter().any(|c| c.position.x >= second_column));
+    assert!(page.size.height > 6.0);
+    for placed in &page.compounds {
+        let left = if placed.position.x >= second_column {
+            second_column
+        } else {
+            0.0
+        };
+        assert!(placed.position.x + placed.size.width <= left + column_width + 1e-6);
+        let page_top = if placed.position.y >= 6.0 { 6.75 } else { 0.0 };
+        assert!(placed.position.y + placed.size.height <= page_top + 6.0 + 1e-6);
+    }
+
+    // Rows with several compounds but the last are justified to the edge of their column
+    let mut rows: Vec<Vec<&sitelen_core::types::PlacedCompound>> = Vec::new();
+    for placed in &page.compounds {
+        match rows.last_mut() {
+            Some(row) if placed.position.x > row.last().unwrap().position.x => row.push(placed),
+            _ => rows.push(vec![placed]),
+        }
+    }
+    let (last, rows) = rows.split_last().unwrap();
+    assert!(!last.is_empty());
+    for row in rows.iter().filter(|row| row.len() > 1) {
+        let end = row.last().unwrap();
+        let right = end.position.x + end.size.width;
+        assert!((right - column_width).abs() < 1e-6 || (right - 12.0).abs() < 1e-6);
+    }
+
+    // The page is rendered with every compound in its box
+    let svg = pipeline
+        .render_document(&document, OutputFormat::Svg)
+        .unwrap();
+    let svg = String::from_utf8(svg).unwrap();
+    assert_eq!(svg.matches(r#"viewBox="0 0 100 100""#).count(), 7);
+}
+
+#[test]
+fn test_document_page_layout() {
+    let config = RenderConfig {
+        page_width: 8.0,
+        exportable: false,
+        ..RenderConfig::default()
+    };
+    let pipeline = Pipeline::with_config(config).unwrap();
+    let text = "# toki\n\nmi pona. sina pona.\n\nkon li tawa\ntelo li tawa";
+    let document = pipeline.parse_document(text).unwrap();
+    let page = pipeline.layout_document(&document);
+
+    // Five compounds: the heading, two paragraph sentences side by side and one row per stanza line
+    let rows: BTreeSet<u64> = page
+        .compounds
+        .iter()
+        .map(|placed| (placed.position.y * 1000.0).round() as u64)
+        .collect();
+    assert_eq!(page.compounds.len(), 5);
+    assert!(page.compounds[1].position.x < page.compounds[2].position.x);
+    assert!(page.compounds[3].position.y < page.compounds[4].position.y);
+    assert!(rows.len() >= 3);
+
+    // Headings are centred and everything fits on the page
+    let heading = &page.compounds[0];
+    let center = heading.position.x + heading.size.width / 2.0;
+    assert!((center - 4.0).abs() < 1e-6);
+    for placed in &page.compounds {
+        assert!(placed.position.x + placed.size.width <= 8.0 + 1e-6);
+        assert!(placed.position.y + placed.size.height <= page.size.height + 1e-6);
+    }
+
+    let svg = String::from_utf8(
+        pipeline
+            .render_document_text(text, OutputFormat::Svg)
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(svg.matches("#tp-wg-tawa\"").count(), 2);
+    assert_eq!(svg.matches("viewBox=\"0 0 100 100\"").count(), 5);
+}
+
+#[test]
+fn test_live_text_reuses_unchanged_layouts() {
+    let config = RenderConfig {
+        exportable: false,
+        ..RenderConfig::default()
+    };
+    let pipeline = Pipeline::with_config(config).unwrap();
+    let mut live = pipeline.live_text("mi moku. sina pona. ona li lape.");
+
+    // Only the sentences touching an edit are laid out again
+    let edits = [
+        (TextEdit::new(Span::new(14, 18), "wawa"), (1, 1, 1)),
+        (TextEdit::insert(8, " jan li toki!"), (0, 1, 2)),
+        (TextEdit::delete(Span::new(0, 9)), (0, 2, 1)),
+    ];
+    for (edit, expected) in &edits {
+        let splice = pipeline.edit_live_text(&mut live, edit).unwrap();
+        assert_eq!(
+            (splice.start, splice.removed, splice.inserted),
+            *expected,
+            "{edit:?}"
+        );
+
+        let expected = pipeline
+            .render_text(live.text(), OutputFormat::Svg)
+            .unwrap();
+        let rendered = pipeline.render_live_text(&live, OutputFormat::Svg).unwrap();
+        assert_eq!(rendered, expected, "{:?}", live.text());
+    }
+    assert_eq!(live.text(), "jan li toki! sina wawa. ona li lape.");
+}
+
+/// Sentences without spans, to compare parses of different texts
+fn structure(sentences: &[Sentence]) -> serde_json::Value {
+    fn strip(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                map.remove("span");
+                map.values_mut().for_each(strip);
+            }
+            serde_json::Value::Array(values) => values.iter_mut().for_each(strip),
+            _ => {}
+        }
+    }
+    let mut value = serde_json::to_value(sentences).unwrap();
+    strip(&mut value);
+    value
+}
+
+#[test]
+fn test_layout_text_round_trip() {
+    // Text read back from a layout parses to the structure that was laid out
+    let pipeline = Pipeline::new().unwrap();
+    let corpus = include_str!("corpus/la_clauses.txt")
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.split_once('\t').unwrap().1);
+    let examples = [
+        "jan Pona li toki e ni: \"mi pona.\" ona li lape.",
+        "jan Ali o, o kama!",
+        "mi en sina li wile ala moku e kili anu telo.",
+        "ona li lon ma Kanata tan tenpo suli.",
+        "ni li telo Wino sina.",
+        "mi wile pana e pona tawa jan Ton.",
+        "ona li toki e \"toki\" tawa meli.",
+        "jan Ton en jan Mali li nimi \"Son\" e jan lili ona.",
+        "mi toki e \"sina pona\" e \"mi pona\".",
+    ];
+    for text in corpus.chain(examples) {
+        let read_back = pipeline.layout_text(text).unwrap().to_text();
+        assert_eq!(
+            structure(&pipeline.parse(&read_back).unwrap()),
+            structure(&pipeline.parse(text).unwrap()),
+            "{text:?} read back as {read_back:?}"
+        );
+    }
+}
@@ -1,10 +1,17 @@
 /// WASM bindings for Sitelen Sitelen renderer
 
-use sitelen_core::{OutputFormat, Pipeline, RenderConfig, init_glyph_registry};
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsValue;
+#[allow(clippy::useless_attribute, clippy::empty_line_after_doc_comments)]
 use once_cell::sync::Lazy;
+use serde::Serialize;
+use sitelen_core::{
+    init_glyph_registry, CriterionScores, InputMode, LayoutSampling, LayoutWeights, Lexicon,
+    LiveText, NameDetection, NumberSystem, OutputFormat, Pipeline, PrepositionUse, RenderConfig,
+    SearchLimits, Span, SyllableMode, TextEdit,
+};
+use std::collections::HashMap;
 use std::sync::Mutex;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsValue;
 
 // Embed the sprite file at compile time
 const SPRITE_CONTENT: &str = include_str!("../../images/glyphs.svg");
@@ -20,33 +27,101 @@ macro_rules! console_log {
 }
 
 // Singleton pipeline instance (using Mutex for thread safety, though WASM is single-threaded)
-static PIPELINE: Lazy<Mutex<Option<Pipeline>>> = Lazy::new(|| {
-    Mutex::new(None)
+static PIPELINE: Lazy<Mutex<Option<Pipeline>>> = Lazy::new(|| Mutex::new(None));
+
+// Lexicon shared by all pipelines (built-in words plus any loaded with `init_lexicon`)
+static LEXICON: Lazy<Mutex<Lexicon>> = Lazy::new(|| Mutex::new(Lexicon::default()));
+
+// Syllabification mode for proper names, set with `set_syllable_mode`
+static SYLLABLE_MODE: Lazy<Mutex<SyllableMode>> = Lazy::new(|| Mutex::new(SyllableMode::default()));
+
+// How proper names are recognised, set with `set_name_detection`
+static NAME_DETECTION: Lazy<Mutex<NameDetection>> =
+    Lazy::new(|| Mutex::new(NameDetection::default()));
+
+// Counting system for number phrases, set with `set_number_system`
+static NUMBER_SYSTEM: Lazy<Mutex<NumberSystem>> = Lazy::new(|| Mutex::new(NumberSystem::default()));
+
+// Whether input is text or structure markup, set with `set_input_mode`
+static INPUT_MODE: Lazy<Mutex<InputMode>> = Lazy::new(|| Mutex::new(InputMode::default()));
+
+// Forced uses of preposition words by byte offset, set with `set_preposition_override`
+static PREPOSITION_OVERRIDES: Lazy<Mutex<HashMap<usize, PrepositionUse>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Whether coordinated words get a shared frame, set with `set_coordination_frame`
+static COORDINATION_FRAME: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+// Bounds on the layout search, set with `set_search_limits`
+static SEARCH_LIMITS: Lazy<Mutex<SearchLimits>> = Lazy::new(|| Mutex::new(SearchLimits::default()));
+
+// Text of the live editor with its cached layouts, set with `set_live_text`
+// Bounds on the ratio of compounds
+static RATIO_BOUNDS: Lazy<Mutex<(f64, f64)>> = Lazy::new(|| {
+    let config = RenderConfig::default();
+    Mutex::new((config.min_ratio, config.max_ratio))
 });
 
+// Seed of random layout selection, when layouts are picked at random
+static RANDOM_SEED: Lazy<Mutex<Option<u64>>> = Lazy::new(|| Mutex::new(None));
+
+// Whether compounds keep their own size instead of being stretched to the widest
+static IGNORE_HEIGHT: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+// Page height, columns and line filling of documents
+static PAGE_LAYOUT: Lazy<Mutex<(Option<f64>, usize, bool)>> =
+    Lazy::new(|| Mutex::new((None, 1, false)));
+
+// Weights of the criteria that choose between layouts
+static LAYOUT_WEIGHTS: Lazy<Mutex<LayoutWeights>> =
+    Lazy::new(|| Mutex::new(LayoutWeights::default()));
+
+static LIVE_TEXT: Lazy<Mutex<Option<LiveText>>> = Lazy::new(|| Mutex::new(None));
+
+/// Create a pipeline using the shared lexicon and parser settings
+fn create_pipeline(mut config: RenderConfig) -> Result<Pipeline, JsValue> {
+    config.coordination_frame = *COORDINATION_FRAME.lock().unwrap();
+    config.layout_weights = *LAYOUT_WEIGHTS.lock().unwrap();
+    (config.min_ratio, config.max_ratio) = *RATIO_BOUNDS.lock().unwrap();
+    config.ignore_height = *IGNORE_HEIGHT.lock().unwrap();
+    (config.page_height, config.columns, config.fill_lines) = *PAGE_LAYOUT.lock().unwrap();
+    if let Some(seed) = *RANDOM_SEED.lock().unwrap() {
+        config.random = true;
+        config.seed = seed;
+    }
+    let lexicon = LEXICON.lock().unwrap().clone();
+    let mut pipeline = Pipeline::with_lexicon(config, lexicon)
+        .map_err(|e| JsValue::from_str(&format!("Failed to create pipeline: {}", e)))?;
+    pipeline.set_syllabifier(*SYLLABLE_MODE.lock().unwrap());
+    pipeline.set_name_detection(*NAME_DETECTION.lock().unwrap());
+    pipeline.set_number_system(*NUMBER_SYSTEM.lock().unwrap());
+    pipeline.set_input_mode(*INPUT_MODE.lock().unwrap());
+    pipeline.set_preposition_overrides(PREPOSITION_OVERRIDES.lock().unwrap().clone());
+    pipeline.set_search_limits(*SEARCH_LIMITS.lock().unwrap());
+    Ok(pipeline)
+}
+
 /// Get or create the pipeline singleton and execute a closure with it
 fn with_pipeline<F, R>(f: F) -> Result<R, JsValue>
 where
     F: FnOnce(&mut Pipeline) -> Result<R, JsValue>,
 {
     let mut pipeline_opt = PIPELINE.lock().unwrap();
-    
+
     if pipeline_opt.is_none() {
-        let config = RenderConfig::default();
-        let pipeline = Pipeline::with_config(config)
-            .map_err(|e| JsValue::from_str(&format!("Failed to create pipeline: {}", e)))?;
+        let pipeline = create_pipeline(RenderConfig::default())?;
         *pipeline_opt = Some(pipeline);
     }
-    
+
     f(pipeline_opt.as_mut().unwrap())
 }
 
 /// Get or create a pipeline with a specific optimal ratio
+#[allow(clippy::field_reassign_with_default)]
 fn get_pipeline_with_ratio(optimal_ratio: f64) -> Result<Pipeline, JsValue> {
     let mut config = RenderConfig::default();
     config.optimal_ratio = optimal_ratio;
-    Pipeline::with_config(config)
-        .map_err(|e| JsValue::from_str(&format!("Failed to create pipeline: {}", e)))
+    create_pipeline(config)
 }
 
 /// Initialize glyph registry (called automatically, but can be called manually for custom sprites)
@@ -59,7 +134,7 @@ pub fn init() {
 }
 
 /// Render text to SVG string
-/// 
+///
 /// # Arguments
 /// * `text` - Toki Pona text to render
 /// * `optimal_ratio` - Optional optimal ratio for layout (if None, uses default)
@@ -67,21 +142,22 @@ pub fn init() {
 pub fn render_svg(text: &str, optimal_ratio: Option<f64>) -> Result<String, JsValue> {
     let bytes = if let Some(ratio) = optimal_ratio {
         let pipeline = get_pipeline_with_ratio(ratio)?;
-        pipeline.render_text(text, OutputFormat::Svg)
+        pipeline
+            .render_text(text, OutputFormat::Svg)
             .map_err(|e| JsValue::from_str(&format!("Rendering failed: {}", e)))?
     } else {
         with_pipeline(|pipeline| {
-            pipeline.render_text(text, OutputFormat::Svg)
+            pipeline
+                .render_text(text, OutputFormat::Svg)
                 .map_err(|e| JsValue::from_str(&format!("Rendering failed: {}", e)))
         })?
     };
-    
-    String::from_utf8(bytes)
-        .map_err(|e| JsValue::from_str(&format!("Invalid SVG UTF-8: {}", e)))
+
+    String::from_utf8(bytes).map_err(|e| JsValue::from_str(&format!("Invalid SVG UTF-8: {}", e)))
 }
 
 /// Render text to PNG bytes
-/// 
+///
 /// # Arguments
 /// * `text` - Toki Pona text to render
 /// * `optimal_ratio` - Optional optimal ratio for layout (if None, uses default)
@@ -89,36 +165,98 @@ pub fn render_svg(text: &str, optimal_ratio: Option<f64>) -> Result<String, JsVa
 pub fn render_png(text: &str, optimal_ratio: Option<f64>) -> Result<Vec<u8>, JsValue> {
     if let Some(ratio) = optimal_ratio {
         let pipeline = get_pipeline_with_ratio(ratio)?;
-        pipeline.render_text(text, OutputFormat::Png)
+        pipeline
+            .render_text(text, OutputFormat::Png)
             .map_err(|e| JsValue::from_str(&format!("Rendering failed: {}", e)))
     } else {
         with_pipeline(|pipeline| {
-            pipeline.render_text(text, OutputFormat::Png)
+            pipeline
+                .render_text(text, OutputFormat::Png)
                 .map_err(|e| JsValue::from_str(&format!("Rendering failed: {}", e)))
         })
     }
 }
 
+/// Render a whole text as one page of headings, paragraphs and stanzas to an SVG string
+///
+/// # Arguments
+/// * `text` - Toki Pona text; blank lines separate blocks and `# ` starts a heading
+/// * `page_width` - Optional page width in glyphs (if None, uses default)
+#[wasm_bindgen]
+pub fn render_document_svg(text: &str, page_width: Option<f64>) -> Result<String, JsValue> {
+    let bytes = if let Some(page_width) = page_width {
+        let pipeline = create_pipeline(RenderConfig {
+            page_width,
+            ..RenderConfig::default()
+        })?;
+        pipeline
+            .render_document_text(text, OutputFormat::Svg)
+            .map_err(|e| JsValue::from_str(&format!("Rendering failed: {}", e)))?
+    } else {
+        with_pipeline(|pipeline| {
+            pipeline
+                .render_document_text(text, OutputFormat::Svg)
+                .map_err(|e| JsValue::from_str(&format!("Rendering failed: {}", e)))
+        })?
+    };
+
+    String::from_utf8(bytes).map_err(|e| JsValue::from_str(&format!("Invalid SVG UTF-8: {}", e)))
+}
+
+/// Parse text into a JSON AST (see `sitelen_core::ast` for the schema)
+#[wasm_bindgen]
+pub fn parse_to_json(text: &str) -> Result<String, JsValue> {
+    with_pipeline(|pipeline| {
+        pipeline
+            .parse_to_json(text)
+            .map_err(|e| JsValue::from_str(&format!("Parse failed: {}", e)))
+    })
+}
+
+/// Render sentences from a JSON AST to an SVG string, skipping the text parser
+///
+/// # Arguments
+/// * `json` - AST as produced by `parse_to_json`, possibly edited
+/// * `optimal_ratio` - Optional optimal ratio for layout (if None, uses default)
+#[wasm_bindgen]
+pub fn render_json_svg(json: &str, optimal_ratio: Option<f64>) -> Result<String, JsValue> {
+    let bytes = if let Some(ratio) = optimal_ratio {
+        let pipeline = get_pipeline_with_ratio(ratio)?;
+        pipeline
+            .render_json(json, OutputFormat::Svg)
+            .map_err(|e| JsValue::from_str(&format!("Rendering failed: {}", e)))?
+    } else {
+        with_pipeline(|pipeline| {
+            pipeline
+                .render_json(json, OutputFormat::Svg)
+                .map_err(|e| JsValue::from_str(&format!("Rendering failed: {}", e)))
+        })?
+    };
+
+    String::from_utf8(bytes).map_err(|e| JsValue::from_str(&format!("Invalid SVG UTF-8: {}", e)))
+}
+
 /// Get all available layout option ratios for a text
 /// Returns a JSON array of ratios sorted from smallest to largest
 #[wasm_bindgen]
 pub fn get_layout_ratios(text: &str) -> Result<String, JsValue> {
     with_pipeline(|pipeline| {
-        let sentences = pipeline.parse(text)
+        let sentences = pipeline
+            .parse(text)
             .map_err(|e| JsValue::from_str(&format!("Parse failed: {}", e)))?;
-        
+
         if sentences.is_empty() {
             return Ok("[]".to_string());
         }
-        
+
         // For interactive rendering, we work with the first sentence as a whole
         // (not split by punctuation like in render_text)
         let sentence = &sentences[0];
         let options = pipeline.layout(sentence);
-        
+
         let mut ratios: Vec<f64> = options.iter().map(|opt| opt.ratio).collect();
         ratios.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-        
+
         serde_json::to_string(&ratios)
             .map_err(|e| JsValue::from_str(&format!("Failed to serialize ratios: {}", e)))
     })
@@ -130,11 +268,15 @@ pub fn get_layout_ratios(text: &str) -> Result<String, JsValue> {
 pub fn render_sentences(text: &str, optimal_ratio: Option<f64>) -> Result<String, JsValue> {
     if let Some(ratio) = optimal_ratio {
         let pipeline = get_pipeline_with_ratio(ratio)?;
-        let sentences = pipeline.parse(text)
+        let sentences = pipeline
+            .parse(text)
             .map_err(|e| JsValue::from_str(&format!("Parse failed: {}", e)))?;
 
         // Filter out sentences with no parts (they won't render anything useful)
-        let sentences: Vec<_> = sentences.into_iter().filter(|s| !s.parts.is_empty()).collect();
+        let sentences: Vec<_> = sentences
+            .into_iter()
+            .filter(|s| !s.parts.is_empty())
+            .collect();
 
         if sentences.is_empty() {
             return Ok(String::new());
@@ -142,7 +284,8 @@ pub fn render_sentences(text: &str, optimal_ratio: Option<f64>) -> Result<String
 
         let mut result = String::new();
         for sentence in &sentences {
-            let bytes = pipeline.render_sentence(sentence, OutputFormat::Svg)
+            let bytes = pipeline
+                .render_sentence(sentence, OutputFormat::Svg)
                 .map_err(|e| JsValue::from_str(&format!("Rendering failed: {}", e)))?;
             let svg = String::from_utf8(bytes)
                 .map_err(|e| JsValue::from_str(&format!("Invalid SVG UTF-8: {}", e)))?;
@@ -156,11 +299,15 @@ pub fn render_sentences(text: &str, optimal_ratio: Option<f64>) -> Result<String
         Ok(result)
     } else {
         with_pipeline(|pipeline| {
-            let sentences = pipeline.parse(text)
+            let sentences = pipeline
+                .parse(text)
                 .map_err(|e| JsValue::from_str(&format!("Parse failed: {}", e)))?;
 
             // Filter out sentences with no parts (they won't render anything useful)
-            let sentences: Vec<_> = sentences.into_iter().filter(|s| !s.parts.is_empty()).collect();
+            let sentences: Vec<_> = sentences
+                .into_iter()
+                .filter(|s| !s.parts.is_empty())
+                .collect();
 
             if sentences.is_empty() {
                 return Ok(String::new());
@@ -168,7 +315,8 @@ pub fn render_sentences(text: &str, optimal_ratio: Option<f64>) -> Result<String
 
             let mut result = String::new();
             for sentence in &sentences {
-                let bytes = pipeline.render_sentence(sentence, OutputFormat::Svg)
+                let bytes = pipeline
+                    .render_sentence(sentence, OutputFormat::Svg)
                     .map_err(|e| JsValue::from_str(&format!("Rendering failed: {}", e)))?;
                 let svg = String::from_utf8(bytes)
                     .map_err(|e| JsValue::from_str(&format!("Invalid SVG UTF-8: {}", e)))?;
@@ -184,6 +332,53 @@ pub fn render_sentences(text: &str, optimal_ratio: Option<f64>) -> Result<String
     }
 }
 
+/// Start live editing of a text, parsing and laying out every sentence
+///
+/// Illegal words are drawn as placeholders. Call again after changing a setting, as
+/// the layouts kept for the text were made with the old one.
+#[wasm_bindgen]
+pub fn set_live_text(text: &str) -> Result<(), JsValue> {
+    let live = with_pipeline(|pipeline| Ok(pipeline.live_text(text)))?;
+    *LIVE_TEXT.lock().unwrap() = Some(live);
+    Ok(())
+}
+
+/// Replace bytes `start..end` of the live text, reparsing only the sentences the edit touches
+///
+/// Offsets are UTF-8 byte offsets, as in spans. Returns the replaced sentences as JSON
+/// `{"start", "removed", "inserted"}`: the `removed` sentences from index `start` were
+/// replaced by `inserted` new ones.
+#[wasm_bindgen]
+pub fn edit_live_text(start: usize, end: usize, replacement: &str) -> Result<String, JsValue> {
+    let mut live_text = LIVE_TEXT.lock().unwrap();
+    let live = live_text
+        .as_mut()
+        .ok_or_else(|| JsValue::from_str("No live text, call set_live_text first"))?;
+    let edit = TextEdit::new(Span::new(start, end), replacement);
+    let splice = with_pipeline(|pipeline| {
+        pipeline
+            .edit_live_text(live, &edit)
+            .map_err(|e| JsValue::from_str(&format!("Edit failed: {}", e)))
+    })?;
+    serde_json::to_string(&splice)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize splice: {}", e)))
+}
+
+/// Render the live text to an SVG string from the layouts kept for its sentences
+#[wasm_bindgen]
+pub fn render_live_svg() -> Result<String, JsValue> {
+    let live_text = LIVE_TEXT.lock().unwrap();
+    let live = live_text
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No live text, call set_live_text first"))?;
+    let bytes = with_pipeline(|pipeline| {
+        pipeline
+            .render_live_text(live, OutputFormat::Svg)
+            .map_err(|e| JsValue::from_str(&format!("Rendering failed: {}", e)))
+    })?;
+    String::from_utf8(bytes).map_err(|e| JsValue::from_str(&format!("Invalid SVG UTF-8: {}", e)))
+}
+
 /// Initialize the glyph registry with custom sprite content
 /// This allows overriding the default embedded sprite with a custom one
 #[wasm_bindgen]
@@ -191,3 +386,317 @@ pub fn init_glyphs(sprite_content: &str) -> Result<(), JsValue> {
     init_glyph_registry(sprite_content)
         .map_err(|e| JsValue::from_str(&format!("Failed to initialize glyphs: {}", e)))
 }
+
+/// Add words (nimi sin) from a JSON lexicon on top of the built-in lexicon
+/// Subsequent renders use the extended lexicon
+#[wasm_bindgen]
+pub fn init_lexicon(lexicon_json: &str) -> Result<(), JsValue> {
+    let words = Lexicon::from_json(lexicon_json)
+        .map_err(|e| JsValue::from_str(&format!("Failed to load lexicon: {}", e)))?;
+    LEXICON.lock().unwrap().extend(words);
+    // Drop the cached pipeline so it is rebuilt with the new lexicon
+    *PIPELINE.lock().unwrap() = None;
+    Ok(())
+}
+
+/// Set how proper names are split into cartouche syllables: "strict" (default) or "lenient"
+///
+/// Lenient mode maps foreign letters to the closest Toki Pona syllables, so `jan Olaf`
+/// is written as o-la-pu. Explicit spellings such as `[o-la-pu]` work in both modes.
+#[wasm_bindgen]
+pub fn set_syllable_mode(mode: &str) -> Result<(), JsValue> {
+    let mode = match mode {
+        "strict" => SyllableMode::Strict,
+        "lenient" => SyllableMode::Lenient,
+        _ => {
+            return Err(JsValue::from_str(&format!(
+                "Unknown syllable mode: {}",
+                mode
+            )))
+        }
+    };
+    *SYLLABLE_MODE.lock().unwrap() = mode;
+    // Drop the cached pipeline so it is rebuilt with the new mode
+    *PIPELINE.lock().unwrap() = None;
+    Ok(())
+}
+
+/// Set how proper names are recognised: "capitalized" (default) or "explicit"
+///
+/// In explicit mode only names marked up as `{Pona}` (or spelled as `[o-la-pu]`) are
+/// drawn in cartouches, so capitalised words are read as ordinary words.
+#[wasm_bindgen]
+pub fn set_name_detection(mode: &str) -> Result<(), JsValue> {
+    let detection = match mode {
+        "capitalized" => NameDetection::Capitalized,
+        "explicit" => NameDetection::Explicit,
+        _ => {
+            return Err(JsValue::from_str(&format!(
+                "Unknown name detection: {}",
+                mode
+            )))
+        }
+    };
+    *NAME_DETECTION.lock().unwrap() = detection;
+    // Drop the cached pipeline so it is rebuilt with the new setting
+    *PIPELINE.lock().unwrap() = None;
+    Ok(())
+}
+
+/// Set the counting system for number phrases: "off" (default), "pu" or "pona"
+///
+/// Recognised number phrases such as `luka tu` are drawn as a compact block of number glyphs.
+#[wasm_bindgen]
+pub fn set_number_system(system: &str) -> Result<(), JsValue> {
+    let system = match system {
+        "off" => NumberSystem::Off,
+        "pu" => NumberSystem::Pu,
+        "pona" => NumberSystem::Pona,
+        _ => {
+            return Err(JsValue::from_str(&format!(
+                "Unknown number system: {}",
+                system
+            )))
+        }
+    };
+    *NUMBER_SYSTEM.lock().unwrap() = system;
+    // Drop the cached pipeline so it is rebuilt with the new setting
+    *PIPELINE.lock().unwrap() = None;
+    Ok(())
+}
+
+/// Set how input is read: "text" (default) or "markup"
+///
+/// Markup spells out the structure with brackets, such as `(mi) li[moku] e(kili).`
+#[wasm_bindgen]
+pub fn set_input_mode(mode: &str) -> Result<(), JsValue> {
+    let mode = match mode {
+        "text" => InputMode::Text,
+        "markup" => InputMode::Markup,
+        _ => return Err(JsValue::from_str(&format!("Unknown input mode: {}", mode))),
+    };
+    *INPUT_MODE.lock().unwrap() = mode;
+    // Drop the cached pipeline so it is rebuilt with the new setting
+    *PIPELINE.lock().unwrap() = None;
+    Ok(())
+}
+
+/// Draw a shared frame around words joined by `en` or `anu` (off by default)
+#[wasm_bindgen]
+pub fn set_coordination_frame(enabled: bool) {
+    *COORDINATION_FRAME.lock().unwrap() = enabled;
+    // Drop the cached pipeline so it is rebuilt with the new setting
+    *PIPELINE.lock().unwrap() = None;
+}
+
+/// Bound the layout search for long sentences
+///
+/// With a `beam_width`, a beam search keeps that many partial layouts instead of trying
+/// every arrangement. `max_nodes` is the number of placements tried per compound before
+/// the rest of the search is finished greedily; leave it out for the default.
+#[wasm_bindgen]
+pub fn set_search_limits(beam_width: Option<u32>, max_nodes: Option<u32>) {
+    let default = SearchLimits::default();
+    *SEARCH_LIMITS.lock().unwrap() = SearchLimits {
+        beam_width: beam_width.map(|width| width as usize),
+        max_nodes: max_nodes.map_or(default.max_nodes, |nodes| nodes as usize),
+    };
+    // Drop the cached pipeline so it is rebuilt with the new setting
+    *PIPELINE.lock().unwrap() = None;
+}
+
+/// Only draw layouts whose ratio (width / height) lies within the bounds
+///
+/// Leave a bound out to remove it. When no layout of a compound fits, the nearest
+/// layouts are used.
+#[wasm_bindgen]
+pub fn set_ratio_bounds(min_ratio: Option<f64>, max_ratio: Option<f64>) {
+    let default = RenderConfig::default();
+    *RATIO_BOUNDS.lock().unwrap() = (
+        min_ratio.unwrap_or(default.min_ratio),
+        max_ratio.unwrap_or(default.max_ratio),
+    );
+    // Drop the cached pipeline so it is rebuilt with the new setting
+    *PIPELINE.lock().unwrap() = None;
+}
+
+/// Pick the layout of every compound at random among those within the ratio bounds
+///
+/// The same text and `seed` always draw the same layouts.
+#[wasm_bindgen]
+pub fn set_random_layouts(enabled: bool, seed: Option<u32>) {
+    *RANDOM_SEED.lock().unwrap() = enabled.then(|| seed.unwrap_or(0) as u64);
+    // Drop the cached pipeline so it is rebuilt with the new setting
+    *PIPELINE.lock().unwrap() = None;
+}
+
+/// Draw every compound at its own size instead of stretching it to the widest (off by default)
+#[wasm_bindgen]
+pub fn set_ignore_height(enabled: bool) {
+    *IGNORE_HEIGHT.lock().unwrap() = enabled;
+    // Drop the cached pipeline so it is rebuilt with the new setting
+    *PIPELINE.lock().unwrap() = None;
+}
+
+/// Lay out documents on pages of a height, and fill their rows evenly
+///
+/// With a `page_height` in glyphs, rows fill `columns` columns (one by default) and
+/// continue on new pages below. With `fill_lines`, the layouts of the compounds are
+/// chosen with the line breaks so that rows are filled evenly, and paragraphs are
+/// justified.
+#[wasm_bindgen]
+pub fn set_page_layout(page_height: Option<f64>, columns: Option<u32>, fill_lines: bool) {
+    *PAGE_LAYOUT.lock().unwrap() = (
+        page_height,
+        columns.map_or(1, |columns| columns as usize),
+        fill_lines,
+    );
+    // Drop the cached pipeline so it is rebuilt with the new setting
+    *PIPELINE.lock().unwrap() = None;
+}
+
+/// Weigh the criteria that choose the layout of a compound
+///
+/// Takes a JSON object with any of the weights `ratio`, `surface`, `contrast`,
+/// `balance` and `subject_order`; weights left out keep their defaults (only `ratio`
+/// counts by default).
+#[wasm_bindgen]
+pub fn set_layout_weights(weights_json: &str) -> Result<(), JsValue> {
+    let weights: LayoutWeights = serde_json::from_str(weights_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid layout weights: {}", e)))?;
+    *LAYOUT_WEIGHTS.lock().unwrap() = weights;
+    // Drop the cached pipeline so it is rebuilt with the new setting
+    *PIPELINE.lock().unwrap() = None;
+    Ok(())
+}
+
+/// Layout option as listed by `rank_layouts`
+#[derive(Serialize)]
+struct RankedOption {
+    score: f64,
+    criteria: CriterionScores,
+    width: f64,
+    height: f64,
+    ratio: f64,
+    text: String,
+}
+
+/// The `count` best layouts of every compound of the text, as a JSON array
+///
+/// Each compound is an array of options, best first, with their `score`, the score of
+/// every criterion in `criteria`, their `width`, `height` and `ratio`, and the `text`
+/// they read as.
+#[wasm_bindgen]
+pub fn rank_layouts(text: &str, count: usize) -> Result<String, JsValue> {
+    with_pipeline(|pipeline| {
+        let compounds = pipeline
+            .compound_options(text)
+            .map_err(|e| JsValue::from_str(&format!("Parse failed: {}", e)))?;
+        let ranking: Vec<Vec<RankedOption>> = compounds
+            .iter()
+            .map(|options| {
+                pipeline
+                    .rank_layouts(options, count)
+                    .into_iter()
+                    .map(|ranked| RankedOption {
+                        score: ranked.score,
+                        criteria: ranked.criteria,
+                        width: ranked.option.size.width,
+                        height: ranked.option.size.height,
+                        ratio: ranked.option.ratio,
+                        text: ranked.option.to_text(),
+                    })
+                    .collect()
+            })
+            .collect();
+        serde_json::to_string(&ranking)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize ranking: {}", e)))
+    })
+}
+
+/// Render `count` different layouts of a text, as a JSON array of SVG strings
+///
+/// Layouts are drawn at random, the better scored ones more often, and never with the
+/// same arrangement twice, so fewer SVGs come back for short texts. The same text and
+/// `seed` always give the same SVGs. A lower `temperature` draws worse scored layouts
+/// less often; 0 gives the best layouts in order.
+#[wasm_bindgen]
+pub fn sample_layouts_svg(
+    text: &str,
+    count: usize,
+    seed: Option<u32>,
+    temperature: Option<f64>,
+) -> Result<String, JsValue> {
+    let default = LayoutSampling::default();
+    let sampling = LayoutSampling {
+        count,
+        seed: seed.map_or(default.seed, u64::from),
+        temperature: temperature.unwrap_or(default.temperature),
+    };
+    with_pipeline(|pipeline| {
+        let layouts = pipeline
+            .sample_layouts(text, &sampling)
+            .map_err(|e| JsValue::from_str(&format!("Parse failed: {}", e)))?;
+        let svgs = layouts
+            .iter()
+            .map(|layout| {
+                let bytes = pipeline
+                    .render(layout, OutputFormat::Svg)
+                    .map_err(|e| JsValue::from_str(&format!("Rendering failed: {}", e)))?;
+                String::from_utf8(bytes)
+                    .map_err(|e| JsValue::from_str(&format!("Invalid SVG UTF-8: {}", e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        serde_json::to_string(&svgs)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize SVGs: {}", e)))
+    })
+}
+
+/// Check text for common mistakes and phrases that render poorly, as a JSON array
+///
+/// Each entry has the `rule`, its `severity` (`"hint"`, `"warning"` or `"error"`), a
+/// `message`, the `span` it is about and the index of its `sentence`.
+#[wasm_bindgen]
+pub fn lint(text: &str) -> Result<String, JsValue> {
+    with_pipeline(|pipeline| {
+        serde_json::to_string(&pipeline.lint(text))
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize lint issues: {}", e)))
+    })
+}
+
+/// Get how every preposition word in the text is used, as a JSON array
+///
+/// Each entry has the `word`, its `span`, its `use` (`"preposition"` or `"content"`) and
+/// the `rule` that decided it.
+#[wasm_bindgen]
+pub fn preposition_decisions(text: &str) -> Result<String, JsValue> {
+    with_pipeline(|pipeline| {
+        serde_json::to_string(&pipeline.preposition_decisions(text))
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize decisions: {}", e)))
+    })
+}
+
+/// Force the use of the preposition word starting at byte `offset`: "preposition",
+/// "content", or "auto" to let the parser decide again
+#[wasm_bindgen]
+pub fn set_preposition_override(offset: usize, usage: &str) -> Result<(), JsValue> {
+    let usage = match usage {
+        "preposition" => Some(PrepositionUse::Preposition),
+        "content" => Some(PrepositionUse::Content),
+        "auto" => None,
+        _ => {
+            return Err(JsValue::from_str(&format!(
+                "Unknown preposition use: {}",
+                usage
+            )))
+        }
+    };
+    let mut overrides = PREPOSITION_OVERRIDES.lock().unwrap();
+    match usage {
+        Some(usage) => overrides.insert(offset, usage),
+        None => overrides.remove(&offset),
+    };
+    // Drop the cached pipeline so it is rebuilt with the new setting
+    *PIPELINE.lock().unwrap() = None;
+    Ok(())
+}